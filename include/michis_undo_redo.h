@@ -0,0 +1,66 @@
+/*
+ * C interface to michis_undo_redo, enabled by the crate's `ffi` feature.
+ *
+ * This header is written by hand, not generated, and must be kept in sync with `src/ffi.rs`; see the
+ * documentation there for the safety requirements of each function.
+ *
+ * An action is recorded by calling `mur_history_begin_action`, adding its operations with
+ * `mur_history_add_redo` and `mur_history_add_undo`, then applying it with
+ * `mur_history_commit_action`.
+ */
+
+#ifndef MICHIS_UNDO_REDO_H
+#define MICHIS_UNDO_REDO_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* The operation completed successfully. */
+#define MUR_OK 0
+/* There was nothing to undo or redo. */
+#define MUR_NOTHING_TO_DO 1
+/* There is no action which operations can be added to. */
+#define MUR_NO_OPEN_ACTION 2
+/* A pointer argument was null. */
+#define MUR_NULL_POINTER 3
+/* A position was outside of the history. */
+#define MUR_OUT_OF_BOUNDS 4
+/* The history has no room for another action. */
+#define MUR_HISTORY_FULL 5
+/* Later actions depend on the action being moved or removed. */
+#define MUR_HAS_DEPENDENTS 6
+/* Actions could not be reordered past each other. */
+#define MUR_DOES_NOT_COMMUTE 7
+
+/* An opaque handle to an undo-redo history. */
+typedef struct MurHistory MurHistory;
+
+/* Performs an operation on `target`. */
+typedef void (*MurApplyFn)(void *user_data, void *target);
+
+/* Frees an operation's `user_data`, once the operation is no longer needed. */
+typedef void (*MurDropFn)(void *user_data);
+
+MurHistory *mur_history_new(void);
+void mur_history_free(MurHistory *history);
+
+int mur_history_begin_action(MurHistory *history, const char *name);
+int mur_history_add_redo(MurHistory *history, MurApplyFn apply, void *user_data, MurDropFn drop);
+int mur_history_add_undo(MurHistory *history, MurApplyFn apply, void *user_data, MurDropFn drop);
+int mur_history_commit_action(MurHistory *history, void *target);
+
+int mur_history_redo(MurHistory *history, void *target);
+int mur_history_undo(MurHistory *history, void *target);
+
+size_t mur_history_len(const MurHistory *history);
+size_t mur_history_position(const MurHistory *history);
+size_t mur_history_action_name(const MurHistory *history, size_t index, char *buf, size_t buf_len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* MICHIS_UNDO_REDO_H */
@@ -0,0 +1,139 @@
+use alloc::vec::Vec;
+use std::{
+	sync::mpsc::{self, Receiver, Sender},
+	thread::{self, JoinHandle},
+};
+
+use crate::{Action, HistoryCommand, HistoryEvent, HistoryStorage, Operation, UndoRedo};
+
+/// A thread which owns a history and the object it operates on, and performs
+/// [`HistoryCommand`]s sent to it over a channel, one at a time. This gives any number of threads
+/// a way to drive the same history without sharing locks.
+///
+/// The thread stops once every [`Sender`] for its commands has been dropped.
+#[derive(Debug)]
+pub struct HistoryActor<Op, For, S = Vec<Action<Op>>> {
+	commands: Sender<HistoryCommand<Op>>,
+	events: Receiver<HistoryEvent>,
+	thread: JoinHandle<(UndoRedo<Op, S>, For)>,
+}
+
+impl<Op, For, S> HistoryActor<Op, For, S>
+where
+	Op: Operation<For> + Send + 'static,
+	For: Send + 'static,
+	S: HistoryStorage<Op> + Send + 'static,
+{
+	/// Spawns a thread which takes ownership of `history` and `target`.
+	pub fn spawn(mut history: UndoRedo<Op, S>, mut target: For) -> Self {
+		let (commands, command_receiver) = mpsc::channel();
+		let (event_sender, events) = mpsc::channel();
+
+		let thread = thread::spawn(move || {
+			for command in command_receiver {
+				let event = history.update(command, &mut target);
+				// If nobody is listening for events, there's nobody to tell about this one.
+				let _ = event_sender.send(event);
+			}
+			(history, target)
+		});
+
+		Self {
+			commands,
+			events,
+			thread,
+		}
+	}
+}
+
+impl<Op, For, S> HistoryActor<Op, For, S> {
+	/// Returns a sender for commands to this actor. It can be cloned and sent to other threads.
+	pub fn commands(&self) -> &Sender<HistoryCommand<Op>> {
+		&self.commands
+	}
+
+	/// Returns the receiver of events from this actor.
+	pub fn events(&self) -> &Receiver<HistoryEvent> {
+		&self.events
+	}
+
+	/// Stops accepting commands from this handle, waits for the thread to finish any commands sent
+	/// by other senders, then returns the history and the object it operated on.
+	///
+	/// Returns `None` if the thread panicked.
+	pub fn join(self) -> Option<(UndoRedo<Op, S>, For)> {
+		drop(self.commands);
+		self.thread.join().ok()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use alloc::string::String;
+
+	use super::*;
+	use crate::{HistoryState, UndoRedoError};
+
+	#[derive(Debug)]
+	struct Add(i32);
+
+	impl Operation<i32> for Add {
+		fn apply(&self, item: &mut i32) {
+			*item += self.0;
+		}
+	}
+
+	fn add(value: i32) -> Action<Add> {
+		let mut action = Action::default();
+		action
+			.set_name("Add")
+			.add_redo_operation(Add(value))
+			.add_undo_operation(Add(-value));
+		action
+	}
+
+	fn state(position: usize, len: usize) -> HistoryState {
+		HistoryState {
+			position,
+			len,
+			undo_name: (position > 0).then(|| String::from("Add")),
+			redo_name: (position < len).then(|| String::from("Add")),
+		}
+	}
+
+	#[test]
+	fn answers_each_command_in_order() {
+		let actor = HistoryActor::spawn(UndoRedo::new(), 0);
+		let commands = [
+			HistoryCommand::Commit(add(1)),
+			HistoryCommand::Commit(add(2)),
+			HistoryCommand::Undo,
+			HistoryCommand::Query,
+			HistoryCommand::JumpTo(3),
+			HistoryCommand::JumpTo(0),
+			HistoryCommand::Undo,
+			HistoryCommand::Redo,
+		];
+		for command in commands {
+			actor.commands().send(command).unwrap();
+		}
+
+		let events: Vec<_> = actor.events().iter().take(8).collect();
+		assert_eq!(
+			events,
+			[
+				HistoryEvent::Changed(state(1, 1)),
+				HistoryEvent::Changed(state(2, 2)),
+				HistoryEvent::Changed(state(1, 2)),
+				HistoryEvent::State(state(1, 2)),
+				HistoryEvent::Failed(UndoRedoError::OutOfBounds),
+				HistoryEvent::Changed(state(0, 2)),
+				HistoryEvent::Failed(UndoRedoError::NothingToDo),
+				HistoryEvent::Changed(state(1, 2)),
+			]
+		);
+
+		let (history, target) = actor.join().unwrap();
+		assert_eq!((history.position(), target), (1, 1));
+	}
+}
@@ -0,0 +1,298 @@
+use alloc::collections::BTreeMap;
+use core::{error, fmt, hash::Hash};
+use std::{
+	collections::HashMap,
+	fs,
+	io::{ErrorKind, Write},
+	path::{Path, PathBuf},
+};
+
+use crate::{
+	HistoryStorage, PersistError, PersistOperation, PersistScope, UndoRedo, UndoRedoManager,
+	persist::write_atomically,
+};
+
+/// Somewhere a [`UndoRedoManager`] keeps the histories of closed documents, so that they can be
+/// restored when the documents are reopened. See [`UndoRedoManager::close`].
+///
+/// This is implemented for maps from IDs to bytes (which keep histories in memory), and for
+/// [`DirectoryArchive`].
+pub trait HistoryArchive<Id> {
+	/// Stores `bytes`, the saved history of the document `id`, replacing any stored for it before.
+	///
+	/// # Errors
+	/// Returns an error if the history could not be stored.
+	fn store(&mut self, id: &Id, bytes: Vec<u8>) -> Result<(), PersistError>;
+
+	/// Returns the saved history of the document `id`, if one is stored.
+	///
+	/// # Errors
+	/// Returns an error if the history could not be retrieved.
+	fn load(&mut self, id: &Id) -> Result<Option<Vec<u8>>, PersistError>;
+
+	/// Removes the saved history of the document `id`, if one is stored.
+	///
+	/// # Errors
+	/// Returns an error if the history could not be removed.
+	fn remove(&mut self, id: &Id) -> Result<(), PersistError>;
+}
+
+impl<Id: Ord + Clone> HistoryArchive<Id> for BTreeMap<Id, Vec<u8>> {
+	fn store(&mut self, id: &Id, bytes: Vec<u8>) -> Result<(), PersistError> {
+		self.insert(id.clone(), bytes);
+		Ok(())
+	}
+
+	fn load(&mut self, id: &Id) -> Result<Option<Vec<u8>>, PersistError> {
+		Ok(self.get(id).cloned())
+	}
+
+	fn remove(&mut self, id: &Id) -> Result<(), PersistError> {
+		self.remove(id);
+		Ok(())
+	}
+}
+
+impl<Id: Hash + Eq + Clone> HistoryArchive<Id> for HashMap<Id, Vec<u8>> {
+	fn store(&mut self, id: &Id, bytes: Vec<u8>) -> Result<(), PersistError> {
+		self.insert(id.clone(), bytes);
+		Ok(())
+	}
+
+	fn load(&mut self, id: &Id) -> Result<Option<Vec<u8>>, PersistError> {
+		Ok(self.get(id).cloned())
+	}
+
+	fn remove(&mut self, id: &Id) -> Result<(), PersistError> {
+		self.remove(id);
+		Ok(())
+	}
+}
+
+/// A [`HistoryArchive`] which stores each history as a file in a directory, named after the
+/// document's ID (as formatted by `Display`). IDs must therefore be valid file names.
+///
+/// Histories stored here survive the application being restarted.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DirectoryArchive {
+	directory: PathBuf,
+}
+
+impl DirectoryArchive {
+	/// Creates an archive which stores histories in `directory`. The directory must already exist.
+	pub fn new(directory: impl Into<PathBuf>) -> Self {
+		Self {
+			directory: directory.into(),
+		}
+	}
+
+	/// Returns the directory histories are stored in.
+	pub fn directory(&self) -> &Path {
+		&self.directory
+	}
+
+	/// Returns the path of the file the history of the document `id` is stored in.
+	pub fn path_of(&self, id: &impl fmt::Display) -> PathBuf {
+		self.directory.join(format!("{id}.history"))
+	}
+}
+
+impl<Id: fmt::Display> HistoryArchive<Id> for DirectoryArchive {
+	fn store(&mut self, id: &Id, bytes: Vec<u8>) -> Result<(), PersistError> {
+		write_atomically(&self.path_of(id), |writer| Ok(writer.write_all(&bytes)?))
+	}
+
+	fn load(&mut self, id: &Id) -> Result<Option<Vec<u8>>, PersistError> {
+		match fs::read(self.path_of(id)) {
+			Ok(bytes) => Ok(Some(bytes)),
+			Err(error) if error.kind() == ErrorKind::NotFound => Ok(None),
+			Err(error) => Err(error.into()),
+		}
+	}
+
+	fn remove(&mut self, id: &Id) -> Result<(), PersistError> {
+		match fs::remove_file(self.path_of(id)) {
+			Err(error) if error.kind() != ErrorKind::NotFound => Err(error.into()),
+			_ => Ok(()),
+		}
+	}
+}
+
+impl<Id, Op, S> UndoRedoManager<Id, Op, S>
+where
+	Id: Ord + Clone,
+	Op: PersistOperation,
+	S: HistoryStorage<Op> + Default,
+{
+	/// Removes the document `id`, as [`Self::remove`] would, after saving its entire history
+	/// (including its current position and save point) into `archive`. Returns `false` if there is
+	/// no such document.
+	///
+	/// Actions committed along with actions in other documents (see [`Self::commit_compound`])
+	/// are no longer undone and redone with them, even once the document is reopened.
+	///
+	/// # Errors
+	/// Returns the archive's error if the history could not be stored. Nothing is changed in this
+	/// case.
+	pub fn close<A: HistoryArchive<Id>>(
+		&mut self,
+		id: &Id,
+		archive: &mut A,
+	) -> Result<bool, PersistError> {
+		let Some(history) = self.get(id) else {
+			return Ok(false);
+		};
+		archive.store(id, history.encode_history(PersistScope::All))?;
+		self.remove(id);
+		Ok(true)
+	}
+
+	/// Adds the document `id` with the history saved for it in `archive` by [`Self::close`], then
+	/// removes the saved history from `archive`, returning `true`. If no history was saved for it,
+	/// it is added with an empty history instead, and `false` is returned.
+	///
+	/// # Errors
+	/// * Returns `ReopenError::AlreadyOpen` if the document is already in this manager.
+	/// * Returns `ReopenError::Persist` with the archive's error if the history could not be
+	///   retrieved or removed.
+	/// * Returns `ReopenError::Persist` with the same errors as [`UndoRedo::load_from_path`] if
+	///   the saved history is invalid.
+	///
+	/// The document is not added in any of these cases. The saved history is left in `archive`,
+	/// unless removing it was what failed.
+	pub fn reopen<A: HistoryArchive<Id>>(
+		&mut self,
+		id: Id,
+		archive: &mut A,
+	) -> Result<bool, ReopenError<Id>> {
+		if self.get(&id).is_some() {
+			return Err(ReopenError::AlreadyOpen(id));
+		}
+		let Some(bytes) = archive.load(&id)? else {
+			self.insert(id, UndoRedo::default());
+			return Ok(false);
+		};
+		let history = UndoRedo::decode_history(&bytes)?;
+		archive.remove(&id)?;
+		self.insert(id, history);
+		Ok(true)
+	}
+}
+
+/// An error indicating an issue with reopening a document, from
+/// [`UndoRedoManager::reopen`].
+#[derive(Debug)]
+pub enum ReopenError<Id> {
+	/// The document is already open.
+	AlreadyOpen(Id),
+	/// The document's saved history could not be loaded.
+	Persist(PersistError),
+}
+
+impl<Id> From<PersistError> for ReopenError<Id> {
+	fn from(value: PersistError) -> Self {
+		Self::Persist(value)
+	}
+}
+
+impl<Id: fmt::Debug> fmt::Display for ReopenError<Id> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::AlreadyOpen(id) => write!(f, "document {id:?} is already open"),
+			Self::Persist(e) => write!(f, "failed to load the saved history: {e}"),
+		}
+	}
+}
+
+impl<Id: fmt::Debug> error::Error for ReopenError<Id> {
+	fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+		match self {
+			Self::AlreadyOpen(_) => None,
+			Self::Persist(e) => Some(e),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{Action, Operation};
+
+	#[derive(Clone, Debug, PartialEq)]
+	struct Add(i32);
+
+	impl Operation<i32> for Add {
+		fn apply(&self, item: &mut i32) {
+			*item += self.0;
+		}
+	}
+
+	impl PersistOperation for Add {
+		const SCHEMA_VERSION: u32 = 1;
+
+		fn encode(&self, out: &mut Vec<u8>) {
+			out.extend(self.0.to_le_bytes());
+		}
+
+		fn decode(bytes: &[u8]) -> Option<Self> {
+			Some(Self(i32::from_le_bytes(bytes.try_into().ok()?)))
+		}
+	}
+
+	fn manager_with_history() -> (UndoRedoManager<u32, Add>, BTreeMap<u32, i32>) {
+		let mut manager = UndoRedoManager::new();
+		let mut documents = BTreeMap::from([(1, 0)]);
+		manager.insert(1, UndoRedo::new());
+		for value in [1, 2] {
+			let mut action = Action::default();
+			action.add_redo_operation(Add(value));
+			action.add_undo_operation(Add(-value));
+			manager.commit_action(&1, action, &mut documents).unwrap();
+		}
+		manager.get_mut(&1).unwrap().mark_save_point();
+		manager.focus(Some(1));
+		manager.undo(&mut documents).unwrap();
+		(manager, documents)
+	}
+
+	#[test]
+	fn reopen_restores_position_and_save_point() {
+		let (mut manager, _) = manager_with_history();
+		let history = manager.get(&1).unwrap().clone();
+		let mut archive = BTreeMap::new();
+
+		assert!(manager.close(&1, &mut archive).unwrap());
+		assert!(manager.get(&1).is_none());
+		assert!(manager.reopen(1, &mut archive).unwrap());
+		assert_eq!(manager.get(&1), Some(&history));
+		assert!(archive.is_empty());
+	}
+
+	#[test]
+	fn reopen_keeps_invalid_history_in_archive() {
+		let (mut manager, _) = manager_with_history();
+		let mut archive = BTreeMap::new();
+		manager.close(&1, &mut archive).unwrap();
+		archive.get_mut(&1).unwrap().truncate(3);
+
+		let result = manager.reopen(1, &mut archive);
+		assert!(matches!(
+			result,
+			Err(ReopenError::Persist(PersistError::Corrupt))
+		));
+		assert!(manager.get(&1).is_none());
+		assert_eq!(archive[&1].len(), 3);
+	}
+
+	#[test]
+	fn reopen_refuses_open_document() {
+		let (mut manager, _) = manager_with_history();
+		let mut archive = BTreeMap::from([(1, Vec::new())]);
+
+		assert!(matches!(
+			manager.reopen(1, &mut archive),
+			Err(ReopenError::AlreadyOpen(1))
+		));
+		assert_eq!(manager.get(&1).unwrap().position(), 1);
+	}
+}
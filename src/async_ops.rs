@@ -0,0 +1,402 @@
+use core::future::Future;
+
+use crate::{Action, HistoryStorage, UndoRedo, UndoRedoError};
+
+/// Like [`Operation`](crate::Operation), but for objects whose changes must be awaited (such as
+/// rows in a database, or resources on a remote server).
+///
+/// Note that the future returned by [`Self::apply`] is not required to be `Send`. If you need to
+/// undo or redo from a multi-threaded runtime, ensure your implementation's future is `Send`.
+pub trait AsyncOperation<For> {
+	fn apply(&self, item: &mut For) -> impl Future<Output = ()>;
+}
+
+impl<Op> Action<Op> {
+	/// Like [`Self::apply`], but for [`AsyncOperation`]s. Each operation is awaited before the next
+	/// begins.
+	pub async fn apply_async<For>(&self, apply_to: &mut For)
+	where
+		Op: AsyncOperation<For>,
+	{
+		for op in self.apply_ops() {
+			op.apply(apply_to).await;
+		}
+	}
+
+	/// Like [`Self::revert`], but for [`AsyncOperation`]s. Each operation is awaited before the next
+	/// begins.
+	pub async fn revert_async<For>(&self, apply_to: &mut For)
+	where
+		Op: AsyncOperation<For>,
+	{
+		for op in self.revert_ops() {
+			op.apply(apply_to).await;
+		}
+	}
+}
+
+/// How far an async undo or redo got through an action before it was interrupted.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct PartialAction {
+	/// Whether the action is being reverted, rather than applied. The action is the one before the
+	/// current position if so, and the one after it otherwise.
+	undoing: bool,
+	/// How many of the action's operations have been performed.
+	ops_done: usize,
+}
+
+impl<Op, S: HistoryStorage<Op>> UndoRedo<Op, S> {
+	/// Like [`Self::redo`], but for [`AsyncOperation`]s.
+	///
+	/// Progress is recorded in the history after every operation, so the returned future can be
+	/// dropped at any point (such as by a timeout). If it is, the history is
+	/// [interrupted](Self::is_interrupted), and calling this again finishes the same action rather
+	/// than applying another. This assumes each operation's own future is cancellation-safe.
+	///
+	/// If an async undo was interrupted instead, it is finished before the action is applied again.
+	///
+	/// # Errors
+	/// See [`Self::redo`].
+	pub async fn redo_async<For>(&mut self, apply_to: &mut For) -> Result<(), UndoRedoError>
+	where
+		Op: AsyncOperation<For>,
+	{
+		let position = match self.partial {
+			Some(partial) if partial.undoing => self.tapehead,
+			_ => self.tapehead + 1,
+		};
+		if position > self.actions.len() {
+			return Err(UndoRedoError::NothingToDo);
+		}
+		self.run_async(position, apply_to).await;
+		Ok(())
+	}
+
+	/// Like [`Self::undo`], but for [`AsyncOperation`]s.
+	///
+	/// The returned future can be dropped at any point, as with [`Self::redo_async`]. Calling this
+	/// again finishes the same action rather than reverting another.
+	///
+	/// If an async redo was interrupted instead, it is finished before the action is reverted
+	/// again, as an action can't be reverted part-way through being applied.
+	///
+	/// # Errors
+	/// See [`Self::undo`].
+	pub async fn undo_async<For>(&mut self, apply_to: &mut For) -> Result<(), UndoRedoError>
+	where
+		Op: AsyncOperation<For>,
+	{
+		let position = match self.partial {
+			Some(partial) if !partial.undoing => self.tapehead,
+			_ => self
+				.tapehead
+				.checked_sub(1)
+				.ok_or(UndoRedoError::NothingToDo)?,
+		};
+		self.run_async(position, apply_to).await;
+		Ok(())
+	}
+
+	/// Like [`Self::jump_to`], but for [`AsyncOperation`]s.
+	///
+	/// The returned future can be dropped at any point, as with [`Self::redo_async`]. Calling this
+	/// again (or any other async undo, redo or jump) first finishes the action it was interrupted
+	/// part-way through.
+	///
+	/// # Errors
+	/// See [`Self::jump_to`].
+	pub async fn jump_to_async<For>(
+		&mut self,
+		position: usize,
+		apply_to: &mut For,
+	) -> Result<(), UndoRedoError>
+	where
+		Op: AsyncOperation<For>,
+	{
+		self.resumable_jump_to(position)?.run(apply_to).await;
+		Ok(())
+	}
+
+	/// Like [`Self::jump_to_async`], but undoes every applied action.
+	pub async fn undo_all_async<For>(&mut self, apply_to: &mut For)
+	where
+		Op: AsyncOperation<For>,
+	{
+		self.run_async(0, apply_to).await;
+	}
+
+	/// Prepares to undo or redo actions until the current position in history is `position`, as
+	/// [`Self::jump_to_async`] does, but returns an [`AsyncJump`] whose progress can be inspected.
+	///
+	/// # Errors
+	/// Returns `UndoRedoError::OutOfBounds` if `position` is past the end of undo-redo history.
+	pub fn resumable_jump_to(
+		&mut self,
+		position: usize,
+	) -> Result<AsyncJump<'_, Op, S>, UndoRedoError> {
+		if position > self.actions.len() {
+			return Err(UndoRedoError::OutOfBounds);
+		}
+		Ok(AsyncJump {
+			history: self,
+			position,
+		})
+	}
+
+	/// Like [`Self::resumable_jump_to`], but undoes every applied action.
+	pub fn resumable_undo_all(&mut self) -> AsyncJump<'_, Op, S> {
+		AsyncJump {
+			history: self,
+			position: 0,
+		}
+	}
+
+	/// Returns `true` if an async undo, redo or jump was interrupted part-way through an action,
+	/// leaving the object with only some of that action's operations performed.
+	///
+	/// The next async undo, redo or jump finishes the action first. The other methods of this
+	/// history don't know about the interrupted action, so one of those should be awaited before
+	/// using them.
+	pub fn is_interrupted(&self) -> bool {
+		self.partial.is_some()
+	}
+
+	/// Undoes or redoes actions until the current position in history is `position`, first
+	/// finishing any interrupted action.
+	async fn run_async<For>(&mut self, position: usize, apply_to: &mut For)
+	where
+		Op: AsyncOperation<For>,
+	{
+		while self.partial.is_some() || self.tapehead != position {
+			let undoing = self
+				.partial
+				.map_or(self.tapehead > position, |partial| partial.undoing);
+			let index = if undoing {
+				self.tapehead.checked_sub(1)
+			} else {
+				Some(self.tapehead)
+			};
+			let Some(action) = index.and_then(|index| self.actions.get(index)) else {
+				return;
+			};
+			let ops = if undoing {
+				action.revert_ops()
+			} else {
+				action.apply_ops()
+			};
+
+			let partial = self.partial.get_or_insert(PartialAction {
+				undoing,
+				ops_done: 0,
+			});
+			while let Some(op) = ops.get(partial.ops_done) {
+				op.apply(apply_to).await;
+				partial.ops_done += 1;
+			}
+			self.partial = None;
+			self.tapehead = if undoing {
+				self.tapehead - 1
+			} else {
+				self.tapehead + 1
+			};
+		}
+	}
+}
+
+/// An in-progress jump through a history of [`AsyncOperation`]s, created by
+/// [`UndoRedo::resumable_jump_to`].
+///
+/// Progress is recorded in the history after every operation, so the future returned by
+/// [`Self::run`] can be dropped at any point (such as by a timeout). Calling [`Self::run`] again,
+/// or any async undo, redo or jump on the history (even after this is dropped), continues from the
+/// next operation. This assumes each operation's own future is cancellation-safe.
+#[derive(Debug)]
+pub struct AsyncJump<'h, Op, S> {
+	history: &'h mut UndoRedo<Op, S>,
+	position: usize,
+}
+
+impl<Op, S: HistoryStorage<Op>> AsyncJump<'_, Op, S> {
+	/// Undoes or redoes actions until the jump is finished.
+	pub async fn run<For>(&mut self, apply_to: &mut For)
+	where
+		Op: AsyncOperation<For>,
+	{
+		self.history.run_async(self.position, apply_to).await;
+	}
+
+	/// Returns `true` if the history has reached the position being jumped to.
+	pub fn is_finished(&self) -> bool {
+		self.history.tapehead == self.position && !self.history.is_interrupted()
+	}
+
+	/// Returns the history's current position. See [`UndoRedo::position`].
+	pub fn position(&self) -> usize {
+		self.history.tapehead
+	}
+
+	/// Returns how many operations of the next action to be applied or reverted have already been
+	/// performed.
+	pub fn ops_done(&self) -> usize {
+		self.history.partial.map_or(0, |partial| partial.ops_done)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use alloc::{vec, vec::Vec};
+	use core::{
+		pin::pin,
+		task::{Context, Poll, Waker},
+	};
+
+	use super::*;
+
+	/// A future which is pending the first time it is polled.
+	struct Yield(bool);
+
+	impl Future for Yield {
+		type Output = ();
+
+		fn poll(mut self: core::pin::Pin<&mut Self>, _: &mut Context<'_>) -> Poll<()> {
+			if self.0 {
+				Poll::Ready(())
+			} else {
+				self.0 = true;
+				Poll::Pending
+			}
+		}
+	}
+
+	#[derive(Debug)]
+	enum Edit {
+		Push(i32),
+		Pop,
+	}
+
+	impl AsyncOperation<Vec<i32>> for Edit {
+		async fn apply(&self, item: &mut Vec<i32>) {
+			Yield(false).await;
+			match self {
+				Self::Push(value) => item.push(*value),
+				Self::Pop => drop(item.pop()),
+			}
+		}
+	}
+
+	/// Polls `future` up to `polls` times, dropping it if it hasn't finished by then.
+	fn poll_times<F: Future>(future: F, polls: usize) -> Option<F::Output> {
+		let mut future = pin!(future);
+		let mut cx = Context::from_waker(Waker::noop());
+		(0..polls).find_map(|_| match future.as_mut().poll(&mut cx) {
+			Poll::Ready(output) => Some(output),
+			Poll::Pending => None,
+		})
+	}
+
+	fn block_on<F: Future>(future: F) -> F::Output {
+		poll_times(future, usize::MAX).expect("the future should finish")
+	}
+
+	/// Returns a history of two unapplied actions, pushing 1 then 2, and then 3.
+	fn history() -> UndoRedo<Edit> {
+		let mut first = Action::default();
+		first
+			.add_redo_operation(Edit::Push(1))
+			.add_redo_operation(Edit::Push(2));
+		first
+			.add_undo_operation(Edit::Pop)
+			.add_undo_operation(Edit::Pop);
+		let mut second = Action::default();
+		second.add_redo_operation(Edit::Push(3));
+		second.add_undo_operation(Edit::Pop);
+		UndoRedo::with_empty_storage(vec![first, second])
+	}
+
+	#[test]
+	fn undoes_and_redoes_in_order() {
+		let mut history = history();
+		let mut target = Vec::new();
+
+		block_on(history.redo_async(&mut target)).unwrap();
+		block_on(history.redo_async(&mut target)).unwrap();
+		assert_eq!(target, [1, 2, 3]);
+		assert!(matches!(
+			block_on(history.redo_async(&mut target)),
+			Err(UndoRedoError::NothingToDo)
+		));
+
+		block_on(history.undo_async(&mut target)).unwrap();
+		assert_eq!((history.position(), &*target), (1, &[1, 2][..]));
+		block_on(history.undo_async(&mut target)).unwrap();
+		assert_eq!((history.position(), &*target), (0, &[][..]));
+		assert!(matches!(
+			block_on(history.undo_async(&mut target)),
+			Err(UndoRedoError::NothingToDo)
+		));
+	}
+
+	#[test]
+	fn resumes_dropped_redo() {
+		let mut history = history();
+		let mut target = Vec::new();
+
+		// The first operation finishes on the second poll.
+		assert_eq!(poll_times(history.redo_async(&mut target), 2), None);
+		assert_eq!(target, [1]);
+		assert!(history.is_interrupted());
+		assert_eq!(history.position(), 0);
+
+		// Redoing again finishes the same action, rather than starting the next.
+		block_on(history.redo_async(&mut target)).unwrap();
+		assert_eq!(target, [1, 2]);
+		assert_eq!(history.position(), 1);
+		assert!(!history.is_interrupted());
+	}
+
+	#[test]
+	fn undo_finishes_dropped_redo_first() {
+		let mut history = history();
+		let mut target = Vec::new();
+
+		assert_eq!(poll_times(history.redo_async(&mut target), 2), None);
+		block_on(history.undo_async(&mut target)).unwrap();
+		assert_eq!((history.position(), &*target), (0, &[][..]));
+
+		block_on(history.jump_to_async(1, &mut target)).unwrap();
+		assert_eq!(poll_times(history.undo_async(&mut target), 2), None);
+		assert_eq!(target, [1]);
+		// Redoing finishes the undo, then applies the action again.
+		block_on(history.redo_async(&mut target)).unwrap();
+		assert_eq!((history.position(), &*target), (1, &[1, 2][..]));
+	}
+
+	#[test]
+	fn resumes_dropped_jump() {
+		let mut history = history();
+		let mut target = Vec::new();
+
+		{
+			let mut jump = history.resumable_jump_to(2).unwrap();
+			assert_eq!(poll_times(jump.run(&mut target), 2), None);
+			assert_eq!((jump.position(), jump.ops_done()), (0, 1));
+			assert!(!jump.is_finished());
+		}
+
+		// The progress is kept by the history, so a new jump continues from it.
+		let mut jump = history.resumable_undo_all();
+		assert_eq!(jump.ops_done(), 1);
+		block_on(jump.run(&mut target));
+		assert!(jump.is_finished());
+		assert_eq!((history.position(), &*target), (0, &[][..]));
+
+		assert_eq!(poll_times(history.jump_to_async(2, &mut target), 3), None);
+		assert_eq!(target, [1, 2]);
+		block_on(history.jump_to_async(2, &mut target)).unwrap();
+		assert_eq!((history.position(), &*target), (2, &[1, 2, 3][..]));
+		assert_eq!(
+			block_on(history.jump_to_async(3, &mut target)),
+			Err(UndoRedoError::OutOfBounds)
+		);
+	}
+}
@@ -0,0 +1,93 @@
+use alloc::vec::Vec;
+
+use crate::{Action, HistoryStorage, UndoRedo};
+
+/// An operation which works out how to revert itself while it is applied, from whatever it
+/// changes (such as "delete selection" remembering the deleted data).
+///
+/// This avoids having to find (and usually clone) everything needed to revert an operation before
+/// applying it. See [`UndoRedo::commit_capturing`].
+pub trait CaptureOperation<For>: Sized {
+	/// Applies this operation to `item`, returning an operation which reverts it.
+	fn apply_capturing(&self, item: &mut For) -> Self;
+}
+
+impl<Op, S: HistoryStorage<Op>> UndoRedo<Op, S> {
+	/// Applies each of `ops` to `apply_to` in order, then adds an action containing them at the
+	/// current point in history, as [`Self::create_action`] would. The action's undo operations
+	/// are the ones captured while applying (see [`CaptureOperation::apply_capturing`]), in reverse
+	/// order.
+	///
+	/// The action is returned so that it can be named.
+	///
+	/// # Panics
+	/// See [`Self::create_action`].
+	pub fn commit_capturing<For>(
+		&mut self,
+		ops: impl IntoIterator<Item = Op>,
+		apply_to: &mut For,
+	) -> &mut Action<Op>
+	where
+		Op: CaptureOperation<For>,
+	{
+		let mut action = Action::default();
+		let mut inverses = Vec::new();
+		for op in ops {
+			inverses.push(op.apply_capturing(apply_to));
+			action.add_redo_operation(op);
+		}
+		for inverse in inverses.into_iter().rev() {
+			action.add_undo_operation(inverse);
+		}
+
+		self.push_applied(action)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use alloc::string::String;
+
+	use super::*;
+	use crate::Operation;
+
+	#[derive(Debug, PartialEq)]
+	enum Edit {
+		Push(char),
+		Pop,
+	}
+
+	impl Operation<String> for Edit {
+		fn apply(&self, item: &mut String) {
+			let _ = self.apply_capturing(item);
+		}
+	}
+
+	impl CaptureOperation<String> for Edit {
+		fn apply_capturing(&self, item: &mut String) -> Self {
+			match self {
+				Self::Push(c) => {
+					item.push(*c);
+					Self::Pop
+				}
+				Self::Pop => Self::Push(item.pop().expect("text should not be empty")),
+			}
+		}
+	}
+
+	#[test]
+	fn reverts_captured_operations_in_reverse() {
+		let mut history = UndoRedo::new();
+		let mut text = String::from("x");
+
+		let action =
+			history.commit_capturing([Edit::Pop, Edit::Push('a'), Edit::Push('b')], &mut text);
+		assert_eq!(action.revert_ops(), [Edit::Pop, Edit::Pop, Edit::Push('x')]);
+		assert_eq!(text, "ab");
+
+		history.undo(&mut text).unwrap();
+		assert_eq!(text, "x");
+		history.redo(&mut text).unwrap();
+		assert_eq!(text, "ab");
+	}
+}
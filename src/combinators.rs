@@ -0,0 +1,156 @@
+use alloc::vec::Vec;
+
+use crate::{InvertibleOperation, Operation};
+
+/// An operation which applies each of a list of operations, in order.
+///
+/// This lets a group of operations be stored and reused as one, without adding a variant for it
+/// to an enum of operations.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Sequence<Op>(pub Vec<Op>);
+
+impl<For, Op: Operation<For>> Operation<For> for Sequence<Op> {
+	fn apply(&self, item: &mut For) {
+		Op::apply_batch(&self.0, item);
+	}
+}
+
+/// The inverse applies the inverse of each operation, in reverse order.
+impl<For, Op: InvertibleOperation<For>> InvertibleOperation<For> for Sequence<Op> {
+	fn inverse(&self) -> Self {
+		Self(self.0.iter().rev().map(Op::inverse).collect())
+	}
+}
+
+/// A condition on `For`, checked by a [`Conditional`] operation.
+pub trait Condition<For> {
+	/// Returns `true` if the condition holds for `item`.
+	fn holds(&self, item: &For) -> bool;
+}
+
+impl<For> Condition<For> for fn(&For) -> bool {
+	fn holds(&self, item: &For) -> bool {
+		self(item)
+	}
+}
+
+/// An operation which applies `op` only if `condition` holds for the object when it is applied.
+///
+/// As whether the condition holds may differ between applying and reverting, this has no inverse.
+/// Its undo operation should usually be another `Conditional`, with a condition that holds
+/// exactly when `op` was applied.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Conditional<C, Op> {
+	/// The condition to check.
+	pub condition: C,
+	/// The operation to apply if the condition holds.
+	pub op: Op,
+}
+
+impl<For, C: Condition<For>, Op: Operation<For>> Operation<For> for Conditional<C, Op> {
+	fn apply(&self, item: &mut For) {
+		if self.condition.holds(item) {
+			self.op.apply(item);
+		}
+	}
+}
+
+/// An operation which applies `op` a number of times in a row.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Repeat<Op> {
+	/// How many times to apply `op`.
+	pub count: usize,
+	/// The operation to apply.
+	pub op: Op,
+}
+
+impl<For, Op: Operation<For>> Operation<For> for Repeat<Op> {
+	fn apply(&self, item: &mut For) {
+		for _ in 0..self.count {
+			self.op.apply(item);
+		}
+	}
+}
+
+/// The inverse applies the inverse of `op` the same number of times.
+impl<For, Op: InvertibleOperation<For>> InvertibleOperation<For> for Repeat<Op> {
+	fn inverse(&self) -> Self {
+		Self {
+			count: self.count,
+			op: self.op.inverse(),
+		}
+	}
+}
+
+#[cfg(feature = "persist")]
+mod persist {
+	use alloc::vec::Vec;
+
+	use super::{Conditional, Repeat, Sequence};
+	use crate::{
+		PersistOperation,
+		persist::{Input, write_bytes, write_len, write_ops},
+	};
+
+	/// Encodes `op` on its own, so that it can be written with [`write_bytes`].
+	fn encoded<Op: PersistOperation>(op: &Op) -> Vec<u8> {
+		let mut out = Vec::new();
+		op.encode(&mut out);
+		out
+	}
+
+	/// Decodes an operation written with [`write_bytes`] from `input`.
+	fn decode_from<Op: PersistOperation>(input: &mut Input<'_>) -> Option<Op> {
+		Op::decode(input.bytes().ok()?)
+	}
+
+	impl<Op: PersistOperation> PersistOperation for Sequence<Op> {
+		const SCHEMA_VERSION: u32 = Op::SCHEMA_VERSION;
+
+		fn encode(&self, out: &mut Vec<u8>) {
+			write_ops(out, &self.0);
+		}
+
+		fn decode(bytes: &[u8]) -> Option<Self> {
+			let mut input = Input(bytes);
+			let ops = input.ops().ok()?;
+			input.0.is_empty().then_some(Self(ops))
+		}
+	}
+
+	/// The schema version combines those of the condition and the operation, so that a change to
+	/// either is detected.
+	impl<C: PersistOperation, Op: PersistOperation> PersistOperation for Conditional<C, Op> {
+		const SCHEMA_VERSION: u32 = C::SCHEMA_VERSION
+			.wrapping_mul(0x0100_0193)
+			.wrapping_add(Op::SCHEMA_VERSION);
+
+		fn encode(&self, out: &mut Vec<u8>) {
+			write_bytes(out, &encoded(&self.condition));
+			write_bytes(out, &encoded(&self.op));
+		}
+
+		fn decode(bytes: &[u8]) -> Option<Self> {
+			let mut input = Input(bytes);
+			let condition = decode_from(&mut input)?;
+			let op = decode_from(&mut input)?;
+			input.0.is_empty().then_some(Self { condition, op })
+		}
+	}
+
+	impl<Op: PersistOperation> PersistOperation for Repeat<Op> {
+		const SCHEMA_VERSION: u32 = Op::SCHEMA_VERSION;
+
+		fn encode(&self, out: &mut Vec<u8>) {
+			write_len(out, self.count);
+			write_bytes(out, &encoded(&self.op));
+		}
+
+		fn decode(bytes: &[u8]) -> Option<Self> {
+			let mut input = Input(bytes);
+			let count = input.len().ok()?;
+			let op = decode_from(&mut input)?;
+			input.0.is_empty().then_some(Self { count, op })
+		}
+	}
+}
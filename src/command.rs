@@ -0,0 +1,113 @@
+use alloc::{format, string::String};
+
+use crate::{Action, HistoryStorage, Operation, UndoRedo, UndoRedoError};
+
+/// A command performed with [`UndoRedo::update`], or sent to a `HistoryActor` (with the `std`
+/// feature) to be performed on its thread.
+///
+/// In Elm-style applications, this can be used as (or wrapped in) a message type.
+#[derive(Clone, Debug)]
+pub enum HistoryCommand<Op> {
+	/// Performs [`UndoRedo::commit_action`].
+	Commit(Action<Op>),
+	/// Performs [`UndoRedo::undo`].
+	Undo,
+	/// Performs [`UndoRedo::redo`].
+	Redo,
+	/// Performs [`UndoRedo::jump_to`].
+	JumpTo(usize),
+	/// Requests the current [`HistoryState`], without changing anything.
+	Query,
+}
+
+/// An event returned by [`UndoRedo::update`] (or sent by a `HistoryActor`) in response to a
+/// [`HistoryCommand`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HistoryEvent {
+	/// A command changed the history, which is now in the given state.
+	Changed(HistoryState),
+	/// A command failed, and nothing was changed.
+	Failed(UndoRedoError),
+	/// The state requested by [`HistoryCommand::Query`].
+	State(HistoryState),
+}
+
+/// A summary of a history, for displaying to the user.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub struct HistoryState {
+	/// See [`UndoRedo::position`].
+	pub position: usize,
+	/// The number of actions in the history.
+	pub len: usize,
+	/// The name of the action that would be reverted by undoing, if any.
+	pub undo_name: Option<String>,
+	/// The name of the action that would be applied by redoing, if any.
+	pub redo_name: Option<String>,
+}
+
+impl HistoryState {
+	/// Returns a label for an "undo" menu item or button, such as `Undo Rename`, or just `Undo` if
+	/// the action that would be reverted has no name.
+	pub fn undo_label(&self) -> String {
+		label("Undo", self.undo_name.as_deref())
+	}
+
+	/// Returns a label for a "redo" menu item or button, such as `Redo Rename`, or just `Redo` if
+	/// the action that would be applied has no name.
+	pub fn redo_label(&self) -> String {
+		label("Redo", self.redo_name.as_deref())
+	}
+}
+
+/// Joins `verb` and `name` into a label, if there is a name.
+fn label(verb: &str, name: Option<&str>) -> String {
+	match name {
+		Some(name) => format!("{verb} {name}"),
+		None => String::from(verb),
+	}
+}
+
+impl<Op, S: HistoryStorage<Op>> UndoRedo<Op, S> {
+	/// Returns a summary of this history's current state.
+	pub fn state(&self) -> HistoryState {
+		HistoryState {
+			position: self.position(),
+			len: self.actions.len(),
+			undo_name: self
+				.undo_action()
+				.and_then(Action::get_name)
+				.map(String::from),
+			redo_name: self
+				.redo_action()
+				.and_then(Action::get_name)
+				.map(String::from),
+		}
+	}
+}
+
+impl<Op, S: HistoryStorage<Op>> UndoRedo<Op, S> {
+	/// Performs `command` on this history and `target`, returning the resulting event, as a
+	/// `HistoryActor` would.
+	///
+	/// This suits the update function of an Elm-style application, where the application's model
+	/// holds both the history and the object it operates on.
+	pub fn update<For>(&mut self, command: HistoryCommand<Op>, target: &mut For) -> HistoryEvent
+	where
+		Op: Operation<For>,
+	{
+		let result = match command {
+			HistoryCommand::Commit(action) => {
+				self.commit_action(action, target);
+				Ok(())
+			}
+			HistoryCommand::Undo => self.undo(target),
+			HistoryCommand::Redo => self.redo(target),
+			HistoryCommand::JumpTo(position) => self.jump_to(position, target),
+			HistoryCommand::Query => return HistoryEvent::State(self.state()),
+		};
+		match result {
+			Ok(()) => HistoryEvent::Changed(self.state()),
+			Err(e) => HistoryEvent::Failed(e),
+		}
+	}
+}
@@ -0,0 +1,144 @@
+use alloc::collections::BTreeSet;
+use core::fmt;
+use std::{
+	io::{self, Write},
+	time::UNIX_EPOCH,
+};
+
+use crate::{HistoryStorage, UndoRedo, UndoTree};
+
+impl<Op: fmt::Debug, S: HistoryStorage<Op>> UndoRedo<Op, S> {
+	/// Writes the list of actions to `writer` as [JSON Lines](https://jsonlines.org/), with one
+	/// JSON object per action, in order from oldest to newest.
+	///
+	/// Each object has the following fields:
+	///
+	/// * `index`: The action's position in the list of actions.
+	/// * `name`: The action's name, or `null` if it has none.
+	/// * `applied`: Whether the action is currently applied.
+	/// * `skipped`: Whether the action is skipped (see [`crate::Action::set_skipped`]).
+	/// * `redo`, `undo`: The action's operations, each formatted using its `Debug` implementation.
+	///
+	/// # Errors
+	/// Returns an error if writing to `writer` fails.
+	pub fn write_json_lines(&self, mut writer: impl Write) -> io::Result<()> {
+		for (index, action) in self.iter_actions().enumerate() {
+			write!(writer, "{{\"index\":{index},\"name\":")?;
+			match &action.name {
+				Some(name) => write_json_string(&mut writer, name)?,
+				None => write!(writer, "null")?,
+			}
+			write!(writer, ",\"applied\":{}", index < self.tapehead)?;
+			write!(writer, ",\"skipped\":{}", action.skipped)?;
+			write!(writer, ",\"redo\":")?;
+			write_json_ops(&mut writer, action.apply_ops.as_slice())?;
+			write!(writer, ",\"undo\":")?;
+			write_json_ops(&mut writer, action.revert_ops.as_slice())?;
+			writeln!(writer, "}}")?;
+		}
+		Ok(())
+	}
+}
+
+impl<Op> UndoTree<Op> {
+	/// Writes the tree to `writer` as a [Graphviz](https://graphviz.org/) DOT graph, for debugging
+	/// or visualizing branches.
+	///
+	/// Each node is labeled with its action's name (or its ID, if it has none), the name of the
+	/// branch starting at it (if any), and when it was created, in seconds since the Unix epoch.
+	/// Nodes along the path from the root to the current node are filled in, and the current node
+	/// itself is drawn with a double outline. Edges leading to the child each node would redo are
+	/// bold.
+	///
+	/// # Errors
+	/// Returns an error if writing to `writer` fails.
+	pub fn write_dot(&self, mut writer: impl Write) -> io::Result<()> {
+		let mut current_path = BTreeSet::new();
+		let mut next = self.current();
+		while let Some(id) = next {
+			current_path.insert(id);
+			next = self.get(id).and_then(|node| node.parent());
+		}
+
+		writeln!(writer, "digraph undo_tree {{")?;
+		writeln!(writer, "\tnode [shape=box];")?;
+		writeln!(writer, "\troot [label=\"root\", style=filled];")?;
+		for (id, node) in self.iter() {
+			let mut label = match node.action().get_name() {
+				Some(name) => name.to_owned(),
+				None => format!("#{}", id.get()),
+			};
+			if let Some(branch_name) = node.branch_name() {
+				label.push_str(&format!("\n[{branch_name}]"));
+			}
+			if let Ok(created) = node.created().duration_since(UNIX_EPOCH) {
+				label.push_str(&format!("\n@{}", created.as_secs()));
+			}
+
+			write!(writer, "\tn{} [label=", id.get())?;
+			write_dot_string(&mut writer, &label)?;
+			if current_path.contains(&id) {
+				write!(writer, ", style=filled")?;
+			}
+			if self.current() == Some(id) {
+				write!(writer, ", peripheries=2")?;
+			}
+			writeln!(writer, "];")?;
+		}
+
+		let parents = core::iter::once(None).chain(self.iter().map(|(id, _)| Some(id)));
+		for parent in parents {
+			for &child in self.children(parent) {
+				match parent {
+					Some(parent) => write!(writer, "\tn{} -> n{}", parent.get(), child.get())?,
+					None => write!(writer, "\troot -> n{}", child.get())?,
+				}
+				if self.redo_child(parent) == Some(child) {
+					write!(writer, " [style=bold]")?;
+				}
+				writeln!(writer, ";")?;
+			}
+		}
+		writeln!(writer, "}}")
+	}
+}
+
+fn write_json_ops<Op: fmt::Debug>(writer: &mut impl Write, ops: &[Op]) -> io::Result<()> {
+	write!(writer, "[")?;
+	for (i, op) in ops.iter().enumerate() {
+		if i > 0 {
+			write!(writer, ",")?;
+		}
+		write_json_string(writer, &format!("{op:?}"))?;
+	}
+	write!(writer, "]")
+}
+
+fn write_json_string(writer: &mut impl Write, s: &str) -> io::Result<()> {
+	write!(writer, "\"")?;
+	for c in s.chars() {
+		match c {
+			'"' => write!(writer, "\\\"")?,
+			'\\' => write!(writer, "\\\\")?,
+			'\n' => write!(writer, "\\n")?,
+			'\r' => write!(writer, "\\r")?,
+			'\t' => write!(writer, "\\t")?,
+			c if c.is_control() => write!(writer, "\\u{:04x}", u32::from(c))?,
+			c => write!(writer, "{c}")?,
+		}
+	}
+	write!(writer, "\"")
+}
+
+fn write_dot_string(writer: &mut impl Write, s: &str) -> io::Result<()> {
+	write!(writer, "\"")?;
+	for c in s.chars() {
+		match c {
+			'"' => write!(writer, "\\\"")?,
+			'\\' => write!(writer, "\\\\")?,
+			'\n' => write!(writer, "\\n")?,
+			c => write!(writer, "{c}")?,
+		}
+	}
+	write!(writer, "\"")
+}
@@ -0,0 +1,501 @@
+//! A C interface to [`UndoRedo`], for embedding this crate in applications written in other
+//! languages. A matching C header is provided at `include/michis_undo_redo.h`.
+//!
+//! Operations are provided by the host application as callbacks, which are passed an opaque
+//! pointer to the object being operated on.
+
+use alloc::boxed::Box;
+use core::{
+	ffi::{CStr, c_char, c_int, c_void},
+	ptr,
+};
+
+use crate::{Operation, UndoRedo, UndoRedoError};
+
+/// A callback which performs an operation. It is passed the `user_data` given alongside it, and the
+/// `target` passed to the function that caused the operation to be performed.
+pub type MurApplyFn = unsafe extern "C" fn(user_data: *mut c_void, target: *mut c_void);
+
+/// A callback which frees an operation's `user_data`, once the operation is no longer needed.
+pub type MurDropFn = unsafe extern "C" fn(user_data: *mut c_void);
+
+/// The operation completed successfully.
+pub const MUR_OK: c_int = 0;
+/// There was nothing to undo or redo.
+pub const MUR_NOTHING_TO_DO: c_int = 1;
+/// There is no action which operations can be added to.
+pub const MUR_NO_OPEN_ACTION: c_int = 2;
+/// A pointer argument was null.
+pub const MUR_NULL_POINTER: c_int = 3;
+/// A position was outside of the history.
+pub const MUR_OUT_OF_BOUNDS: c_int = 4;
+/// The history has no room for another action.
+pub const MUR_HISTORY_FULL: c_int = 5;
+/// Later actions depend on the action being moved or removed.
+pub const MUR_HAS_DEPENDENTS: c_int = 6;
+/// Actions could not be reordered past each other.
+pub const MUR_DOES_NOT_COMMUTE: c_int = 7;
+
+/// The opaque handle to a history used by the C interface.
+pub type MurHistory = UndoRedo<FfiOperation>;
+
+/// An operation provided over the C interface.
+#[derive(Debug)]
+pub struct FfiOperation {
+	apply: MurApplyFn,
+	drop: Option<MurDropFn>,
+	user_data: *mut c_void,
+}
+
+/// The object being operated on, as passed over the C interface.
+#[derive(Debug)]
+pub struct FfiTarget(*mut c_void);
+
+impl Operation<FfiTarget> for FfiOperation {
+	fn apply(&self, item: &mut FfiTarget) {
+		// SAFETY: The caller of `mur_history_add_redo`/`mur_history_add_undo` promised that this
+		// callback is safe to call with this user data and any target passed to undo/redo.
+		unsafe { (self.apply)(self.user_data, item.0) }
+	}
+}
+
+impl Drop for FfiOperation {
+	fn drop(&mut self) {
+		if let Some(drop) = self.drop {
+			// SAFETY: The caller of `mur_history_add_redo`/`mur_history_add_undo` promised that
+			// this callback is safe to call with this user data, once the operation is dropped.
+			unsafe { drop(self.user_data) }
+		}
+	}
+}
+
+fn result_code(result: Result<(), UndoRedoError>) -> c_int {
+	match result {
+		Ok(()) => MUR_OK,
+		Err(UndoRedoError::NothingToDo) => MUR_NOTHING_TO_DO,
+		Err(UndoRedoError::OutOfBounds) => MUR_OUT_OF_BOUNDS,
+		Err(UndoRedoError::HistoryFull) => MUR_HISTORY_FULL,
+		Err(UndoRedoError::HasDependents) => MUR_HAS_DEPENDENTS,
+		Err(UndoRedoError::DoesNotCommute) => MUR_DOES_NOT_COMMUTE,
+	}
+}
+
+/// Creates a new, empty history. It must be freed with [`mur_history_free`].
+#[unsafe(no_mangle)]
+pub extern "C" fn mur_history_new() -> *mut MurHistory {
+	Box::into_raw(Box::default())
+}
+
+/// Frees a history created with [`mur_history_new`], dropping all of its operations.
+///
+/// # Safety
+/// `history` must be null, or a pointer returned by [`mur_history_new`] that has not yet been
+/// freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mur_history_free(history: *mut MurHistory) {
+	if !history.is_null() {
+		// SAFETY: Upheld by the caller.
+		drop(unsafe { Box::from_raw(history) });
+	}
+}
+
+/// Begins a new action at the current point in history, erasing any unapplied actions. Operations
+/// can then be added to it with [`mur_history_add_redo`] and [`mur_history_add_undo`], after which
+/// it is applied with [`mur_history_commit_action`].
+///
+/// Until it is applied, the action is the first unapplied action, so [`mur_history_redo`] would
+/// also apply it.
+///
+/// `name` may be null, in which case the action has no name. Otherwise, it is copied, replacing
+/// any invalid UTF-8 with U+FFFD.
+///
+/// # Safety
+/// * `history` must be a valid pointer returned by [`mur_history_new`].
+/// * `name` must be null, or point to a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mur_history_begin_action(
+	history: *mut MurHistory,
+	name: *const c_char,
+) -> c_int {
+	// SAFETY: Upheld by the caller.
+	let Some(history) = (unsafe { history.as_mut() }) else {
+		return MUR_NULL_POINTER;
+	};
+	let action = history.create_action();
+	if !name.is_null() {
+		// SAFETY: Upheld by the caller.
+		let name = unsafe { CStr::from_ptr(name) };
+		action.set_name(name.to_string_lossy());
+	}
+	MUR_OK
+}
+
+/// Adds an operation to perform when redoing the action begun by [`mur_history_begin_action`].
+///
+/// `drop` may be null. Otherwise, it is called with `user_data` once the operation is no longer
+/// needed. If this function fails, `drop` is called immediately.
+///
+/// # Safety
+/// * `history` must be a valid pointer returned by [`mur_history_new`].
+/// * It must be safe to call `apply` with `user_data`, and any target passed to the functions
+///   which perform operations, for as long as the history exists.
+/// * It must be safe to call `drop` with `user_data` once.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mur_history_add_redo(
+	history: *mut MurHistory,
+	apply: MurApplyFn,
+	user_data: *mut c_void,
+	drop: Option<MurDropFn>,
+) -> c_int {
+	let op = FfiOperation {
+		apply,
+		drop,
+		user_data,
+	};
+	// SAFETY: Upheld by the caller.
+	match unsafe { open_action(history) } {
+		Ok(action) => {
+			action.add_redo_operation(op);
+			MUR_OK
+		}
+		Err(code) => code,
+	}
+}
+
+/// Adds an operation to perform when undoing the action begun by [`mur_history_begin_action`].
+///
+/// See [`mur_history_add_redo`] for how the arguments are used.
+///
+/// # Safety
+/// See [`mur_history_add_redo`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mur_history_add_undo(
+	history: *mut MurHistory,
+	apply: MurApplyFn,
+	user_data: *mut c_void,
+	drop: Option<MurDropFn>,
+) -> c_int {
+	let op = FfiOperation {
+		apply,
+		drop,
+		user_data,
+	};
+	// SAFETY: Upheld by the caller.
+	match unsafe { open_action(history) } {
+		Ok(action) => {
+			action.add_undo_operation(op);
+			MUR_OK
+		}
+		Err(code) => code,
+	}
+}
+
+/// Returns the action begun by [`mur_history_begin_action`], if it has not yet been applied.
+///
+/// # Safety
+/// `history` must be null, or a valid pointer returned by [`mur_history_new`].
+unsafe fn open_action<'a>(
+	history: *mut MurHistory,
+) -> Result<&'a mut crate::Action<FfiOperation>, c_int> {
+	// SAFETY: Upheld by the caller.
+	let history = unsafe { history.as_mut() }.ok_or(MUR_NULL_POINTER)?;
+	if history.tapehead + 1 != history.actions.len() {
+		return Err(MUR_NO_OPEN_ACTION);
+	}
+	history
+		.actions
+		.get_mut(history.tapehead)
+		.ok_or(MUR_NO_OPEN_ACTION)
+}
+
+/// Applies the action begun by [`mur_history_begin_action`] to `target`, after which no more
+/// operations can be added to it.
+///
+/// Returns `MUR_NO_OPEN_ACTION` if no action has been begun since the last one was applied.
+///
+/// # Safety
+/// See [`mur_history_redo`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mur_history_commit_action(
+	history: *mut MurHistory,
+	target: *mut c_void,
+) -> c_int {
+	// SAFETY: Upheld by the caller.
+	if let Err(code) = unsafe { open_action(history) } {
+		return code;
+	}
+	// SAFETY: Upheld by the caller.
+	unsafe { mur_history_redo(history, target) }
+}
+
+/// Applies the first unapplied action to `target`.
+///
+/// Returns `MUR_NOTHING_TO_DO` if there is no action to apply.
+///
+/// # Safety
+/// * `history` must be a valid pointer returned by [`mur_history_new`].
+/// * `target` must be valid to pass to the callbacks of the operations being applied.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mur_history_redo(history: *mut MurHistory, target: *mut c_void) -> c_int {
+	// SAFETY: Upheld by the caller.
+	let Some(history) = (unsafe { history.as_mut() }) else {
+		return MUR_NULL_POINTER;
+	};
+	result_code(history.redo(&mut FfiTarget(target)))
+}
+
+/// Reverts the last applied action on `target`.
+///
+/// Returns `MUR_NOTHING_TO_DO` if there is no action to revert.
+///
+/// # Safety
+/// See [`mur_history_redo`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mur_history_undo(history: *mut MurHistory, target: *mut c_void) -> c_int {
+	// SAFETY: Upheld by the caller.
+	let Some(history) = (unsafe { history.as_mut() }) else {
+		return MUR_NULL_POINTER;
+	};
+	result_code(history.undo(&mut FfiTarget(target)))
+}
+
+/// Returns the number of actions in the history, or 0 if `history` is null.
+///
+/// # Safety
+/// `history` must be null, or a valid pointer returned by [`mur_history_new`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mur_history_len(history: *const MurHistory) -> usize {
+	// SAFETY: Upheld by the caller.
+	unsafe { history.as_ref() }.map_or(0, |history| history.actions.len())
+}
+
+/// Returns the number of actions which are currently applied, or 0 if `history` is null.
+///
+/// # Safety
+/// `history` must be null, or a valid pointer returned by [`mur_history_new`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mur_history_position(history: *const MurHistory) -> usize {
+	// SAFETY: Upheld by the caller.
+	unsafe { history.as_ref() }.map_or(0, UndoRedo::position)
+}
+
+/// Copies the name of the action at `index` into `buf` as a NUL-terminated string, truncating it
+/// if it does not fit in `buf_len` bytes.
+///
+/// Returns the length of the full name in bytes (not including the NUL terminator), so that a
+/// large enough buffer can be allocated. Returns 0 if the action does not exist or has no name.
+///
+/// # Safety
+/// * `history` must be null, or a valid pointer returned by [`mur_history_new`].
+/// * `buf` must be null, or valid for writes of `buf_len` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mur_history_action_name(
+	history: *const MurHistory,
+	index: usize,
+	buf: *mut c_char,
+	buf_len: usize,
+) -> usize {
+	// SAFETY: Upheld by the caller.
+	let name = unsafe { history.as_ref() }
+		.and_then(|history| history.actions.get(index))
+		.and_then(|action| action.get_name())
+		.unwrap_or_default();
+
+	if !buf.is_null() && buf_len > 0 {
+		let copied = name.len().min(buf_len - 1);
+		// SAFETY: `buf` is valid for `buf_len` bytes, and `copied + 1 <= buf_len`. `name` cannot
+		// overlap `buf`, as it is owned by the history.
+		unsafe {
+			ptr::copy_nonoverlapping(name.as_ptr(), buf.cast::<u8>(), copied);
+			buf.add(copied).write(0);
+		}
+	}
+	name.len()
+}
+
+#[cfg(test)]
+mod tests {
+	use core::cell::Cell;
+
+	use super::*;
+
+	/// The `user_data` of each operation in these tests.
+	struct Tracked {
+		amount: i32,
+		drops: Cell<usize>,
+	}
+
+	impl Tracked {
+		fn new(amount: i32) -> Self {
+			Self {
+				amount,
+				drops: Cell::new(0),
+			}
+		}
+
+		fn user_data(&self) -> *mut c_void {
+			ptr::from_ref(self).cast_mut().cast()
+		}
+	}
+
+	unsafe extern "C" fn add(user_data: *mut c_void, target: *mut c_void) {
+		// SAFETY: Every operation in these tests is given a `Tracked` and an `i32` target.
+		unsafe {
+			*target.cast::<i32>() += (*user_data.cast::<Tracked>()).amount;
+		}
+	}
+
+	unsafe extern "C" fn count_drop(user_data: *mut c_void) {
+		// SAFETY: Every operation in these tests is given a `Tracked`.
+		let tracked = unsafe { &*user_data.cast::<Tracked>() };
+		tracked.drops.set(tracked.drops.get() + 1);
+	}
+
+	/// Begins an action adding `redo.amount`, which `undo` reverts, and commits it to `target`.
+	///
+	/// # Safety
+	/// `history` must be a valid pointer returned by [`mur_history_new`], and `redo` and `undo`
+	/// must outlive it.
+	unsafe fn commit(
+		history: *mut MurHistory,
+		redo: &Tracked,
+		undo: &Tracked,
+		target: *mut c_void,
+	) {
+		// SAFETY: Upheld by the caller.
+		unsafe {
+			assert_eq!(mur_history_begin_action(history, c"Add".as_ptr()), MUR_OK);
+			let code = mur_history_add_redo(history, add, redo.user_data(), Some(count_drop));
+			assert_eq!(code, MUR_OK);
+			let code = mur_history_add_undo(history, add, undo.user_data(), Some(count_drop));
+			assert_eq!(code, MUR_OK);
+			let code = mur_history_commit_action(history, target);
+			assert_eq!(code, MUR_OK);
+		}
+	}
+
+	#[test]
+	fn commits_undoes_and_redoes() {
+		let (redo, undo) = (Tracked::new(5), Tracked::new(-5));
+		let mut target = 0;
+		let history = mur_history_new();
+		let target_ptr: *mut c_void = ptr::from_mut(&mut target).cast();
+		// SAFETY: `history` is freed at the end, before the operations go out of scope.
+		unsafe {
+			commit(history, &redo, &undo, target_ptr);
+			assert_eq!(mur_history_len(history), 1);
+			assert_eq!(mur_history_position(history), 1);
+			assert_eq!(
+				mur_history_commit_action(history, target_ptr),
+				MUR_NO_OPEN_ACTION
+			);
+
+			assert_eq!(mur_history_undo(history, target_ptr), MUR_OK);
+			assert_eq!(mur_history_undo(history, target_ptr), MUR_NOTHING_TO_DO);
+			assert_eq!(mur_history_redo(history, target_ptr), MUR_OK);
+			mur_history_free(history);
+		}
+		assert_eq!(target, 5);
+		assert_eq!((redo.drops.get(), undo.drops.get()), (1, 1));
+	}
+
+	#[test]
+	fn drops_rejected_operations_once() {
+		let rejected = [Tracked::new(1), Tracked::new(2), Tracked::new(3)];
+		let mut target = 0;
+		let history = mur_history_new();
+		// SAFETY: `history` is freed at the end, before the operations go out of scope.
+		unsafe {
+			let code =
+				mur_history_add_redo(history, add, rejected[0].user_data(), Some(count_drop));
+			assert_eq!(code, MUR_NO_OPEN_ACTION);
+
+			let (redo, undo) = (Tracked::new(4), Tracked::new(-4));
+			commit(history, &redo, &undo, ptr::from_mut(&mut target).cast());
+			let code =
+				mur_history_add_undo(history, add, rejected[1].user_data(), Some(count_drop));
+			assert_eq!(code, MUR_NO_OPEN_ACTION);
+
+			let code = mur_history_add_redo(
+				ptr::null_mut(),
+				add,
+				rejected[2].user_data(),
+				Some(count_drop),
+			);
+			assert_eq!(code, MUR_NULL_POINTER);
+			mur_history_free(history);
+			assert_eq!((redo.drops.get(), undo.drops.get()), (1, 1));
+		}
+		assert_eq!(target, 4);
+		assert!(rejected.iter().all(|tracked| tracked.drops.get() == 1));
+	}
+
+	#[test]
+	fn drops_erased_actions_once() {
+		let (redo, undo) = (Tracked::new(1), Tracked::new(-1));
+		let mut target = 0;
+		let history = mur_history_new();
+		let target_ptr: *mut c_void = ptr::from_mut(&mut target).cast();
+		// SAFETY: `history` is freed before the operations go out of scope.
+		unsafe {
+			commit(history, &redo, &undo, target_ptr);
+			assert_eq!(mur_history_undo(history, target_ptr), MUR_OK);
+			// Beginning an action erases the undone one.
+			assert_eq!(mur_history_begin_action(history, ptr::null()), MUR_OK);
+			assert_eq!((redo.drops.get(), undo.drops.get()), (1, 1));
+			assert_eq!(mur_history_len(history), 1);
+			mur_history_free(history);
+		}
+		assert_eq!((redo.drops.get(), undo.drops.get()), (1, 1));
+	}
+
+	#[test]
+	fn truncates_action_names() {
+		let history = mur_history_new();
+		let mut buf = [c_char::MAX; 8];
+		let buf_ptr = buf.as_mut_ptr();
+		// SAFETY: `buf` is valid for writes of its length, and `history` is freed at the end.
+		unsafe {
+			assert_eq!(mur_history_begin_action(history, c"Paint".as_ptr()), MUR_OK);
+			let name = |len| mur_history_action_name(history, 0, buf_ptr, len);
+
+			assert_eq!(name(8), 5);
+			assert_eq!(CStr::from_ptr(buf_ptr), c"Paint");
+			assert_eq!(name(4), 5);
+			assert_eq!(CStr::from_ptr(buf_ptr), c"Pai");
+			assert_eq!(name(1), 5);
+			assert_eq!(CStr::from_ptr(buf_ptr), c"");
+
+			buf_ptr.write(c_char::MAX);
+			assert_eq!(name(0), 5);
+			assert_eq!(buf_ptr.read(), c_char::MAX);
+			assert_eq!(mur_history_action_name(history, 0, ptr::null_mut(), 8), 5);
+			assert_eq!(mur_history_action_name(history, 1, buf_ptr, 8), 0);
+			assert_eq!(CStr::from_ptr(buf_ptr), c"");
+			mur_history_free(history);
+		}
+	}
+
+	#[test]
+	fn maps_each_error_to_its_own_code() {
+		let codes = [
+			UndoRedoError::NothingToDo,
+			UndoRedoError::OutOfBounds,
+			UndoRedoError::HistoryFull,
+			UndoRedoError::HasDependents,
+			UndoRedoError::DoesNotCommute,
+		]
+		.map(|error| result_code(Err(error)));
+		assert_eq!(
+			codes,
+			[
+				MUR_NOTHING_TO_DO,
+				MUR_OUT_OF_BOUNDS,
+				MUR_HISTORY_FULL,
+				MUR_HAS_DEPENDENTS,
+				MUR_DOES_NOT_COMMUTE
+			]
+		);
+		assert_eq!(result_code(Ok(())), MUR_OK);
+	}
+}
@@ -0,0 +1,46 @@
+use crate::{Action, HistoryStorage, Operation, UndoRedo};
+
+/// An [`Operation`] which can create the operation that reverts it, without needing to look at
+/// the object it operates on.
+///
+/// For common shapes of operation, this can be implemented with
+/// [`invertible_operation!`](crate::invertible_operation).
+pub trait InvertibleOperation<For>: Operation<For> + Sized {
+	/// Returns an operation which reverts this one.
+	fn inverse(&self) -> Self;
+}
+
+impl<Op> Action<Op> {
+	/// Adds `operation` to perform when redoing/applying this action, and its inverse to perform
+	/// when undoing/reverting it.
+	///
+	/// The inverse is performed before the undo operations already added, so that undoing reverts
+	/// operations in the opposite order to the one they were applied in.
+	pub fn add_invertible_operation<For>(&mut self, operation: Op) -> &mut Self
+	where
+		Op: InvertibleOperation<For>,
+	{
+		self.revert_ops.insert_first(operation.inverse());
+		self.apply_ops.push(operation);
+		self
+	}
+}
+
+impl<Op, S: HistoryStorage<Op>> UndoRedo<Op, S> {
+	/// Adds an action containing `operation` (see [`Action::add_invertible_operation`]) at the
+	/// current point in history, as [`Self::create_action`] would, then applies it to `apply_to`.
+	///
+	/// The action is returned so that it can be named.
+	///
+	/// # Panics
+	/// See [`Self::create_action`].
+	pub fn commit_invertible<For>(&mut self, operation: Op, apply_to: &mut For) -> &mut Action<Op>
+	where
+		Op: InvertibleOperation<For>,
+	{
+		let mut action = Action::default();
+		action.add_invertible_operation(operation);
+		action.apply(apply_to);
+		self.push_applied(action)
+	}
+}
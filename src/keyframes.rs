@@ -0,0 +1,547 @@
+use alloc::{collections::BTreeMap, vec::Vec};
+use core::{error, fmt, ops::Range};
+
+use crate::{Action, HistoryStorage, Operation, Snapshot, UndoRedo, UndoRedoError};
+
+/// Snapshots of an object, captured every so many actions of its history, so that jumping a long
+/// way through the history can restore the nearest snapshot and only undo or redo the rest.
+///
+/// The keyframes are kept separately from the history, so they must be told when it changes:
+/// * Call [`Self::after_commit`] after each action is added to the history.
+/// * Call [`Self::after_evict`] after actions are removed from the beginning of the history.
+/// * Call [`Self::clear`] after anything else that changes what the applied actions do (such as
+///   skipping an action, or undoing one selectively).
+///
+/// Undoing and redoing don't invalidate any keyframes. See [`AnchoredUndoRedo`] for a history
+/// which keeps its keyframes up to date itself.
+///
+/// To limit how much memory the keyframes use, see [`Self::set_memory_budget`].
+#[derive(Clone, Debug)]
+pub struct Keyframes<State> {
+	interval: usize,
+	restore_cost: usize,
+	budget: Option<MemoryBudget<State>>,
+	frames: BTreeMap<usize, State>,
+}
+
+#[derive(Clone, Debug)]
+struct MemoryBudget<State> {
+	bytes: usize,
+	size_of: fn(&State) -> usize,
+}
+
+impl<State> Keyframes<State> {
+	/// Creates an empty set of keyframes, which will capture a keyframe every `interval` actions.
+	/// An `interval` of 0 is treated as 1.
+	pub const fn new(interval: usize) -> Self {
+		Self {
+			interval: if interval == 0 { 1 } else { interval },
+			restore_cost: 0,
+			budget: None,
+			frames: BTreeMap::new(),
+		}
+	}
+
+	/// Returns the number of actions between keyframes.
+	pub fn interval(&self) -> usize {
+		self.interval
+	}
+
+	/// Returns how many actions restoring a keyframe is considered to cost. See
+	/// [`Self::set_restore_cost`].
+	pub fn restore_cost(&self) -> usize {
+		self.restore_cost
+	}
+
+	/// Sets how many actions restoring a keyframe is considered to cost, for deciding whether to
+	/// restore one when jumping. By default, this is 0, so a keyframe is restored whenever it is
+	/// any closer than the current position in history.
+	pub fn set_restore_cost(&mut self, cost: usize) {
+		self.restore_cost = cost;
+	}
+
+	/// Limits the keyframes to `bytes` of memory, as measured by `size_of` for each keyframe.
+	/// Whenever they use more than that, the oldest keyframes are removed until they fit, except
+	/// that the newest keyframe is always kept.
+	pub fn set_memory_budget(&mut self, bytes: usize, size_of: fn(&State) -> usize) {
+		self.budget = Some(MemoryBudget { bytes, size_of });
+		self.enforce_budget();
+	}
+
+	/// Removes the limit set by [`Self::set_memory_budget`].
+	pub fn clear_memory_budget(&mut self) {
+		self.budget = None;
+	}
+
+	/// Returns the memory used by the keyframes, as measured by the function given to
+	/// [`Self::set_memory_budget`], or `None` if there is no memory budget.
+	pub fn memory_usage(&self) -> Option<usize> {
+		let budget = self.budget.as_ref()?;
+		Some(self.frames.values().map(budget.size_of).sum())
+	}
+
+	fn enforce_budget(&mut self) {
+		let Some(MemoryBudget { bytes, size_of }) = self.budget.as_ref() else {
+			return;
+		};
+		let mut used: usize = self.frames.values().map(size_of).sum();
+		while used > *bytes && self.frames.len() > 1 {
+			if let Some((_, oldest)) = self.frames.pop_first() {
+				used -= size_of(&oldest);
+			}
+		}
+	}
+
+	/// Returns the positions in history which have a keyframe, in ascending order.
+	pub fn positions(&self) -> impl Iterator<Item = usize> + '_ {
+		self.frames.keys().copied()
+	}
+
+	/// Removes every keyframe.
+	pub fn clear(&mut self) {
+		self.frames.clear();
+	}
+
+	/// Captures a keyframe of `target`, replacing any already captured at `position`.
+	/// `target` must be in the state it would be in at that position in history.
+	pub fn capture<For: Snapshot<State = State>>(&mut self, position: usize, target: &For) {
+		self.frames.insert(position, target.capture());
+		self.enforce_budget();
+	}
+
+	/// Updates the keyframes after an action was added to `history`, which `target` must already
+	/// have had applied to it.
+	///
+	/// Keyframes after the new action are removed, as adding it discarded the actions they were
+	/// captured for. Then, if the last remaining keyframe is at least the interval behind the
+	/// position in history (or there is none), a keyframe is captured there.
+	pub fn after_commit<Op, S, For>(&mut self, history: &UndoRedo<Op, S>, target: &For)
+	where
+		S: HistoryStorage<Op>,
+		For: Snapshot<State = State>,
+	{
+		let position = history.position();
+		self.frames.split_off(&position);
+		let last = self.frames.keys().next_back();
+		if last.is_none_or(|&last| position - last >= self.interval) {
+			self.capture(position, target);
+		}
+	}
+
+	/// Updates the keyframes after `count` actions were removed from the beginning of the history
+	/// (see [`UndoRedo::evict_oldest`]), moving the remaining keyframes to match.
+	pub fn after_evict(&mut self, count: usize) {
+		if count == 0 {
+			return;
+		}
+		let kept = self.frames.split_off(&count);
+		self.frames = kept
+			.into_iter()
+			.map(|(position, state)| (position - count, state))
+			.collect();
+	}
+
+	/// Like [`UndoRedo::jump_to`], but if a keyframe is closer to `position` than the current
+	/// position in history (including [the cost of restoring it](Self::set_restore_cost)), the
+	/// keyframe is restored first, so that fewer actions need to be undone or redone.
+	///
+	/// # Errors
+	/// See [`UndoRedo::jump_to`].
+	pub fn jump_to<Op, S, For>(
+		&self,
+		history: &mut UndoRedo<Op, S>,
+		position: usize,
+		target: &mut For,
+	) -> Result<(), UndoRedoError>
+	where
+		Op: Operation<For>,
+		S: HistoryStorage<Op>,
+		For: Snapshot<State = State>,
+	{
+		if position > history.actions.len() {
+			return Err(UndoRedoError::OutOfBounds);
+		}
+
+		let distance = |from: usize| from.abs_diff(position);
+		let nearest = self
+			.frames
+			.iter()
+			.filter(|&(&frame, _)| frame <= history.actions.len())
+			.min_by_key(|&(&frame, _)| distance(frame));
+		if let Some((&frame, state)) = nearest
+			&& distance(frame).saturating_add(self.restore_cost) < distance(history.tapehead)
+		{
+			target.restore(state);
+			history.tapehead = frame;
+		}
+		history.jump_to(position, target)
+	}
+}
+
+impl<State: PartialEq> Keyframes<State> {
+	/// Checks that the keyframes agree with the actions of `history`: that applying the actions
+	/// between each pair of neighbouring keyframes turns the older keyframe into the newer one,
+	/// and that reverting them turns the newer keyframe back into the older one.
+	///
+	/// `scratch` is restored to each keyframe in turn, and has the actions applied to it, so its
+	/// state afterwards is unspecified. Neither `history` nor any other object is changed.
+	///
+	/// # Errors
+	/// Returns the first (oldest) pair of keyframes which don't agree with the actions between
+	/// them.
+	pub fn verify<Op, S, For>(
+		&self,
+		history: &UndoRedo<Op, S>,
+		scratch: &mut For,
+	) -> Result<(), Divergence>
+	where
+		Op: Operation<For>,
+		S: HistoryStorage<Op>,
+		For: Snapshot<State = State>,
+	{
+		let frames = || self.frames.range(..=history.actions.len());
+		for ((&from, older), (&to, newer)) in frames().zip(frames().skip(1)) {
+			scratch.restore(older);
+			for action in (from..to).filter_map(|index| history.actions.get(index)) {
+				action.apply(scratch);
+			}
+			if scratch.capture() != *newer {
+				return Err(Divergence {
+					actions: from..to,
+					reverting: false,
+				});
+			}
+
+			scratch.restore(newer);
+			for action in (from..to)
+				.rev()
+				.filter_map(|index| history.actions.get(index))
+			{
+				action.revert(scratch);
+			}
+			if scratch.capture() != *older {
+				return Err(Divergence {
+					actions: from..to,
+					reverting: true,
+				});
+			}
+		}
+		Ok(())
+	}
+}
+
+/// An error indicating that [`Keyframes`] disagree with the actions between them, as found by
+/// [`Keyframes::verify`].
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Divergence {
+	actions: Range<usize>,
+	reverting: bool,
+}
+
+impl Divergence {
+	/// Returns the indices of the actions between the two keyframes which disagree. One or more
+	/// of these actions doesn't do what the keyframes say it should.
+	pub fn actions(&self) -> Range<usize> {
+		self.actions.clone()
+	}
+
+	/// Returns `true` if the keyframes disagree when reverting the actions, or `false` if they
+	/// disagree when applying them.
+	pub fn reverting(&self) -> bool {
+		self.reverting
+	}
+}
+
+impl fmt::Display for Divergence {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let direction = if self.reverting {
+			"reverting"
+		} else {
+			"applying"
+		};
+		write!(
+			f,
+			"{direction} actions {} to {} does not match their keyframes",
+			self.actions.start, self.actions.end
+		)
+	}
+}
+
+impl error::Error for Divergence {}
+
+/// An [`UndoRedo`] combined with [`Keyframes`] of the object it operates on, which are kept up to
+/// date automatically. Jumping through history restores the nearest keyframe, or undoes and
+/// redoes from the current position, whichever is closer.
+#[derive(Clone, Debug)]
+pub struct AnchoredUndoRedo<Op, State, S = Vec<Action<Op>>> {
+	history: UndoRedo<Op, S>,
+	keyframes: Keyframes<State>,
+}
+
+impl<Op, State, S: HistoryStorage<Op>> AnchoredUndoRedo<Op, State, S> {
+	/// Wraps `history`, capturing a keyframe every `interval` actions. `target` must be in the
+	/// state it would be in at the current position in history, where the first keyframe is
+	/// captured.
+	pub fn new<For>(history: UndoRedo<Op, S>, interval: usize, target: &For) -> Self
+	where
+		For: Snapshot<State = State>,
+	{
+		let mut keyframes = Keyframes::new(interval);
+		keyframes.capture(history.position(), target);
+		Self { history, keyframes }
+	}
+
+	/// Returns the history.
+	pub fn history(&self) -> &UndoRedo<Op, S> {
+		&self.history
+	}
+
+	/// Returns the keyframes.
+	pub fn keyframes(&self) -> &Keyframes<State> {
+		&self.keyframes
+	}
+
+	/// Returns the keyframes mutably, such as to [set the cost of restoring
+	/// one](Keyframes::set_restore_cost).
+	pub fn keyframes_mut(&mut self) -> &mut Keyframes<State> {
+		&mut self.keyframes
+	}
+
+	/// Returns the history and keyframes.
+	pub fn into_parts(self) -> (UndoRedo<Op, S>, Keyframes<State>) {
+		(self.history, self.keyframes)
+	}
+
+	/// Performs [`UndoRedo::commit_action`], then updates the keyframes to match.
+	///
+	/// # Panics
+	/// See [`UndoRedo::commit_action`].
+	pub fn commit_action<For>(&mut self, action: Action<Op>, apply_to: &mut For)
+	where
+		Op: Operation<For>,
+		For: Snapshot<State = State>,
+	{
+		let expected = self.history.position() + 1;
+		self.history.commit_action(action, apply_to);
+		// Storages may remove the oldest actions to make room for the new one.
+		self.keyframes
+			.after_evict(expected - self.history.position());
+		self.keyframes.after_commit(&self.history, apply_to);
+	}
+
+	/// Performs [`UndoRedo::undo`].
+	///
+	/// # Errors
+	/// See [`UndoRedo::undo`].
+	pub fn undo<For>(&mut self, apply_to: &mut For) -> Result<(), UndoRedoError>
+	where
+		Op: Operation<For>,
+	{
+		self.history.undo(apply_to)
+	}
+
+	/// Performs [`UndoRedo::redo`].
+	///
+	/// # Errors
+	/// See [`UndoRedo::redo`].
+	///
+	/// # Panics
+	/// See [`UndoRedo::redo`].
+	pub fn redo<For>(&mut self, apply_to: &mut For) -> Result<(), UndoRedoError>
+	where
+		Op: Operation<For>,
+	{
+		self.history.redo(apply_to)
+	}
+
+	/// Performs [`Keyframes::jump_to`].
+	///
+	/// # Errors
+	/// See [`UndoRedo::jump_to`].
+	pub fn jump_to<For>(&mut self, position: usize, apply_to: &mut For) -> Result<(), UndoRedoError>
+	where
+		Op: Operation<For>,
+		For: Snapshot<State = State>,
+	{
+		self.keyframes
+			.jump_to(&mut self.history, position, apply_to)
+	}
+
+	/// Performs [`UndoRedo::evict_oldest`], then moves the keyframes to match.
+	pub fn evict_oldest(&mut self, count: usize) -> usize {
+		let evicted = self.history.evict_oldest(count);
+		self.keyframes.after_evict(evicted);
+		evicted
+	}
+
+	/// Performs [`Keyframes::verify`].
+	///
+	/// # Errors
+	/// See [`Keyframes::verify`].
+	pub fn verify<For>(&self, scratch: &mut For) -> Result<(), Divergence>
+	where
+		Op: Operation<For>,
+		State: PartialEq,
+		For: Snapshot<State = State>,
+	{
+		self.keyframes.verify(&self.history, scratch)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use alloc::vec::Vec;
+
+	use super::*;
+	use crate::Action;
+
+	/// A number which counts how many times it has been restored from a keyframe.
+	#[derive(Debug, Default)]
+	struct Counter {
+		value: i32,
+		restores: usize,
+	}
+
+	impl Snapshot for Counter {
+		type State = i32;
+
+		fn capture(&self) -> i32 {
+			self.value
+		}
+
+		fn restore(&mut self, state: &i32) {
+			self.value = *state;
+			self.restores += 1;
+		}
+	}
+
+	#[derive(Clone, Debug, PartialEq)]
+	struct Add(i32);
+
+	impl Operation<Counter> for Add {
+		fn apply(&self, item: &mut Counter) {
+			item.value += self.0;
+		}
+	}
+
+	fn add(value: i32) -> Action<Add> {
+		let mut action = Action::default();
+		action.add_redo_operation(Add(value));
+		action.add_undo_operation(Add(-value));
+		action
+	}
+
+	/// Returns a history of adding 1 through 6, with a keyframe every 2 actions.
+	fn history() -> (UndoRedo<Add>, Keyframes<i32>, Counter) {
+		let mut target = Counter::default();
+		let mut history = UndoRedo::new();
+		let mut keyframes = Keyframes::new(2);
+		keyframes.capture(0, &target);
+		for value in 1..=6 {
+			history.commit_action(add(value), &mut target);
+			keyframes.after_commit(&history, &target);
+		}
+		(history, keyframes, target)
+	}
+
+	#[test]
+	fn captures_every_interval() {
+		let (mut history, mut keyframes, mut target) = history();
+		assert_eq!(keyframes.positions().collect::<Vec<_>>(), [0, 2, 4, 6]);
+
+		// Committing after undoing discards the keyframes of the erased actions.
+		history.jump_to(3, &mut target).unwrap();
+		history.commit_action(add(10), &mut target);
+		keyframes.after_commit(&history, &target);
+		assert_eq!(keyframes.positions().collect::<Vec<_>>(), [0, 2, 4]);
+
+		keyframes.after_evict(2);
+		assert_eq!(keyframes.positions().collect::<Vec<_>>(), [0, 2]);
+	}
+
+	#[test]
+	fn jump_restores_nearest_keyframe() {
+		let (mut history, keyframes, mut target) = history();
+		keyframes.jump_to(&mut history, 1, &mut target).unwrap();
+		assert_eq!((target.value, target.restores), (1, 1));
+		assert_eq!(history.position(), 1);
+
+		// No keyframe is closer to 5 than 6 is.
+		history.jump_to(6, &mut target).unwrap();
+		keyframes.jump_to(&mut history, 5, &mut target).unwrap();
+		assert_eq!((target.value, target.restores), (15, 1));
+
+		assert_eq!(
+			keyframes.jump_to(&mut history, 7, &mut target),
+			Err(UndoRedoError::OutOfBounds)
+		);
+	}
+
+	/// Returns the same history as [`history`], anchored to its keyframes.
+	fn anchored() -> (AnchoredUndoRedo<Add, i32>, Counter) {
+		let mut target = Counter::default();
+		let mut anchored = AnchoredUndoRedo::new(UndoRedo::new(), 2, &target);
+		for value in 1..=6 {
+			anchored.commit_action(add(value), &mut target);
+		}
+		(anchored, target)
+	}
+
+	#[test]
+	fn anchored_jump_weighs_restore_cost() {
+		let (mut anchored, mut target) = anchored();
+		assert_eq!(
+			anchored.keyframes().positions().collect::<Vec<_>>(),
+			[0, 2, 4, 6]
+		);
+		anchored.jump_to(3, &mut target).unwrap();
+		assert_eq!(target.value, 1 + 2 + 3);
+		assert_eq!(target.restores, 1);
+		assert_eq!(anchored.history().position(), 3);
+
+		// Restoring a keyframe costs more than undoing a single action.
+		anchored.keyframes_mut().set_restore_cost(2);
+		anchored.jump_to(2, &mut target).unwrap();
+		assert_eq!(target.value, 1 + 2);
+		assert_eq!(target.restores, 1);
+
+		assert_eq!(
+			anchored.jump_to(7, &mut target),
+			Err(UndoRedoError::OutOfBounds)
+		);
+	}
+
+	#[test]
+	fn memory_budget_keeps_newest_keyframes() {
+		let (_, mut keyframes, _) = history();
+		assert_eq!(keyframes.memory_usage(), None);
+
+		keyframes.set_memory_budget(8, |_| 4);
+		assert_eq!(keyframes.positions().collect::<Vec<_>>(), [4, 6]);
+		assert_eq!(keyframes.memory_usage(), Some(8));
+
+		// The newest keyframe is kept even if it doesn't fit.
+		keyframes.set_memory_budget(0, |_| 4);
+		assert_eq!(keyframes.positions().collect::<Vec<_>>(), [6]);
+		keyframes.clear_memory_budget();
+		assert_eq!(keyframes.memory_usage(), None);
+	}
+
+	#[test]
+	fn verify_finds_divergent_keyframe() {
+		let (anchored, mut scratch) = anchored();
+		assert_eq!(anchored.verify(&mut scratch), Ok(()));
+
+		let (history, mut keyframes) = anchored.into_parts();
+		keyframes.capture(
+			4,
+			&Counter {
+				value: 0,
+				restores: 0,
+			},
+		);
+		let divergence = keyframes.verify(&history, &mut scratch).unwrap_err();
+		assert_eq!(divergence.actions(), 2..4);
+		assert!(!divergence.reverting());
+	}
+}
@@ -0,0 +1,75 @@
+use core::ptr;
+
+use crate::{InvertibleOperation, Lens, MergeableOperation, Operation};
+
+/// An operation which sets a value, holding the value from before it was set so that it can be
+/// inverted.
+///
+/// Combined with a [`Lens`], this sets one field of a larger object, so a field of a nested model
+/// can be changed without writing an operation for it. See [`Lens::set`].
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Set<T> {
+	/// The value before the operation.
+	pub old: T,
+	/// The value after the operation.
+	pub new: T,
+}
+
+impl<T: Clone> Operation<T> for Set<T> {
+	fn apply(&self, item: &mut T) {
+		item.clone_from(&self.new);
+	}
+}
+
+impl<T: Clone> InvertibleOperation<T> for Set<T> {
+	fn inverse(&self) -> Self {
+		Self {
+			old: self.new.clone(),
+			new: self.old.clone(),
+		}
+	}
+}
+
+/// Merges consecutive sets of the same value, keeping the first value from before and the last
+/// value from after.
+impl<T> MergeableOperation for Set<T> {
+	fn merge(&mut self, next: Self) -> Result<(), Self> {
+		self.new = next.new;
+		Ok(())
+	}
+}
+
+impl<T: Clone, Outer> Lens<Set<T>, Outer, T> {
+	/// Creates an operation which sets the field of `target` returned by `project` to `new`,
+	/// capturing the field's current value. `target` is left unchanged.
+	///
+	/// `project` can be written with [`lens!`](crate::lens).
+	pub fn set(target: &mut Outer, project: fn(&mut Outer) -> &mut T, new: T) -> Self {
+		let old = project(target).clone();
+		Self::new(Set { old, new }, project)
+	}
+}
+
+impl<Op, Outer, Inner> InvertibleOperation<Outer> for Lens<Op, Outer, Inner>
+where
+	Op: InvertibleOperation<Inner>,
+{
+	fn inverse(&self) -> Self {
+		Self::new(self.op().inverse(), self.project)
+	}
+}
+
+/// Merges consecutive operations on the same field, if their operations can be merged. Fields are
+/// compared by the address of their projection functions, so the same field reached through two
+/// different functions is treated as a different field.
+impl<Op: MergeableOperation, Outer, Inner> MergeableOperation for Lens<Op, Outer, Inner> {
+	fn merge(&mut self, next: Self) -> Result<(), Self> {
+		if !ptr::fn_addr_eq(self.project, next.project) {
+			return Err(next);
+		}
+		let project = next.project;
+		self.op
+			.merge(next.into_op())
+			.map_err(|op| Self::new(op, project))
+	}
+}
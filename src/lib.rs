@@ -1,17 +1,194 @@
-use core::{error, fmt};
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::{
+	string::{String, ToString},
+	vec::Vec,
+};
+use core::{error, fmt, marker::PhantomData, ops::ControlFlow};
+
+use crate::op_list::OpList;
+
+#[cfg(feature = "std")]
+mod actor;
+#[cfg(feature = "persist")]
+mod archive;
+#[cfg(feature = "async")]
+mod async_ops;
+mod capture;
+mod combinators;
+mod command;
+#[cfg(feature = "std")]
+mod export;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod invertible;
+mod keyframes;
+mod lens;
+mod macros;
+#[cfg(feature = "std")]
+mod manager;
+mod merge;
+mod op_list;
+#[cfg(feature = "ops")]
+pub mod ops;
+mod output;
+#[cfg(feature = "persist")]
+mod paged;
+mod payload;
+#[cfg(feature = "persist")]
+mod persist;
+mod recording;
+mod replay;
+mod selective;
+#[cfg(feature = "std")]
+mod shared;
+mod shortcuts;
+mod sized;
+#[cfg(feature = "std")]
+mod snapshot;
+#[cfg(feature = "std")]
+mod staging;
+mod state;
+mod storage;
+mod store;
+mod targets;
+mod thread_safety;
+#[cfg(feature = "std")]
+mod tree;
+
+#[cfg(feature = "std")]
+pub use actor::HistoryActor;
+#[cfg(feature = "persist")]
+pub use archive::{DirectoryArchive, HistoryArchive, ReopenError};
+#[cfg(feature = "async")]
+pub use async_ops::{AsyncJump, AsyncOperation};
+pub use capture::CaptureOperation;
+pub use combinators::{Condition, Conditional, Repeat, Sequence};
+pub use command::{HistoryCommand, HistoryEvent, HistoryState};
+pub use invertible::InvertibleOperation;
+pub use keyframes::{AnchoredUndoRedo, Divergence, Keyframes};
+pub use lens::Set;
+#[cfg(feature = "std")]
+pub use manager::{UndoRedoManager, UndoScope};
+pub use merge::MergeableOperation;
+pub use output::OutputOperation;
+#[cfg(feature = "persist")]
+pub use paged::{
+	ActionLoader, ActionSpiller, FileActionLoader, PagedError, PagedUndoRedo, SpillFile,
+};
+pub use payload::{OwnedOrBorrowed, PromoteOperation};
+#[cfg(feature = "compression")]
+pub use persist::{Compression, Lz77};
+#[cfg(feature = "persist")]
+pub use persist::{PersistError, PersistOperation, PersistScope};
+pub use recording::Macro;
+pub use replay::ReplayMismatch;
+pub use selective::{
+	CommuteOperation, Conflict, ConflictReport, DependencyPolicy, KeyedOperation,
+	SelectiveUndoError,
+};
+#[cfg(feature = "std")]
+pub use shared::SharedUndoRedo;
+pub use shortcuts::{KeyPress, Platform, Shortcut, ShortcutHandler};
+#[cfg(feature = "std")]
+pub use snapshot::{HistorySnapshot, SnapshotPublisher, SnapshotReader};
+#[cfg(feature = "std")]
+pub use staging::{ActionId, ActionStage, ActionSubmitter, ActionTicket};
+pub use state::{ApplyPatch, Diffable, RecordOperation, Restore, Snapshot};
+pub use storage::{ArcStorage, ArrayStorage, BoundedStorage, HistoryStorage};
+pub use store::Store;
+pub use targets::{
+	Handle, Lens, OnTarget, RegistryEdit, ResolveError, TargetRegistry, TargetResolver,
+};
+pub use thread_safety::{DynUndoRedo, SyncDynUndoRedo, SyncOperation};
+#[cfg(feature = "std")]
+pub use tree::{NodeId, PruneStrategy, TreeNode, UndoTree};
 
 /// Represents one thing that will be applied to an object `For`, to reach a desired state.
 ///
 /// While the name `Operation` usually implies a single type of operation, you'll most likely want
-/// to implement this on an enum of operations to apply over `For`.
+/// to implement this on an enum of operations to apply over `For`. If the operations can't all be
+/// listed in one enum, use trait objects instead (see [`DynUndoRedo`]).
 pub trait Operation<For> {
 	fn apply(&self, item: &mut For);
+
+	/// Applies each of `ops` to `item`, in order.
+	///
+	/// [`Action`]s call this with all of their operations at once, so override it if a series of
+	/// operations can be applied more efficiently together than one at a time. By default, this
+	/// calls [`Self::apply`] for each operation.
+	fn apply_batch(ops: &[Self], item: &mut For)
+	where
+		Self: Sized,
+	{
+		ops.iter().for_each(|op| op.apply(item));
+	}
+}
+
+/// Like [`Operation`], but also given a context `Ctx` when applied, for state that operations need
+/// but that doesn't belong in `For` (such as asset caches or random number generators).
+///
+/// This is implemented for every [`Operation`], which ignores the context. Use
+/// [`UndoRedo::undo_with`], [`UndoRedo::redo_with`], and [`UndoRedo::jump_to_with`] to pass a
+/// context.
+pub trait ContextOperation<For, Ctx> {
+	fn apply_with(&self, item: &mut For, ctx: &mut Ctx);
+
+	/// Applies each of `ops` to `item`, in order.
+	///
+	/// See [`Operation::apply_batch`]. By default, this calls [`Self::apply_with`] for each
+	/// operation.
+	fn apply_batch_with(ops: &[Self], item: &mut For, ctx: &mut Ctx)
+	where
+		Self: Sized,
+	{
+		ops.iter().for_each(|op| op.apply_with(item, ctx));
+	}
+}
+
+impl<Op: Operation<For>, For, Ctx> ContextOperation<For, Ctx> for Op {
+	fn apply_with(&self, item: &mut For, _ctx: &mut Ctx) {
+		self.apply(item);
+	}
+
+	fn apply_batch_with(ops: &[Self], item: &mut For, _ctx: &mut Ctx) {
+		Op::apply_batch(ops, item);
+	}
+}
+
+/// An [`Operation`] which can report how much heap memory it owns, so that the memory used by a
+/// history can be measured.
+///
+/// This is also implemented for common types that operations hold (such as primitives, `String`,
+/// `Vec`, `Box`, and `Option`), so that an operation's heap size can be found by summing those of
+/// its fields. [`sized_operation!`](crate::sized_operation) implements it this way.
+pub trait SizedOperation {
+	/// Returns the approximate number of bytes of heap memory owned by this operation. This should
+	/// not include `size_of::<Self>()`.
+	fn heap_size(&self) -> usize;
 }
 
 /// An undo-redo history implemented as a list of [`Action`]s.
-#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
-pub struct UndoRedo<Op> {
-	actions: Vec<Action<Op>>,
+///
+/// The list of actions is kept in `S`, which by default is a `Vec`. See [`HistoryStorage`] for
+/// other ways of storing actions.
+///
+/// [`Self::undo`], [`Self::redo`], and [`Self::jump_to`] never allocate memory themselves, making
+/// them suitable for use on threads where allocating is not allowed (such as real-time audio
+/// threads). This holds as long as the storage's [`HistoryStorage::get`], and the operations being
+/// applied, don't allocate either. All storages provided by this crate meet this requirement.
+///
+/// # Threads
+/// A history is `Send` if its storage is, and `Sync` if its storage is. For the storages provided by
+/// this crate, that means a history is `Send` when `Op` is `Send`, and `Sync` when `Op` is `Sync`
+/// (except for [`ArcStorage`], which needs `Op` to be both for either). These guarantees are
+/// checked at compile time. For histories of trait objects, see [`SyncOperation`].
+#[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct UndoRedo<Op, S = Vec<Action<Op>>> {
+	actions: S,
+	_op: PhantomData<fn() -> Op>,
 	/// Where we are in `self.actions`, as an index that points to the "beginning" of an action's
 	/// slot - before the list of undo & redo operations.
 	///
@@ -24,13 +201,73 @@ pub struct UndoRedo<Op> {
 	/// * When undoing/reverting an action, this index points to the action *after* the one whose
 	///   operations will be reverted.
 	tapehead: usize,
+	/// The value `self.tapehead` had when [`UndoRedo::mark_save_point`] was last called, if that
+	/// point in history still exists.
+	save_point: Option<usize>,
+	/// The action which an async undo or redo was interrupted part-way through, if any.
+	#[cfg(feature = "async")]
+	partial: Option<async_ops::PartialAction>,
 }
 
 impl<Op> UndoRedo<Op> {
+	/// Creates an empty undo-redo history.
+	///
+	/// This can be used in constant contexts, such as when initializing a `static`.
+	pub const fn new() -> Self {
+		Self::with_empty_storage(Vec::new())
+	}
+}
+
+impl<Op, S> UndoRedo<Op, S> {
+	/// Creates an undo-redo history which stores its actions in `storage`, without clearing it
+	/// first. If `storage` has any actions, they are treated as unapplied.
+	///
+	/// Unlike [`Self::with_storage`], this can be used in constant contexts, such as when
+	/// initializing a `static`.
+	pub const fn with_empty_storage(storage: S) -> Self {
+		Self {
+			actions: storage,
+			_op: PhantomData,
+			tapehead: 0,
+			save_point: None,
+			#[cfg(feature = "async")]
+			partial: None,
+		}
+	}
+}
+
+impl<Op, S: HistoryStorage<Op>> UndoRedo<Op, S> {
+	/// Creates an empty undo-redo history which stores its actions in `storage`.
+	///
+	/// Any actions already in `storage` are removed.
+	pub fn with_storage(mut storage: S) -> Self {
+		storage.clear();
+		Self::with_empty_storage(storage)
+	}
+
 	/// Resets the undo-redo history to its default state.
 	pub fn clear_history(&mut self) {
 		self.actions.clear();
 		self.tapehead = 0;
+		self.save_point = None;
+	}
+
+	/// Marks the current point in history as the one where the object being operated on was last
+	/// saved.
+	pub fn mark_save_point(&mut self) {
+		self.save_point = Some(self.tapehead);
+	}
+
+	/// Returns the point in history marked by [`Self::mark_save_point`], if it still exists. This
+	/// is measured as the number of actions that were applied at that point.
+	pub fn save_point(&self) -> Option<usize> {
+		self.save_point
+	}
+
+	/// Returns `true` if the current point in history is the one marked by
+	/// [`Self::mark_save_point`].
+	pub fn is_at_save_point(&self) -> bool {
+		self.save_point == Some(self.tapehead)
 	}
 
 	/// Creates a new action at the current point in history, returning it so it can be filled with
@@ -45,14 +282,151 @@ impl<Op> UndoRedo<Op> {
 		if self.actions.len() > self.tapehead {
 			self.actions.truncate(self.tapehead);
 		}
+		// If the save point was among the deleted actions, it can no longer be reached.
+		if self
+			.save_point
+			.is_some_and(|save_point| save_point > self.tapehead)
+		{
+			self.save_point = None;
+		}
+
+		// The newest action can no longer be added to once another is created, so release any
+		// spare capacity it has.
+		if let Some(newest) = self
+			.tapehead
+			.checked_sub(1)
+			.and_then(|index| self.actions.get_mut(index))
+		{
+			newest.seal();
+		}
 
-		// TODO: Switch to `Vec::push_mut` when it becomes stable
-		self.actions.push(Action::default());
+		let evicted = self.actions.push(Action::default());
+		self.forget_evicted(evicted);
 		self.actions
-			.last_mut()
+			.get_mut(self.tapehead)
 			.expect("action should have been pushed")
 	}
 
+	/// Like [`Self::create_action`], but fails rather than removing the oldest action when the
+	/// storage is full.
+	///
+	/// # Errors
+	/// Returns `UndoRedoError::HistoryFull` if there is no room for another action. This can only
+	/// happen when there are no unapplied actions, as those would be erased to make room. The
+	/// history is left unchanged in this case.
+	///
+	/// # Panics
+	/// See [`Self::create_action`].
+	pub fn try_create_action(&mut self) -> Result<&mut Action<Op>, UndoRedoError> {
+		if self.actions.len() <= self.tapehead && self.actions.is_full() {
+			return Err(UndoRedoError::HistoryFull);
+		}
+		Ok(self.create_action())
+	}
+
+	/// Adds an already-built action at the current point in history, as [`Self::create_action`]
+	/// would, then applies it to `apply_to`.
+	///
+	/// # Panics
+	/// See [`Self::create_action`].
+	pub fn commit_action<For>(&mut self, action: Action<Op>, apply_to: &mut For)
+	where
+		Op: Operation<For>,
+	{
+		let slot = self.create_action();
+		*slot = action;
+		slot.apply(apply_to);
+		self.tapehead += 1;
+	}
+
+	/// Adds an action which has already been applied at the current point in history, as
+	/// [`Self::create_action`] would, and returns it.
+	///
+	/// # Panics
+	/// See [`Self::create_action`].
+	pub(crate) fn push_applied(&mut self, action: Action<Op>) -> &mut Action<Op> {
+		*self.create_action() = action;
+		self.tapehead += 1;
+		self.actions
+			.get_mut(self.tapehead - 1)
+			.expect("the most recently pushed action should always be available")
+	}
+
+	/// Removes up to `count` of the oldest actions from the history, returning how many were
+	/// removed. Removed actions can no longer be undone.
+	///
+	/// This is mostly useful for limiting the size of a history. Unapplied actions are never
+	/// removed, even if `count` is large enough to reach them.
+	pub fn evict_oldest(&mut self, count: usize) -> usize {
+		let evicted = self.actions.evict_oldest(count.min(self.tapehead));
+		self.forget_evicted(evicted);
+		evicted
+	}
+
+	/// Adjusts our positions in history to account for `evicted` actions having been removed
+	/// from the beginning of `self.actions`.
+	fn forget_evicted(&mut self, evicted: usize) {
+		self.tapehead = self.tapehead.saturating_sub(evicted);
+		self.save_point = self
+			.save_point
+			.and_then(|save_point| save_point.checked_sub(evicted));
+	}
+
+	/// Returns the approximate number of bytes of memory used by this history, including the
+	/// memory owned by its operations (as reported by [`SizedOperation::heap_size`]).
+	///
+	/// Actions shared with other histories (such as through [`ArcStorage`]) are counted in full.
+	pub fn approx_memory_usage(&self) -> usize
+	where
+		Op: SizedOperation,
+	{
+		size_of::<Self>()
+			+ self.actions.heap_size()
+			+ self
+				.iter_actions()
+				.map(Action::approx_heap_size)
+				.sum::<usize>()
+	}
+
+	/// Releases any memory this history holds but doesn't need, by [sealing](Action::seal) every
+	/// action and shrinking the storage.
+	///
+	/// Actions shared with other histories (such as through [`ArcStorage`]) are left as-is.
+	pub fn shrink_memory(&mut self) {
+		for index in 0..self.actions.len() {
+			if let Some(action) = self.actions.get_mut(index) {
+				action.seal();
+			}
+		}
+		self.actions.shrink_to_fit();
+	}
+
+	/// Creates a new history containing only the applied actions of this one, so that the two
+	/// can diverge from the current point in history.
+	///
+	/// When using [`ArcStorage`], the actions are shared between both histories rather than
+	/// copied, so forking is cheap.
+	///
+	/// With any other storage, this deep-copies the history: it is [cloned](Clone::clone) along
+	/// with every action and operation in it (unapplied actions are then dropped from the copy).
+	/// Use [`ArcStorage`] if histories are forked often.
+	pub fn fork(&self) -> Self
+	where
+		S: Clone,
+	{
+		let mut fork = self.clone();
+		fork.actions.truncate(fork.tapehead);
+		fork.save_point = fork
+			.save_point
+			.filter(|&save_point| save_point <= fork.tapehead);
+		fork
+	}
+
+	/// Returns an iterator over the actions in this history, from oldest to newest.
+	pub(crate) fn iter_actions(&self) -> impl Iterator<Item = &Action<Op>> {
+		(0..self.actions.len()).filter_map(|index| self.actions.get(index))
+	}
+
 	/// Applies the first unapplied action.
 	///
 	/// If no action exists to be applied, nothing happens.
@@ -66,6 +440,24 @@ impl<Op> UndoRedo<Op> {
 	pub fn redo<For>(&mut self, apply_to: &mut For) -> Result<(), UndoRedoError>
 	where
 		Op: Operation<For>,
+	{
+		self.redo_with(apply_to, &mut ())
+	}
+
+	/// Like [`Self::redo`], but passes `ctx` to each operation applied.
+	///
+	/// # Errors
+	/// See [`Self::redo`].
+	///
+	/// # Panics
+	/// See [`Self::redo`].
+	pub fn redo_with<For, Ctx>(
+		&mut self,
+		apply_to: &mut For,
+		ctx: &mut Ctx,
+	) -> Result<(), UndoRedoError>
+	where
+		Op: ContextOperation<For, Ctx>,
 	{
 		match self.actions.get(self.tapehead) {
 			Some(action) => {
@@ -74,7 +466,7 @@ impl<Op> UndoRedo<Op> {
 					.checked_add(1)
 					.expect("tapehead should not be at usize::MAX");
 
-				action.apply(apply_to);
+				action.apply_with(apply_to, ctx);
 				Ok(())
 			}
 			None => Err(UndoRedoError::NothingToDo),
@@ -89,6 +481,21 @@ impl<Op> UndoRedo<Op> {
 	pub fn undo<For>(&mut self, apply_to: &mut For) -> Result<(), UndoRedoError>
 	where
 		Op: Operation<For>,
+	{
+		self.undo_with(apply_to, &mut ())
+	}
+
+	/// Like [`Self::undo`], but passes `ctx` to each operation applied.
+	///
+	/// # Errors
+	/// See [`Self::undo`].
+	pub fn undo_with<For, Ctx>(
+		&mut self,
+		apply_to: &mut For,
+		ctx: &mut Ctx,
+	) -> Result<(), UndoRedoError>
+	where
+		Op: ContextOperation<For, Ctx>,
 	{
 		match self.tapehead.checked_sub(1) {
 			Some(new_index) => self.tapehead = new_index,
@@ -96,36 +503,220 @@ impl<Op> UndoRedo<Op> {
 		}
 
 		if let Some(action) = self.actions.get(self.tapehead) {
-			action.revert(apply_to);
+			action.revert_with(apply_to, ctx);
 			return Ok(());
 		}
 
 		Err(UndoRedoError::NothingToDo)
 	}
+
+	/// Returns the current position in history, as the number of actions which are applied.
+	pub fn position(&self) -> usize {
+		self.tapehead
+	}
+
+	/// Returns the action which would be reverted by [`Self::undo`], if any.
+	pub fn undo_action(&self) -> Option<&Action<Op>> {
+		self.actions.get(self.tapehead.checked_sub(1)?)
+	}
+
+	/// Returns the action which would be applied by [`Self::redo`], if any.
+	pub fn redo_action(&self) -> Option<&Action<Op>> {
+		self.actions.get(self.tapehead)
+	}
+
+	/// Undoes or redoes actions until the current position in history is `position` (that is,
+	/// until the first `position` actions are applied, and no others).
+	///
+	/// # Errors
+	/// Returns `UndoRedoError::OutOfBounds` if `position` is past the end of undo-redo history. No
+	/// actions are undone or redone in this case.
+	pub fn jump_to<For>(&mut self, position: usize, apply_to: &mut For) -> Result<(), UndoRedoError>
+	where
+		Op: Operation<For>,
+	{
+		self.jump_to_with(position, apply_to, &mut ())
+	}
+
+	/// Like [`Self::jump_to`], but passes `ctx` to each operation applied.
+	///
+	/// # Errors
+	/// See [`Self::jump_to`].
+	pub fn jump_to_with<For, Ctx>(
+		&mut self,
+		position: usize,
+		apply_to: &mut For,
+		ctx: &mut Ctx,
+	) -> Result<(), UndoRedoError>
+	where
+		Op: ContextOperation<For, Ctx>,
+	{
+		if position > self.actions.len() {
+			return Err(UndoRedoError::OutOfBounds);
+		}
+
+		while self.tapehead > position {
+			self.undo_with(apply_to, ctx)?;
+		}
+		while self.tapehead < position {
+			self.redo_with(apply_to, ctx)?;
+		}
+		Ok(())
+	}
+
+	/// Reverts up to `count` of the last applied actions, returning how many were reverted.
+	///
+	/// # Errors
+	/// Returns `UndoRedoError::NothingToDo` if `count` is not 0, but there is nothing to revert.
+	pub fn undo_n<For>(&mut self, count: usize, apply_to: &mut For) -> Result<usize, UndoRedoError>
+	where
+		Op: Operation<For>,
+	{
+		if count > 0 && self.tapehead == 0 {
+			return Err(UndoRedoError::NothingToDo);
+		}
+		let count = count.min(self.tapehead);
+		self.jump_to(self.tapehead - count, apply_to)?;
+		Ok(count)
+	}
+
+	/// Applies up to `count` of the first unapplied actions, returning how many were applied.
+	///
+	/// # Errors
+	/// Returns `UndoRedoError::NothingToDo` if `count` is not 0, but there is nothing to apply.
+	pub fn redo_n<For>(&mut self, count: usize, apply_to: &mut For) -> Result<usize, UndoRedoError>
+	where
+		Op: Operation<For>,
+	{
+		let unapplied = self.actions.len().saturating_sub(self.tapehead);
+		if count > 0 && unapplied == 0 {
+			return Err(UndoRedoError::NothingToDo);
+		}
+		let count = count.min(unapplied);
+		self.jump_to(self.tapehead + count, apply_to)?;
+		Ok(count)
+	}
+
+	/// Applies every applied action, in order, to `apply_to`. This is intended for rebuilding an
+	/// object from scratch (such as one newly constructed with `Default`) using only its history.
+	///
+	/// The position within the history is not changed.
+	///
+	/// After each action is applied, `progress` is called with the number of actions applied so
+	/// far and the total number that will be applied. If it returns `ControlFlow::Break`, replaying
+	/// stops early.
+	///
+	/// Skipped actions (see [`Action::set_skipped`]) are left out, but still count towards the
+	/// number applied.
+	///
+	/// Returns the number of actions that were applied.
+	pub fn replay_into<For>(
+		&self,
+		apply_to: &mut For,
+		mut progress: impl FnMut(usize, usize) -> ControlFlow<()>,
+	) -> usize
+	where
+		Op: Operation<For>,
+	{
+		let total = self.tapehead;
+		for (index, action) in self.iter_actions().take(total).enumerate() {
+			action.apply(apply_to);
+			if progress(index + 1, total).is_break() {
+				return index + 1;
+			}
+		}
+		total
+	}
+
+	/// Sets whether the action at `index` is skipped (see [`Action::set_skipped`]), keeping
+	/// `apply_to` consistent with the history: if the action is currently applied, skipping it
+	/// reverts it, and un-skipping it applies it again.
+	///
+	/// Since this reverts or applies the action out of order, it is only correct if no later
+	/// applied action depends on its changes.
+	///
+	/// # Errors
+	/// Returns `UndoRedoError::OutOfBounds` if there is no action at `index`, or if it cannot be
+	/// modified by the history's storage.
+	pub fn set_skipped<For>(
+		&mut self,
+		index: usize,
+		skipped: bool,
+		apply_to: &mut For,
+	) -> Result<(), UndoRedoError>
+	where
+		Op: Operation<For>,
+	{
+		let applied = index < self.tapehead;
+		let action = self
+			.actions
+			.get_mut(index)
+			.ok_or(UndoRedoError::OutOfBounds)?;
+		if action.skipped == skipped {
+			return Ok(());
+		}
+
+		if applied && skipped {
+			action.revert(apply_to);
+		}
+		action.skipped = skipped;
+		if applied && !skipped {
+			action.apply(apply_to);
+		}
+		Ok(())
+	}
 }
 
-// `Op` is only used inside of `Vec`s, so a "default" state would not generate any `Op`. As the
-// `Default` derive macro assumes that we want a trait bound on `Op` no matter what, we have to
+// `Op` is only used inside of the storage, so cloning only requires that the storage can be cloned.
+// As the `Clone` derive macro assumes that we want a trait bound on `Op` no matter what, we have to
+// manually implement `Clone`.
+impl<Op, S: Clone> Clone for UndoRedo<Op, S> {
+	fn clone(&self) -> Self {
+		Self {
+			actions: self.actions.clone(),
+			_op: PhantomData,
+			tapehead: self.tapehead,
+			save_point: self.save_point,
+			#[cfg(feature = "async")]
+			partial: self.partial,
+		}
+	}
+}
+
+// `Op` is only used inside of the storage, so a "default" state would not generate any `Op`. As
+// the `Default` derive macro assumes that we want a trait bound on `Op` no matter what, we have to
 // manually implement `Default`.
-impl<Op> Default for UndoRedo<Op> {
+impl<Op, S: Default> Default for UndoRedo<Op, S> {
 	fn default() -> Self {
 		Self {
 			actions: Default::default(),
+			_op: PhantomData,
 			tapehead: Default::default(),
+			save_point: Default::default(),
+			#[cfg(feature = "async")]
+			partial: None,
 		}
 	}
 }
 
 /// An error indicating an issue with performing an undo or redo.
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum UndoRedoError {
 	NothingToDo,
+	OutOfBounds,
+	HistoryFull,
+	HasDependents,
+	DoesNotCommute,
 }
 
 impl fmt::Display for UndoRedoError {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		match self {
 			Self::NothingToDo => write!(f, "nothing to perform"),
+			Self::OutOfBounds => write!(f, "position is outside of undo-redo history"),
+			Self::HistoryFull => write!(f, "undo-redo history is full"),
+			Self::HasDependents => write!(f, "later actions depend on this action"),
+			Self::DoesNotCommute => write!(f, "actions cannot be reordered past each other"),
 		}
 	}
 }
@@ -137,8 +728,10 @@ impl error::Error for UndoRedoError {}
 #[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Action<Op> {
 	name: Option<String>,
-	apply_ops: Vec<Op>,
-	revert_ops: Vec<Op>,
+	apply_ops: OpList<Op>,
+	revert_ops: OpList<Op>,
+	/// If `true`, applying or reverting this action does nothing.
+	skipped: bool,
 }
 
 impl<Op> Action<Op> {
@@ -167,22 +760,141 @@ impl<Op> Action<Op> {
 		self
 	}
 
+	/// Returns a copy of this action with its operations swapped, so that applying the copy
+	/// reverts this action and vice versa. The copy has no name, and is skipped if this action is.
+	pub fn inverse(&self) -> Self
+	where
+		Op: Clone,
+	{
+		Self {
+			name: None,
+			apply_ops: self.revert_ops.clone(),
+			revert_ops: self.apply_ops.clone(),
+			skipped: self.skipped,
+		}
+	}
+
+	/// Returns a copy of this action with `f` applied to each of its operations, in order (all of
+	/// the redo operations first).
+	pub fn map_ops<NewOp>(self, mut f: impl FnMut(Op) -> NewOp) -> Action<NewOp> {
+		Action {
+			name: self.name,
+			apply_ops: self
+				.apply_ops
+				.into_vec()
+				.into_iter()
+				.map(&mut f)
+				.collect::<Vec<_>>()
+				.into(),
+			revert_ops: self
+				.revert_ops
+				.into_vec()
+				.into_iter()
+				.map(f)
+				.collect::<Vec<_>>()
+				.into(),
+			skipped: self.skipped,
+		}
+	}
+
+	/// Returns `true` if this action is skipped. See [`Self::set_skipped`].
+	pub fn is_skipped(&self) -> bool {
+		self.skipped
+	}
+
+	/// Sets whether this action is skipped. A skipped action stays in history, but applying or
+	/// reverting it does nothing, so it is left out when undoing, redoing, jumping, or replaying.
+	///
+	/// To skip an action that is already applied, while also reverting its changes, use
+	/// [`UndoRedo::set_skipped`].
+	pub fn set_skipped(&mut self, skipped: bool) -> &mut Self {
+		self.skipped = skipped;
+		self
+	}
+
+	/// Releases any memory this action holds but doesn't need, as it would if no further
+	/// operations were going to be added to it. Its operations are moved into boxed slices, which
+	/// hold no spare capacity; adding more operations afterwards moves them back out.
+	///
+	/// This is done automatically for an action when another action is created after it, as an
+	/// action cannot be added to once it is no longer the newest one.
+	pub fn seal(&mut self) {
+		if let Some(name) = &mut self.name {
+			name.shrink_to_fit();
+		}
+		self.apply_ops.seal();
+		self.revert_ops.seal();
+	}
+
+	/// Returns the approximate number of bytes of heap memory owned by this action, including the
+	/// memory owned by its operations (as reported by [`SizedOperation::heap_size`]).
+	pub fn approx_heap_size(&self) -> usize
+	where
+		Op: SizedOperation,
+	{
+		let name = self.name.as_ref().map_or(0, String::capacity);
+		let ops = self
+			.apply_ops
+			.as_slice()
+			.iter()
+			.chain(self.revert_ops.as_slice());
+		name + self.apply_ops.heap_size()
+			+ self.revert_ops.heap_size()
+			+ ops.map(SizedOperation::heap_size).sum::<usize>()
+	}
+
 	pub fn apply<For>(&self, apply_to: &mut For)
 	where
 		Op: Operation<For>,
 	{
-		self.apply_ops.iter().for_each(|o| o.apply(apply_to));
+		Op::apply_batch(self.apply_ops(), apply_to);
 	}
 
 	pub fn revert<For>(&self, apply_to: &mut For)
 	where
 		Op: Operation<For>,
 	{
-		self.revert_ops.iter().for_each(|o| o.apply(apply_to));
+		Op::apply_batch(self.revert_ops(), apply_to);
+	}
+
+	/// Like [`Self::apply`], but passes `ctx` to each operation.
+	pub fn apply_with<For, Ctx>(&self, apply_to: &mut For, ctx: &mut Ctx)
+	where
+		Op: ContextOperation<For, Ctx>,
+	{
+		Op::apply_batch_with(self.apply_ops(), apply_to, ctx);
+	}
+
+	/// Like [`Self::revert`], but passes `ctx` to each operation.
+	pub fn revert_with<For, Ctx>(&self, apply_to: &mut For, ctx: &mut Ctx)
+	where
+		Op: ContextOperation<For, Ctx>,
+	{
+		Op::apply_batch_with(self.revert_ops(), apply_to, ctx);
+	}
+
+	/// Returns the operations performed when applying this action, which are none if it is
+	/// skipped.
+	pub(crate) fn apply_ops(&self) -> &[Op] {
+		if self.skipped {
+			&[]
+		} else {
+			self.apply_ops.as_slice()
+		}
+	}
+
+	/// Returns the operations performed when reverting this action, which are none if it is
+	/// skipped.
+	pub(crate) fn revert_ops(&self) -> &[Op] {
+		if self.skipped {
+			&[]
+		} else {
+			self.revert_ops.as_slice()
+		}
 	}
 }
 
-// `Op` is only used inside of `Vec`s, so a "default" state would not generate any `Op`. As the
+// `Op` is only used inside of `OpList`s, so a "default" state would not generate any `Op`. As the
 // `Default` derive macro assumes that we want a trait bound on `Op` no matter what, we have to
 // manually implement `Default`.
 impl<Op> Default for Action<Op> {
@@ -191,6 +903,7 @@ impl<Op> Default for Action<Op> {
 			name: Default::default(),
 			apply_ops: Default::default(),
 			revert_ops: Default::default(),
+			skipped: false,
 		}
 	}
 }
@@ -0,0 +1,486 @@
+/// Declares an enum of operations on `For`, where each variant holds one operation, and implements
+/// [`Operation<For>`](crate::Operation) for it by applying whichever operation it holds.
+///
+/// This saves writing out a `match` over every variant by hand. The enum is written as usual,
+/// except that `for For` follows its name, and each variant must be a tuple variant with a single
+/// field:
+///
+/// ```
+/// use michis_undo_redo::{Operation, operation_enum};
+///
+/// struct Insert(String);
+/// # impl Operation<String> for Insert {
+/// #     fn apply(&self, item: &mut String) {
+/// #         item.push_str(&self.0);
+/// #     }
+/// # }
+/// struct Pop;
+/// # impl Operation<String> for Pop {
+/// #     fn apply(&self, item: &mut String) {
+/// #         item.pop();
+/// #     }
+/// # }
+///
+/// operation_enum! {
+///     /// An operation on a line of text.
+///     pub enum TextOp for String {
+///         Insert(Insert),
+///         Pop(Pop),
+///     }
+/// }
+///
+/// let mut line = String::from("Hi");
+/// TextOp::from(Insert(String::from("!?"))).apply(&mut line);
+/// TextOp::Pop(Pop).apply(&mut line);
+/// assert_eq!(line, "Hi!");
+/// ```
+///
+/// `From` is implemented for each variant's operation type, so each variant must hold a different
+/// type.
+///
+/// This can also combine operations on different parts of `For` (such as those of separate
+/// subsystems) into one operation type for a single history. A variant followed by `=> field`
+/// applies its operation to that field of `For`, rather than to `For` itself:
+///
+/// ```
+/// # use michis_undo_redo::{Operation, operation_enum};
+/// struct App {
+///     text: String,
+///     layers: Vec<String>,
+///     width: u32,
+/// }
+///
+/// struct TextOp(char);
+/// # impl Operation<String> for TextOp {
+/// #     fn apply(&self, item: &mut String) {
+/// #         item.push(self.0);
+/// #     }
+/// # }
+/// struct LayerOp(String);
+/// # impl Operation<Vec<String>> for LayerOp {
+/// #     fn apply(&self, item: &mut Vec<String>) {
+/// #         item.push(self.0.clone());
+/// #     }
+/// # }
+/// struct ResizeOp(u32);
+/// # impl Operation<App> for ResizeOp {
+/// #     fn apply(&self, item: &mut App) {
+/// #         item.width = self.0;
+/// #     }
+/// # }
+///
+/// operation_enum! {
+///     pub enum AppOp for App {
+///         Text(TextOp) => text,
+///         Layer(LayerOp) => layers,
+///         Resize(ResizeOp),
+///     }
+/// }
+///
+/// let mut app = App { text: String::new(), layers: Vec::new(), width: 0 };
+/// AppOp::from(TextOp('a')).apply(&mut app);
+/// AppOp::from(LayerOp(String::from("Background"))).apply(&mut app);
+/// AppOp::from(ResizeOp(640)).apply(&mut app);
+/// assert_eq!((app.text.as_str(), app.layers.len(), app.width), ("a", 1, 640));
+/// ```
+#[macro_export]
+macro_rules! operation_enum {
+	(
+		$(#[$meta:meta])*
+		$vis:vis enum $name:ident for $for:ty {
+			$(
+				$(#[$variant_meta:meta])*
+				$variant:ident($op:ty) $(=> $field:ident)?
+			),+ $(,)?
+		}
+	) => {
+		$(#[$meta])*
+		$vis enum $name {
+			$(
+				$(#[$variant_meta])*
+				$variant($op),
+			)+
+		}
+
+		impl $crate::Operation<$for> for $name {
+			fn apply(&self, item: &mut $for) {
+				match self {
+					$(Self::$variant(op) => $crate::Operation::apply(op, &mut (*item)$(.$field)?),)+
+				}
+			}
+		}
+
+		$(
+			impl ::core::convert::From<$op> for $name {
+				fn from(op: $op) -> Self {
+					Self::$variant(op)
+				}
+			}
+		)+
+	};
+}
+
+/// Implements [`InvertibleOperation<For>`](crate::InvertibleOperation) for common shapes of
+/// operation. The operation must implement `Clone`.
+///
+/// For a struct holding a value from before the operation and one from after it, the inverse
+/// swaps them:
+///
+/// ```
+/// use michis_undo_redo::{InvertibleOperation, invertible_operation};
+/// # use michis_undo_redo::Operation;
+///
+/// # struct Person {
+/// #     name: String,
+/// # }
+/// #[derive(Clone)]
+/// struct SetName {
+///     old: String,
+///     new: String,
+/// }
+/// # impl Operation<Person> for SetName {
+/// #     fn apply(&self, item: &mut Person) {
+/// #         item.name = self.new.clone();
+/// #     }
+/// # }
+///
+/// invertible_operation! {
+///     impl InvertibleOperation<Person> for SetName { swap(old, new) }
+/// }
+///
+/// let rename = SetName { old: String::from("Ann"), new: String::from("Bea") };
+/// assert_eq!(rename.inverse().new, "Ann");
+/// ```
+///
+/// For an enum whose variants come in opposing pairs, each holding the same type, the inverse
+/// turns each variant into its opposite. Every variant must be part of a pair:
+///
+/// ```
+/// # use michis_undo_redo::{InvertibleOperation, Operation, invertible_operation};
+/// #[derive(Clone, Debug, PartialEq)]
+/// enum TextOp {
+///     Insert(String),
+///     Remove(String),
+///     Indent(usize),
+///     Dedent(usize),
+/// }
+/// # impl Operation<String> for TextOp {
+/// #     fn apply(&self, _: &mut String) {}
+/// # }
+///
+/// invertible_operation! {
+///     impl InvertibleOperation<String> for TextOp { Insert <=> Remove, Indent <=> Dedent }
+/// }
+///
+/// assert_eq!(TextOp::Indent(4).inverse(), TextOp::Dedent(4));
+/// assert_eq!(TextOp::Remove(String::from("a")).inverse(), TextOp::Insert(String::from("a")));
+/// ```
+#[macro_export]
+macro_rules! invertible_operation {
+	(impl InvertibleOperation<$for:ty> for $op:ty { swap($a:ident, $b:ident) }) => {
+		impl $crate::InvertibleOperation<$for> for $op {
+			fn inverse(&self) -> Self {
+				let mut inverse = ::core::clone::Clone::clone(self);
+				::core::mem::swap(&mut inverse.$a, &mut inverse.$b);
+				inverse
+			}
+		}
+	};
+	(impl InvertibleOperation<$for:ty> for $op:ty { $($a:ident <=> $b:ident),+ $(,)? }) => {
+		impl $crate::InvertibleOperation<$for> for $op {
+			fn inverse(&self) -> Self {
+				match self {
+					$(
+						Self::$a(value) => Self::$b(::core::clone::Clone::clone(value)),
+						Self::$b(value) => Self::$a(::core::clone::Clone::clone(value)),
+					)+
+				}
+			}
+		}
+	};
+}
+
+/// Declares an enum of operations which each set one field of `For`, along with functions which
+/// record and apply them.
+///
+/// Each variant is written as the field it sets, its type, and the name of a function to generate:
+///
+/// ```
+/// use michis_undo_redo::{UndoRedo, undoable_fields};
+///
+/// struct Settings {
+///     volume: f32,
+///     name: String,
+/// }
+///
+/// undoable_fields! {
+///     /// An operation on a `Settings`.
+///     pub enum SettingsOp for Settings {
+///         Volume(volume: f32) => set_volume,
+///         Name(name: String) => set_name,
+///     }
+/// }
+///
+/// let mut history = UndoRedo::new();
+/// let mut settings = Settings { volume: 1.0, name: String::from("Default") };
+/// SettingsOp::set_volume(&mut history, &mut settings, 0.5);
+/// SettingsOp::set_name(&mut history, &mut settings, String::from("Quiet"));
+/// assert_eq!((settings.volume, settings.name.as_str()), (0.5, "Quiet"));
+///
+/// history.jump_to(0, &mut settings).unwrap();
+/// assert_eq!((settings.volume, settings.name.as_str()), (1.0, "Default"));
+/// ```
+///
+/// Each variant holds the field's value from before and after the operation, as `old` and `new`.
+/// The enum implements [`Operation<For>`](crate::Operation) (by cloning `new` into the field) and
+/// [`InvertibleOperation<For>`](crate::InvertibleOperation). Each generated function (such as
+/// `SettingsOp::set_volume(&mut history, &mut settings, 0.5)`) captures the field's current
+/// value, then sets it through [`UndoRedo::commit_invertible`](crate::UndoRedo::commit_invertible).
+///
+/// Every field's type must implement `Clone`.
+#[macro_export]
+macro_rules! undoable_fields {
+	(
+		$(#[$meta:meta])*
+		$vis:vis enum $name:ident for $for:ty {
+			$(
+				$(#[$variant_meta:meta])*
+				$variant:ident($field:ident: $field_ty:ty) => $setter:ident
+			),+ $(,)?
+		}
+	) => {
+		$(#[$meta])*
+		$vis enum $name {
+			$(
+				$(#[$variant_meta])*
+				$variant {
+					/// The field's value before the operation.
+					old: $field_ty,
+					/// The field's value after the operation.
+					new: $field_ty,
+				},
+			)+
+		}
+
+		impl $crate::Operation<$for> for $name {
+			fn apply(&self, item: &mut $for) {
+				match self {
+					$(
+						Self::$variant { new, .. } => {
+							item.$field = ::core::clone::Clone::clone(new);
+						}
+					)+
+				}
+			}
+		}
+
+		impl $crate::InvertibleOperation<$for> for $name {
+			fn inverse(&self) -> Self {
+				match self {
+					$(
+						Self::$variant { old, new } => Self::$variant {
+							old: ::core::clone::Clone::clone(new),
+							new: ::core::clone::Clone::clone(old),
+						},
+					)+
+				}
+			}
+		}
+
+		impl $name {
+			$(
+				/// Sets the field to `value`, recording it as an action in `history`.
+				///
+				/// The action is returned so that it can be named.
+				$vis fn $setter<'history, S: $crate::HistoryStorage<Self>>(
+					history: &'history mut $crate::UndoRedo<Self, S>,
+					target: &mut $for,
+					value: $field_ty,
+				) -> &'history mut $crate::Action<Self> {
+					let old = ::core::clone::Clone::clone(&target.$field);
+					history.commit_invertible(Self::$variant { old, new: value }, target)
+				}
+			)+
+		}
+	};
+}
+
+/// Implements [`MergeableOperation`](crate::MergeableOperation) for a struct, by merging each of
+/// its fields with a chosen strategy. Every field must be listed:
+///
+/// ```
+/// use michis_undo_redo::{MergeableOperation, mergeable_operation};
+///
+/// struct SetText {
+///     target: u32,
+///     old: String,
+///     new: String,
+/// }
+///
+/// mergeable_operation! {
+///     impl MergeableOperation for SetText {
+///         target: same,
+///         old: keep_first,
+///         new: keep_last,
+///     }
+/// }
+///
+/// let set = |target, old: &str, new: &str| SetText { target, old: old.into(), new: new.into() };
+/// let mut typing = set(1, "", "a");
+/// assert!(typing.merge(set(1, "a", "ab")).is_ok());
+/// assert_eq!((typing.old.as_str(), typing.new.as_str()), ("", "ab"));
+/// // Edits to another target aren't merged.
+/// assert!(typing.merge(set(2, "", "b")).is_err());
+/// ```
+///
+/// The strategies are:
+///
+/// * `same`: The field must be equal (by `PartialEq`) in both operations for them to be merged.
+/// * `keep_first`: The merged operation keeps this operation's value.
+/// * `keep_last`: The merged operation takes the next operation's value.
+/// * `concat`: The next operation's value is appended to this operation's value (by `Extend`).
+#[macro_export]
+macro_rules! mergeable_operation {
+	(impl MergeableOperation for $op:ty { $($field:ident: $strategy:ident),+ $(,)? }) => {
+		impl $crate::MergeableOperation for $op {
+			fn merge(&mut self, next: Self) -> ::core::result::Result<(), Self> {
+				if !(true $(&& $crate::mergeable_operation!(@can_merge $strategy, self.$field, next.$field))+) {
+					return ::core::result::Result::Err(next);
+				}
+				let Self { $($field),+ } = next;
+				$($crate::mergeable_operation!(@merge $strategy, self.$field, $field);)+
+				::core::result::Result::Ok(())
+			}
+		}
+	};
+	(@can_merge same, $this:expr, $next:expr) => {
+		$this == $next
+	};
+	(@can_merge $strategy:ident, $this:expr, $next:expr) => {
+		true
+	};
+	(@merge same, $this:expr, $next:expr) => {
+		::core::mem::drop($next)
+	};
+	(@merge keep_first, $this:expr, $next:expr) => {
+		::core::mem::drop($next)
+	};
+	(@merge keep_last, $this:expr, $next:expr) => {
+		$this = $next
+	};
+	(@merge concat, $this:expr, $next:expr) => {
+		::core::iter::Extend::extend(&mut $this, $next)
+	};
+}
+
+/// Implements [`SizedOperation`](crate::SizedOperation) for a struct or enum, by summing the heap
+/// sizes of the listed fields. Fields that own no heap memory may be left out.
+///
+/// For a struct, list its fields:
+///
+/// ```
+/// use michis_undo_redo::{SizedOperation, sized_operation};
+///
+/// struct SetText {
+///     target: u32,
+///     old: String,
+///     new: String,
+/// }
+///
+/// sized_operation! {
+///     impl SizedOperation for SetText { old, new }
+/// }
+///
+/// let set = SetText { target: 1, old: String::with_capacity(8), new: String::with_capacity(16) };
+/// assert_eq!(set.heap_size(), 24);
+/// ```
+///
+/// For an enum, list every variant after `match`, naming each field of a tuple variant and the
+/// relevant fields of a struct variant:
+///
+/// ```
+/// # use michis_undo_redo::{SizedOperation, sized_operation};
+/// enum TextOp {
+///     Insert(usize, String),
+///     Replace { start: usize, end: usize, text: String },
+///     Clear,
+/// }
+///
+/// sized_operation! {
+///     impl SizedOperation for TextOp {
+///         match {
+///             Insert(index, text),
+///             Replace { text },
+///             Clear,
+///         }
+///     }
+/// }
+///
+/// assert_eq!(TextOp::Insert(0, String::with_capacity(4)).heap_size(), 4);
+/// let replace = TextOp::Replace { start: 0, end: 2, text: String::with_capacity(8) };
+/// assert_eq!(replace.heap_size(), 8);
+/// assert_eq!(TextOp::Clear.heap_size(), 0);
+/// ```
+#[macro_export]
+macro_rules! sized_operation {
+	(
+		impl SizedOperation for $op:ty {
+			match {
+				$(
+					$variant:ident
+					$(($($tuple_field:ident),* $(,)?))?
+					$({$($struct_field:ident),* $(,)?})?
+				),+ $(,)?
+			}
+		}
+	) => {
+		impl $crate::SizedOperation for $op {
+			fn heap_size(&self) -> usize {
+				match self {
+					$(
+						Self::$variant $(($($tuple_field),*))? $({$($struct_field,)* ..})? => {
+							0 $($(+ $crate::SizedOperation::heap_size($tuple_field))*)?
+								$($(+ $crate::SizedOperation::heap_size($struct_field))*)?
+						}
+					)+
+				}
+			}
+		}
+	};
+	(impl SizedOperation for $op:ty { $($field:ident),* $(,)? }) => {
+		impl $crate::SizedOperation for $op {
+			fn heap_size(&self) -> usize {
+				0 $(+ $crate::SizedOperation::heap_size(&self.$field))*
+			}
+		}
+	};
+}
+
+/// Creates a projection function for a [`Lens`](crate::Lens), from a type and a path to one of
+/// its fields:
+///
+/// ```
+/// # use michis_undo_redo::{Lens, UndoRedo, lens};
+/// # struct Audio {
+/// #     volume: f32,
+/// # }
+/// # struct Settings {
+/// #     audio: Audio,
+/// # }
+/// # let mut settings = Settings { audio: Audio { volume: 1.0 } };
+/// # let mut history = UndoRedo::new();
+/// let project = lens!(Settings => audio.volume);
+/// history.commit_invertible(Lens::set(&mut settings, project, 0.5), &mut settings);
+/// # assert_eq!(settings.audio.volume, 0.5);
+/// # history.undo(&mut settings).unwrap();
+/// # assert_eq!(settings.audio.volume, 1.0);
+/// ```
+///
+/// The result is a `fn(&mut Settings) -> &mut f32`, which returns the field at that path.
+#[macro_export]
+macro_rules! lens {
+	($outer:ty => $($field:tt).+) => {{
+		let project: fn(&mut $outer) -> &mut _ = |outer| &mut outer.$($field).+;
+		project
+	}};
+}
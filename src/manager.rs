@@ -0,0 +1,939 @@
+use alloc::{boxed::Box, collections::BTreeMap, string::String, vec::Vec};
+use core::fmt;
+
+use crate::{
+	Action, HistoryState, HistoryStorage, Operation, ResolveError, SizedOperation, TargetResolver,
+	UndoRedo, UndoRedoError,
+};
+
+/// The histories of many documents (such as the files open in a multi-window application), one of
+/// which may be focused.
+///
+/// Undoing and redoing act on the focused document's history by default, so that an application's
+/// menu can be driven by the manager alone. See [`UndoScope`] for the alternative. The documents
+/// themselves are kept elsewhere, and found through a [`TargetResolver`] by their IDs.
+///
+/// An action spanning several documents can be committed with [`Self::commit_compound`], so that it
+/// is undone and redone in all of them at once.
+///
+/// Each document's history can be limited to a number of actions (see [`Self::set_action_limit`]),
+/// and all of them together to an amount of memory (see [`Self::set_memory_budget`]).
+///
+/// Subscribers (see [`Self::subscribe`]) are told whenever any document's history changes.
+pub struct UndoRedoManager<Id, Op, S = Vec<Action<Op>>> {
+	histories: BTreeMap<Id, UndoRedo<Op, S>>,
+	focused: Option<Id>,
+	scope: UndoScope,
+	/// The documents of each applied action (or actions committed together), across every
+	/// history, in the order they were applied.
+	applied: Vec<Entry<Id>>,
+	/// The documents of each undone action (or actions committed together), across every history,
+	/// in the order they were undone.
+	undone: Vec<Entry<Id>>,
+	/// The most applied actions each document may have, for those which are limited.
+	limits: BTreeMap<Id, usize>,
+	budget: Option<MemoryBudget<Op, S>>,
+	/// Every document, from the least recently used to the most recently used.
+	recent: Vec<Id>,
+	subscribers: Vec<Subscriber<Id>>,
+}
+
+/// Which document a [`UndoRedoManager`] undoes and redoes.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub enum UndoScope {
+	/// Undoing and redoing act on the focused document.
+	#[default]
+	Focused,
+	/// Undoing acts on whichever document was changed most recently, and redoing on whichever
+	/// document was undone most recently, regardless of which document is focused.
+	Global,
+}
+
+#[derive(Debug)]
+struct MemoryBudget<Op, S> {
+	bytes: usize,
+	size_of: fn(&UndoRedo<Op, S>) -> usize,
+	action_size_of: fn(&Action<Op>) -> usize,
+}
+
+/// The documents changed by one entry of a [`UndoRedoManager`]'s applied or undone actions, each
+/// with the position of its history just after the change.
+///
+/// In an undone entry, a position of 0 (which no history can be at after a change) marks a
+/// document which can no longer redo its part, so that the other documents can't redo theirs
+/// without it.
+type Entry<Id> = Vec<(Id, usize)>;
+
+/// A callback registered with [`UndoRedoManager::subscribe`].
+type Subscriber<Id> = Box<dyn FnMut(&Id, &HistoryState)>;
+
+// Subscribers are closures, which can't be formatted. As the `Debug` derive macro assumes that
+// every field can be, we have to manually implement `Debug`.
+impl<Id: fmt::Debug, Op: fmt::Debug, S: fmt::Debug> fmt::Debug for UndoRedoManager<Id, Op, S> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("UndoRedoManager")
+			.field("histories", &self.histories)
+			.field("focused", &self.focused)
+			.field("scope", &self.scope)
+			.field("applied", &self.applied)
+			.field("undone", &self.undone)
+			.field("limits", &self.limits)
+			.field("budget", &self.budget)
+			.field("recent", &self.recent)
+			.field("subscribers", &self.subscribers.len())
+			.finish()
+	}
+}
+
+// A manager with no histories holds no `Op`s or `S`s. As the `Default` derive macro assumes that
+// we want trait bounds on them no matter what, we have to manually implement `Default`.
+impl<Id, Op, S> Default for UndoRedoManager<Id, Op, S> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<Id, Op, S> UndoRedoManager<Id, Op, S> {
+	/// Creates a manager with no documents.
+	pub const fn new() -> Self {
+		Self {
+			histories: BTreeMap::new(),
+			focused: None,
+			scope: UndoScope::Focused,
+			applied: Vec::new(),
+			undone: Vec::new(),
+			limits: BTreeMap::new(),
+			budget: None,
+			recent: Vec::new(),
+			subscribers: Vec::new(),
+		}
+	}
+
+	/// Registers `subscriber` to be called with a document's ID and the state of its history,
+	/// whenever that history is changed through this manager.
+	pub fn subscribe(&mut self, subscriber: impl FnMut(&Id, &HistoryState) + 'static) {
+		self.subscribers.push(Box::new(subscriber));
+	}
+
+	/// Returns the ID of the focused document, if any.
+	pub fn focused(&self) -> Option<&Id> {
+		self.focused.as_ref()
+	}
+
+	/// Returns which document undoing and redoing act on.
+	pub fn scope(&self) -> UndoScope {
+		self.scope
+	}
+
+	/// Sets which document undoing and redoing act on. This can be changed at any time.
+	pub fn set_scope(&mut self, scope: UndoScope) {
+		self.scope = scope;
+	}
+
+	/// Returns the IDs of every document, in order.
+	pub fn ids(&self) -> impl Iterator<Item = &Id> {
+		self.histories.keys()
+	}
+}
+
+impl<Id: Ord + Clone, Op, S: HistoryStorage<Op>> UndoRedoManager<Id, Op, S> {
+	/// Adds a document with the history `history`, returning the history it replaces, if any.
+	///
+	/// Actions in a replaced history are no longer undone or redone along with actions in other
+	/// documents, as if it had been [removed](Self::remove).
+	pub fn insert(&mut self, id: Id, history: UndoRedo<Op, S>) -> Option<UndoRedo<Op, S>> {
+		self.touch(&id);
+		self.forget_document(&id);
+		let replaced = self.histories.insert(id.clone(), history);
+		self.enforce_action_limit(&id);
+		self.enforce_memory_budget();
+		replaced
+	}
+
+	/// Removes a document, returning its history. If it was focused, no document is focused
+	/// afterwards.
+	///
+	/// Actions in other documents which were committed along with its applied actions (see
+	/// [`Self::commit_compound`]) can still be undone, but those committed along with its undone
+	/// actions can no longer be redone.
+	pub fn remove(&mut self, id: &Id) -> Option<UndoRedo<Op, S>> {
+		if self.focused.as_ref() == Some(id) {
+			self.focused = None;
+		}
+		self.forget_document(id);
+		self.limits.remove(id);
+		self.recent.retain(|recent| recent != id);
+		self.histories.remove(id)
+	}
+
+	/// Returns a document's history.
+	pub fn get(&self, id: &Id) -> Option<&UndoRedo<Op, S>> {
+		self.histories.get(id)
+	}
+
+	/// Returns a document's history mutably. Changes made through it are not reported to
+	/// subscribers, or taken into account by [`UndoScope::Global`].
+	pub fn get_mut(&mut self, id: &Id) -> Option<&mut UndoRedo<Op, S>> {
+		self.histories.get_mut(id)
+	}
+
+	/// Focuses the document `id`, or no document if `id` is `None`. Returns `false` (leaving the
+	/// focus unchanged) if there is no such document.
+	pub fn focus(&mut self, id: Option<Id>) -> bool {
+		if id
+			.as_ref()
+			.is_some_and(|id| !self.histories.contains_key(id))
+		{
+			return false;
+		}
+		if let Some(id) = &id {
+			self.touch(id);
+		}
+		self.focused = id;
+		true
+	}
+
+	/// Returns the focused document's history, if any.
+	pub fn focused_history(&self) -> Option<&UndoRedo<Op, S>> {
+		self.histories.get(self.focused.as_ref()?)
+	}
+
+	/// Returns a summary of the focused document's history, if any.
+	pub fn state(&self) -> Option<HistoryState> {
+		self.focused_history().map(UndoRedo::state)
+	}
+
+	/// Returns the most applied actions the document `id` may have, if it is limited.
+	pub fn action_limit(&self, id: &Id) -> Option<usize> {
+		self.limits.get(id).copied()
+	}
+
+	/// Limits the document `id` to `limit` applied actions, or removes its limit if `limit` is
+	/// `None`. A `limit` of 0 is treated as 1, as a history must be able to hold its newest action.
+	/// Returns `false` (changing nothing) if there is no such document.
+	///
+	/// Whenever the document has more applied actions than that, the oldest are removed, as by
+	/// [`UndoRedo::evict_oldest`]. Unapplied actions are never removed.
+	pub fn set_action_limit(&mut self, id: &Id, limit: Option<usize>) -> bool {
+		if !self.histories.contains_key(id) {
+			return false;
+		}
+		match limit {
+			Some(limit) => self.limits.insert(id.clone(), limit.max(1)),
+			None => self.limits.remove(id),
+		};
+		self.enforce_action_limit(id);
+		true
+	}
+
+	/// Limits every document's history together to `bytes` of memory, as measured by
+	/// [`UndoRedo::approx_memory_usage`].
+	///
+	/// Whenever they use more than that, the oldest actions are removed from the least recently
+	/// used document (by committing, undoing, redoing or focusing) until they fit, then from the
+	/// next least recently used, and so on. Each document keeps at least its newest applied
+	/// action, and unapplied actions are never removed.
+	pub fn set_memory_budget(&mut self, bytes: usize)
+	where
+		Op: SizedOperation,
+	{
+		self.budget = Some(MemoryBudget {
+			bytes,
+			size_of: UndoRedo::approx_memory_usage,
+			action_size_of: Action::approx_heap_size,
+		});
+		self.enforce_memory_budget();
+	}
+
+	/// Removes the limit set by [`Self::set_memory_budget`].
+	pub fn clear_memory_budget(&mut self) {
+		self.budget = None;
+	}
+
+	/// Returns the memory used by every document's history, as measured by
+	/// [`UndoRedo::approx_memory_usage`], or `None` if there is no memory budget.
+	pub fn memory_usage(&self) -> Option<usize> {
+		let budget = self.budget.as_ref()?;
+		Some(self.histories.values().map(budget.size_of).sum())
+	}
+
+	/// Removes the oldest actions of the document `id` until it fits within its limit.
+	fn enforce_action_limit(&mut self, id: &Id) {
+		let (Some(limit), Some(history)) = (self.limits.get(id), self.histories.get_mut(id)) else {
+			return;
+		};
+		let evicted = history.evict_oldest(history.position().saturating_sub(*limit));
+		if evicted > 0 {
+			self.forget_evicted(id, evicted);
+			self.notify(id);
+		}
+	}
+
+	/// Removes the oldest actions of the least recently used documents until every document fits
+	/// within the memory budget.
+	fn enforce_memory_budget(&mut self) {
+		let Some(budget) = self.budget.as_ref() else {
+			return;
+		};
+		let (bytes, action_size_of) = (budget.bytes, budget.action_size_of);
+		let mut used: usize = self.histories.values().map(budget.size_of).sum();
+		for id in self.recent.clone() {
+			if used <= bytes {
+				break;
+			}
+			let Some(history) = self.histories.get_mut(&id) else {
+				continue;
+			};
+			// Work out how many of the oldest actions need to go, keeping a running total rather
+			// than measuring the whole history again after each one.
+			let mut count = 0;
+			for action in history
+				.iter_actions()
+				.take(history.position().saturating_sub(1))
+			{
+				if used <= bytes {
+					break;
+				}
+				used = used.saturating_sub(action_size_of(action));
+				count += 1;
+			}
+			let evicted = history.evict_oldest(count);
+			if evicted > 0 {
+				self.forget_evicted(&id, evicted);
+				self.notify(&id);
+			}
+		}
+	}
+
+	/// Marks the document `id` as the most recently used.
+	fn touch(&mut self, id: &Id) {
+		self.recent.retain(|recent| recent != id);
+		self.recent.push(id.clone());
+	}
+
+	/// Removes the document `id` from the entries of applied actions, and prevents the entries of
+	/// undone actions it is part of from being redone.
+	fn forget_document(&mut self, id: &Id) {
+		retain_members(&mut self.applied, |(member, _)| member != id);
+		self.break_undone(id);
+	}
+
+	/// Prevents the entries of undone actions that the document `id` is part of from being redone,
+	/// by marking it in them (see [`Entry`]). Entries no document can redo are removed.
+	fn break_undone(&mut self, id: &Id) {
+		for (member, at) in self.undone.iter_mut().flatten() {
+			if member == id {
+				*at = 0;
+			}
+		}
+		self.undone
+			.retain(|entry| entry.iter().any(|(_, at)| *at > 0));
+	}
+
+	/// Adjusts the positions recorded for the document `id` to account for `evicted` of its oldest
+	/// actions having been removed, forgetting those that were.
+	fn forget_evicted(&mut self, id: &Id, evicted: usize) {
+		for (member, at) in self.applied.iter_mut().chain(&mut self.undone).flatten() {
+			if member == id {
+				*at = at.saturating_sub(evicted);
+			}
+		}
+		retain_members(&mut self.applied, |(_, at)| *at > 0);
+	}
+
+	/// Returns the ID of the document that undoing would act on, if any would be undone.
+	pub fn undo_target(&self) -> Option<&Id> {
+		match self.scope {
+			UndoScope::Focused => self.focused.as_ref().filter(|id| self.can_undo_in(id)),
+			UndoScope::Global => self
+				.undo_entry()
+				.and_then(|index| last_id(&self.applied[index])),
+		}
+	}
+
+	/// Returns the ID of the document that redoing would act on, if any would be redone.
+	pub fn redo_target(&self) -> Option<&Id> {
+		match self.scope {
+			UndoScope::Focused => self.focused.as_ref().filter(|id| self.can_redo_in(id)),
+			UndoScope::Global => self
+				.redo_entry()
+				.and_then(|index| last_id(&self.undone[index])),
+		}
+	}
+
+	/// Returns `true` if undoing would revert an action.
+	pub fn can_undo(&self) -> bool {
+		self.undo_target().is_some()
+	}
+
+	/// Returns `true` if redoing would apply an action.
+	pub fn can_redo(&self) -> bool {
+		self.redo_target().is_some()
+	}
+
+	/// Returns a label for an "undo" menu item (see [`HistoryState::undo_label`]), for the
+	/// document that undoing would act on.
+	pub fn undo_label(&self) -> String {
+		self.undo_target()
+			.and_then(|id| self.histories.get(id))
+			.map(UndoRedo::state)
+			.unwrap_or_default()
+			.undo_label()
+	}
+
+	/// Returns a label for a "redo" menu item (see [`HistoryState::redo_label`]), for the
+	/// document that redoing would act on.
+	pub fn redo_label(&self) -> String {
+		self.redo_target()
+			.and_then(|id| self.histories.get(id))
+			.map(UndoRedo::state)
+			.unwrap_or_default()
+			.redo_label()
+	}
+
+	/// Returns `true` if the document `id` has an action to undo.
+	fn can_undo_in(&self, id: &Id) -> bool {
+		self.histories
+			.get(id)
+			.is_some_and(|history| history.undo_action().is_some())
+	}
+
+	/// Returns `true` if the document `id` has an action to redo.
+	fn can_redo_in(&self, id: &Id) -> bool {
+		self.histories
+			.get(id)
+			.is_some_and(|history| history.redo_action().is_some())
+	}
+
+	/// Returns the index of the entry in `self.applied` that undoing would revert, if any.
+	///
+	/// For [`UndoScope::Focused`], this is the entry of the focused document's newest applied
+	/// action, which might not be revertible if other documents in it have changed since.
+	fn undo_entry(&self) -> Option<usize> {
+		match self.scope {
+			UndoScope::Focused => {
+				let id = self.focused.as_ref()?;
+				let position = self.histories.get(id)?.position();
+				find_entry(&self.applied, id, position)
+			}
+			UndoScope::Global => self
+				.applied
+				.iter()
+				.rposition(|entry| self.is_at_entry(entry, 0)),
+		}
+	}
+
+	/// Returns the index of the entry in `self.undone` that redoing would reapply, if any.
+	///
+	/// See [`Self::undo_entry`].
+	fn redo_entry(&self) -> Option<usize> {
+		match self.scope {
+			UndoScope::Focused => {
+				let id = self.focused.as_ref()?;
+				let position = self.histories.get(id)?.position();
+				find_entry(&self.undone, id, position + 1)
+			}
+			UndoScope::Global => self
+				.undone
+				.iter()
+				.rposition(|entry| self.is_at_entry(entry, 1)),
+		}
+	}
+
+	/// Returns `true` if every document in `entry` is `offset` actions before the position recorded
+	/// for it.
+	fn is_at_entry(&self, entry: &Entry<Id>, offset: usize) -> bool {
+		entry.iter().all(|(id, position)| {
+			self.histories
+				.get(id)
+				.is_some_and(|history| history.position() + offset == *position)
+		})
+	}
+
+	/// Returns `ResolveError::Missing` for the first of `ids` which has no history in this manager,
+	/// or cannot be found in `documents`.
+	fn check_present<'id, R>(
+		&self,
+		ids: impl IntoIterator<Item = &'id Id>,
+		documents: &mut R,
+	) -> Result<(), ResolveError<Id>>
+	where
+		Id: 'id,
+		R: TargetResolver<Id>,
+	{
+		for id in ids {
+			if !self.histories.contains_key(id) || documents.resolve(id).is_none() {
+				return Err(ResolveError::Missing(id.clone()));
+			}
+		}
+		Ok(())
+	}
+
+	/// Performs [`UndoRedo::commit_action`] on the history of the document `id`, which is found in
+	/// `documents`.
+	///
+	/// # Errors
+	/// Returns `ResolveError::Missing` if there is no document `id`, either in this manager or in
+	/// `documents`. Nothing is changed in this case.
+	///
+	/// # Panics
+	/// See [`UndoRedo::create_action`].
+	pub fn commit_action<R>(
+		&mut self,
+		id: &Id,
+		action: Action<Op>,
+		documents: &mut R,
+	) -> Result<(), ResolveError<Id>>
+	where
+		R: TargetResolver<Id>,
+		Op: Operation<R::Target>,
+	{
+		self.commit_compound(Vec::from([(id.clone(), action)]), documents)
+	}
+
+	/// Performs [`UndoRedo::commit_action`] with each of `actions`, on the history of the document
+	/// it is paired with, in order. The actions are then undone and redone together, as one action.
+	///
+	/// Undoing reverts them in the reverse order to that they were committed in, and redoing
+	/// reapplies them in the same order. If any of the documents has changed since (such as by
+	/// committing another action to it), the actions can't be undone or redone until it is
+	/// brought back to the same point in its history. Once undone, if another action is committed
+	/// to any of the documents (erasing its part), the rest can't be redone either. If a history's
+	/// storage removes one of the actions to make room for newer ones, the rest can still be undone
+	/// together.
+	///
+	/// Each document should only appear once. Combine multiple actions for one document into one
+	/// action instead.
+	///
+	/// # Errors
+	/// Returns `ResolveError::Missing` if any of the documents can't be found, either in this
+	/// manager or in `documents`. Nothing is changed in this case.
+	///
+	/// # Panics
+	/// See [`UndoRedo::create_action`].
+	pub fn commit_compound<R>(
+		&mut self,
+		actions: Vec<(Id, Action<Op>)>,
+		documents: &mut R,
+	) -> Result<(), ResolveError<Id>>
+	where
+		R: TargetResolver<Id>,
+		Op: Operation<R::Target>,
+	{
+		self.check_present(actions.iter().map(|(id, _)| id), documents)?;
+		let mut entry = Vec::with_capacity(actions.len());
+		for (id, action) in actions {
+			let (Some(history), Some(document)) =
+				(self.histories.get_mut(&id), documents.resolve(&id))
+			else {
+				continue;
+			};
+			let expected = history.position() + 1;
+			history.commit_action(action, document);
+			// Storages may remove the oldest actions to make room for the new one, which can then
+			// no longer be undone.
+			let evicted = expected - history.position();
+			// Committing erases the document's unapplied actions, so the other documents' parts of
+			// them can no longer be redone either.
+			self.break_undone(&id);
+			self.forget_evicted(&id, evicted);
+			self.enforce_action_limit(&id);
+			self.touch(&id);
+			self.notify(&id);
+			if let Some(history) = self.histories.get(&id) {
+				entry.push((id, history.position()));
+			}
+		}
+		if !entry.is_empty() {
+			self.applied.push(entry);
+		}
+		self.enforce_memory_budget();
+		Ok(())
+	}
+
+	/// Performs [`UndoRedo::undo`] on the history of the document chosen by [`Self::scope`],
+	/// returning the document's ID. If its action was committed along with actions in other
+	/// documents (see [`Self::commit_compound`]), they are all undone.
+	///
+	/// # Errors
+	/// * Returns `ResolveError::History` with `UndoRedoError::NothingToDo` if there is no document
+	///   to undo (see [`Self::undo_target`]).
+	/// * Returns `ResolveError::History` with `UndoRedoError::HasDependents` if the action was
+	///   committed along with actions in other documents, and one of those documents has changed
+	///   since.
+	/// * Returns `ResolveError::Missing` if any of the documents cannot be found in `documents`.
+	///
+	/// Nothing is changed in any of these cases.
+	pub fn undo<R>(&mut self, documents: &mut R) -> Result<Id, ResolveError<Id>>
+	where
+		R: TargetResolver<Id>,
+		Op: Operation<R::Target>,
+	{
+		let id = self
+			.undo_target()
+			.cloned()
+			.ok_or(UndoRedoError::NothingToDo)?;
+		self.undo_from(&id, self.undo_entry(), documents)?;
+		Ok(id)
+	}
+
+	/// Performs [`UndoRedo::redo`] on the history of the document chosen by [`Self::scope`],
+	/// returning the document's ID. If its action was committed along with actions in other
+	/// documents (see [`Self::commit_compound`]), they are all redone.
+	///
+	/// # Errors
+	/// See [`Self::undo`].
+	///
+	/// # Panics
+	/// See [`UndoRedo::redo`].
+	pub fn redo<R>(&mut self, documents: &mut R) -> Result<Id, ResolveError<Id>>
+	where
+		R: TargetResolver<Id>,
+		Op: Operation<R::Target>,
+	{
+		let id = self
+			.redo_target()
+			.cloned()
+			.ok_or(UndoRedoError::NothingToDo)?;
+		self.redo_from(&id, self.redo_entry(), documents)?;
+		Ok(id)
+	}
+
+	/// Undoes one action in every document which has an action to undo, as though [`Self::undo`]
+	/// were called with each document focused in turn. Returns the result for each of those
+	/// documents.
+	///
+	/// Actions committed along with actions in other documents (see [`Self::commit_compound`])
+	/// are undone together, and count as one action in each document.
+	pub fn undo_everywhere<R>(
+		&mut self,
+		documents: &mut R,
+	) -> BTreeMap<Id, Result<(), ResolveError<Id>>>
+	where
+		R: TargetResolver<Id>,
+		Op: Operation<R::Target>,
+	{
+		let ids: Vec<Id> = self
+			.histories
+			.keys()
+			.filter(|id| self.can_undo_in(id))
+			.cloned()
+			.collect();
+		let mut results = BTreeMap::new();
+		for id in ids {
+			if results.contains_key(&id) {
+				continue;
+			}
+			let position = self.histories.get(&id).map_or(0, UndoRedo::position);
+			let index = find_entry(&self.applied, &id, position);
+			match self.undo_from(&id, index, documents) {
+				Ok(members) => results.extend(members.into_iter().map(|member| (member, Ok(())))),
+				Err(error) => {
+					results.insert(id, Err(error));
+				}
+			}
+		}
+		results
+	}
+
+	/// Redoes one action in every document which has an action to redo, as though [`Self::redo`]
+	/// were called with each document focused in turn. Returns the result for each of those
+	/// documents.
+	///
+	/// See [`Self::undo_everywhere`].
+	///
+	/// # Panics
+	/// See [`UndoRedo::redo`].
+	pub fn redo_everywhere<R>(
+		&mut self,
+		documents: &mut R,
+	) -> BTreeMap<Id, Result<(), ResolveError<Id>>>
+	where
+		R: TargetResolver<Id>,
+		Op: Operation<R::Target>,
+	{
+		let ids: Vec<Id> = self
+			.histories
+			.keys()
+			.filter(|id| self.can_redo_in(id))
+			.cloned()
+			.collect();
+		let mut results = BTreeMap::new();
+		for id in ids {
+			if results.contains_key(&id) {
+				continue;
+			}
+			let position = self.histories.get(&id).map_or(0, UndoRedo::position);
+			let index = find_entry(&self.undone, &id, position + 1);
+			match self.redo_from(&id, index, documents) {
+				Ok(members) => results.extend(members.into_iter().map(|member| (member, Ok(())))),
+				Err(error) => {
+					results.insert(id, Err(error));
+				}
+			}
+		}
+		results
+	}
+
+	/// Undoes the newest action of the document `id`, along with the rest of the entry at `index`
+	/// in `self.applied` if it has one. Returns the IDs of the documents undone.
+	fn undo_from<R>(
+		&mut self,
+		id: &Id,
+		index: Option<usize>,
+		documents: &mut R,
+	) -> Result<Vec<Id>, ResolveError<Id>>
+	where
+		R: TargetResolver<Id>,
+		Op: Operation<R::Target>,
+	{
+		let ids = self.entry_ids(index.map(|index| &self.applied[index]), id, 0)?;
+		self.check_present(&ids, documents)?;
+		for member in ids.iter().rev() {
+			self.perform(member, documents, UndoRedo::undo)?;
+		}
+		if let Some(index) = index {
+			let entry = self.applied.remove(index);
+			self.undone.push(entry);
+		}
+		Ok(ids)
+	}
+
+	/// Redoes the next action of the document `id`, along with the rest of the entry at `index`
+	/// in `self.undone` if it has one. Returns the IDs of the documents redone.
+	fn redo_from<R>(
+		&mut self,
+		id: &Id,
+		index: Option<usize>,
+		documents: &mut R,
+	) -> Result<Vec<Id>, ResolveError<Id>>
+	where
+		R: TargetResolver<Id>,
+		Op: Operation<R::Target>,
+	{
+		let ids = self.entry_ids(index.map(|index| &self.undone[index]), id, 1)?;
+		self.check_present(&ids, documents)?;
+		for member in &ids {
+			self.perform(member, documents, UndoRedo::redo)?;
+		}
+		if let Some(index) = index {
+			let entry = self.undone.remove(index);
+			self.applied.push(entry);
+		}
+		Ok(ids)
+	}
+
+	/// Returns the IDs of the documents in `entry`, after checking that each is `offset` actions
+	/// before its recorded position (see [`Self::is_at_entry`]). If there is no entry (as for
+	/// actions committed through [`Self::get_mut`]), only `id` is returned.
+	fn entry_ids(
+		&self,
+		entry: Option<&Entry<Id>>,
+		id: &Id,
+		offset: usize,
+	) -> Result<Vec<Id>, UndoRedoError> {
+		let Some(entry) = entry else {
+			return Ok(Vec::from([id.clone()]));
+		};
+		if !self.is_at_entry(entry, offset) {
+			return Err(UndoRedoError::HasDependents);
+		}
+		Ok(entry.iter().map(|(member, _)| member.clone()).collect())
+	}
+
+	/// Calls `f` with the history of the document `id` and the document itself, then tells
+	/// subscribers if it succeeded.
+	fn perform<R>(
+		&mut self,
+		id: &Id,
+		documents: &mut R,
+		f: impl FnOnce(&mut UndoRedo<Op, S>, &mut R::Target) -> Result<(), UndoRedoError>,
+	) -> Result<(), ResolveError<Id>>
+	where
+		R: TargetResolver<Id>,
+	{
+		let missing = || ResolveError::Missing(id.clone());
+		let history = self.histories.get_mut(id).ok_or_else(missing)?;
+		let document = documents.resolve(id).ok_or_else(missing)?;
+		f(history, document)?;
+		self.touch(id);
+		self.notify(id);
+		Ok(())
+	}
+
+	/// Calls every subscriber with the state of the history of the document `id`.
+	fn notify(&mut self, id: &Id) {
+		let Some(history) = self.histories.get(id) else {
+			return;
+		};
+		let state = history.state();
+		for subscriber in &mut self.subscribers {
+			subscriber(id, &state);
+		}
+	}
+}
+
+/// Returns the ID of the document changed last in `entry`.
+fn last_id<Id>(entry: &Entry<Id>) -> Option<&Id> {
+	entry.last().map(|(id, _)| id)
+}
+
+/// Returns the index of the last of `entries` in which the document `id` is at `position`.
+fn find_entry<Id: PartialEq>(entries: &[Entry<Id>], id: &Id, position: usize) -> Option<usize> {
+	entries.iter().rposition(|entry| {
+		entry
+			.iter()
+			.any(|(member, at)| member == id && *at == position)
+	})
+}
+
+/// Keeps only the documents in `entries` for which `f` returns `true`, then removes any entries
+/// left empty.
+fn retain_members<Id>(entries: &mut Vec<Entry<Id>>, mut f: impl FnMut(&(Id, usize)) -> bool) {
+	for entry in entries.iter_mut() {
+		entry.retain(&mut f);
+	}
+	entries.retain(|entry| !entry.is_empty());
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[derive(Clone, Debug, PartialEq)]
+	struct Add(i32);
+
+	impl Operation<i32> for Add {
+		fn apply(&self, item: &mut i32) {
+			*item += self.0;
+		}
+	}
+
+	fn add(value: i32) -> Action<Add> {
+		let mut action = Action::default();
+		action.add_redo_operation(Add(value));
+		action.add_undo_operation(Add(-value));
+		action
+	}
+
+	/// Returns a manager with the documents 1 and 2, where 1 is focused and 5 has been moved from
+	/// 1 to 2 by a compound action.
+	fn moved() -> (UndoRedoManager<u32, Add>, BTreeMap<u32, i32>) {
+		let mut manager = UndoRedoManager::new();
+		let mut documents = BTreeMap::from([(1, 10), (2, 0)]);
+		manager.insert(1, UndoRedo::new());
+		manager.insert(2, UndoRedo::new());
+		manager.focus(Some(1));
+		manager
+			.commit_compound(Vec::from([(1, add(-5)), (2, add(5))]), &mut documents)
+			.unwrap();
+		(manager, documents)
+	}
+
+	#[test]
+	fn compound_undo_and_redo_act_on_every_document() {
+		let (mut manager, mut documents) = moved();
+		assert_eq!(documents, BTreeMap::from([(1, 5), (2, 5)]));
+
+		assert_eq!(manager.undo(&mut documents), Ok(1));
+		assert_eq!(documents, BTreeMap::from([(1, 10), (2, 0)]));
+		manager.focus(Some(2));
+		assert_eq!(manager.redo(&mut documents), Ok(2));
+		assert_eq!(documents, BTreeMap::from([(1, 5), (2, 5)]));
+	}
+
+	#[test]
+	fn compound_undo_refuses_changed_documents() {
+		let (mut manager, mut documents) = moved();
+		manager.commit_action(&2, add(1), &mut documents).unwrap();
+
+		assert_eq!(
+			manager.undo(&mut documents),
+			Err(ResolveError::History(UndoRedoError::HasDependents))
+		);
+		assert_eq!(documents, BTreeMap::from([(1, 5), (2, 6)]));
+	}
+
+	#[test]
+	fn compound_commit_checks_every_document_first() {
+		let (mut manager, mut documents) = moved();
+		let result = manager.commit_compound(Vec::from([(1, add(1)), (3, add(1))]), &mut documents);
+
+		assert_eq!(result, Err(ResolveError::Missing(3)));
+		assert_eq!(documents, BTreeMap::from([(1, 5), (2, 5)]));
+		assert_eq!(manager.get(&1).unwrap().position(), 1);
+	}
+
+	#[test]
+	fn compound_redo_refuses_erased_parts() {
+		let (mut manager, mut documents) = moved();
+		manager.undo(&mut documents).unwrap();
+		manager.commit_action(&1, add(1), &mut documents).unwrap();
+		manager.focus(Some(2));
+
+		assert_eq!(
+			manager.redo(&mut documents),
+			Err(ResolveError::History(UndoRedoError::HasDependents))
+		);
+		manager.set_scope(UndoScope::Global);
+		assert!(!manager.can_redo());
+	}
+
+	#[test]
+	fn replacing_a_history_ungroups_its_actions() {
+		let (mut manager, mut documents) = moved();
+		let mut replacement = UndoRedo::new();
+		replacement.commit_action(add(-5), documents.get_mut(&1).unwrap());
+		manager.insert(1, replacement);
+		manager.focus(Some(2));
+
+		assert_eq!(manager.undo(&mut documents), Ok(2));
+		assert_eq!(documents, BTreeMap::from([(1, 0), (2, 0)]));
+	}
+
+	#[derive(Clone, Debug)]
+	struct Blob(Vec<u8>);
+
+	impl Operation<i32> for Blob {
+		fn apply(&self, _: &mut i32) {}
+	}
+
+	impl SizedOperation for Blob {
+		fn heap_size(&self) -> usize {
+			self.0.capacity()
+		}
+	}
+
+	#[test]
+	fn memory_budget_evicts_least_recently_used_first() {
+		let mut manager = UndoRedoManager::<u32, Blob>::new();
+		let mut documents = BTreeMap::from([(1, 0), (2, 0)]);
+		manager.insert(1, UndoRedo::new());
+		manager.insert(2, UndoRedo::new());
+		for id in [1, 2] {
+			for _ in 0..3 {
+				let mut action = Action::default();
+				action.add_redo_operation(Blob(Vec::with_capacity(1000)));
+				manager.commit_action(&id, action, &mut documents).unwrap();
+			}
+		}
+		let positions = |manager: &UndoRedoManager<u32, Blob>| {
+			(
+				manager.get(&1).unwrap().position(),
+				manager.get(&2).unwrap().position(),
+			)
+		};
+
+		manager.set_memory_budget(usize::MAX);
+		let used = manager.memory_usage().unwrap();
+		manager.set_memory_budget(used - 1);
+		assert_eq!(positions(&manager), (2, 3));
+
+		manager.focus(Some(1));
+		manager.set_memory_budget(used - 2500);
+		assert_eq!(positions(&manager), (2, 1));
+
+		manager.set_memory_budget(0);
+		assert_eq!(positions(&manager), (1, 1));
+	}
+}
@@ -0,0 +1,99 @@
+use crate::{Action, HistoryStorage, InvertibleOperation, UndoRedo};
+
+/// An operation which can absorb the operation performed directly after it, so that several small
+/// changes (such as consecutive edits to the same property) can be stored and undone as one.
+///
+/// For structs, this can be implemented with
+/// [`mergeable_operation!`](crate::mergeable_operation). See [`UndoRedo::commit_merging`].
+pub trait MergeableOperation: Sized {
+	/// Merges `next`, which is performed directly after this operation, into this one, so that
+	/// applying this operation has the same effect as applying both.
+	///
+	/// # Errors
+	/// Returns `next` unchanged if the two operations cannot be merged.
+	fn merge(&mut self, next: Self) -> Result<(), Self>;
+}
+
+impl<Op> Action<Op> {
+	/// Like [`Self::add_invertible_operation`], but if `operation` can be
+	/// [merged](MergeableOperation::merge) into the last redo operation, it is merged instead, and
+	/// the first undo operation is replaced by the inverse of the merged operation.
+	///
+	/// This assumes that the first undo operation reverts the last redo operation, as it does when
+	/// every operation is added through [`Self::add_invertible_operation`] or this method.
+	pub fn add_invertible_operation_merging<For>(&mut self, operation: Op) -> &mut Self
+	where
+		Op: InvertibleOperation<For> + MergeableOperation,
+	{
+		match self.try_merge(operation) {
+			Ok(()) => self,
+			Err(operation) => self.add_invertible_operation(operation),
+		}
+	}
+
+	/// Merges `operation` into the last redo operation, as described in
+	/// [`Self::add_invertible_operation_merging`], returning it unchanged if it can't be merged.
+	fn try_merge<For>(&mut self, operation: Op) -> Result<(), Op>
+	where
+		Op: InvertibleOperation<For> + MergeableOperation,
+	{
+		let Some(last) = self.apply_ops.as_mut_slice().last_mut() else {
+			return Err(operation);
+		};
+		last.merge(operation)?;
+
+		let inverse = last.inverse();
+		match self.revert_ops.as_mut_slice().first_mut() {
+			Some(first) => *first = inverse,
+			None => self.revert_ops.push(inverse),
+		}
+		Ok(())
+	}
+}
+
+impl<Op, S: HistoryStorage<Op>> UndoRedo<Op, S> {
+	/// Applies `operation` to `apply_to`, then [merges](MergeableOperation::merge) it into the
+	/// newest action if it is applied and its last redo operation can absorb `operation`.
+	/// Otherwise, this adds a new action containing `operation`, as [`Self::commit_invertible`]
+	/// would.
+	///
+	/// This collapses consecutive changes, such as typing into a text field, into one action. To
+	/// stop a later change from being merged into the newest action, create a new action first
+	/// (with [`Self::create_action`]). If the point in history being merged into was marked as the
+	/// save point, the mark is removed, as the merged action no longer leads to the saved state.
+	///
+	/// The action which `operation` was added to is returned.
+	///
+	/// # Panics
+	/// See [`Self::create_action`].
+	pub fn commit_merging<For>(&mut self, operation: Op, apply_to: &mut For) -> &mut Action<Op>
+	where
+		Op: InvertibleOperation<For> + MergeableOperation,
+	{
+		operation.apply(apply_to);
+
+		let newest = self
+			.tapehead
+			.checked_sub(1)
+			.filter(|&index| index + 1 == self.actions.len());
+		let operation = match newest.and_then(|index| self.actions.get_mut(index)) {
+			Some(action) if !action.is_skipped() => match action.try_merge(operation) {
+				Ok(()) => {
+					if self.save_point == Some(self.tapehead) {
+						self.save_point = None;
+					}
+					return self
+						.actions
+						.get_mut(self.tapehead - 1)
+						.expect("the action merged into should still be available");
+				}
+				Err(operation) => operation,
+			},
+			_ => operation,
+		};
+
+		let mut action = Action::default();
+		action.add_invertible_operation(operation);
+		self.push_applied(action)
+	}
+}
@@ -0,0 +1,196 @@
+use alloc::{boxed::Box, vec::Vec};
+use core::{
+	cmp::Ordering,
+	fmt,
+	hash::{Hash, Hasher},
+};
+
+/// The list of operations held by an [`Action`](crate::Action) for one direction.
+///
+/// Once [sealed](Self::seal), a list keeps its operations in a boxed slice, which holds no spare
+/// capacity and is smaller than a `Vec`. Adding to a sealed list turns it back into a `Vec`.
+///
+/// With the `inline-ops` feature enabled, a list holding a single operation keeps it inline
+/// rather than allocating, as most actions only have one operation in each direction.
+#[derive(Clone)]
+pub(crate) struct OpList<Op> {
+	repr: Repr<Op>,
+}
+
+#[derive(Clone)]
+enum Repr<Op> {
+	#[cfg(feature = "inline-ops")]
+	Empty,
+	#[cfg(feature = "inline-ops")]
+	One(Op),
+	Open(Vec<Op>),
+	Sealed(Box<[Op]>),
+}
+
+impl<Op> OpList<Op> {
+	pub(crate) fn as_slice(&self) -> &[Op] {
+		match &self.repr {
+			#[cfg(feature = "inline-ops")]
+			Repr::Empty => &[],
+			#[cfg(feature = "inline-ops")]
+			Repr::One(op) => core::slice::from_ref(op),
+			Repr::Open(ops) => ops,
+			Repr::Sealed(ops) => ops,
+		}
+	}
+
+	pub(crate) fn as_mut_slice(&mut self) -> &mut [Op] {
+		match &mut self.repr {
+			#[cfg(feature = "inline-ops")]
+			Repr::Empty => &mut [],
+			#[cfg(feature = "inline-ops")]
+			Repr::One(op) => core::slice::from_mut(op),
+			Repr::Open(ops) => ops,
+			Repr::Sealed(ops) => ops,
+		}
+	}
+
+	pub(crate) fn push(&mut self, op: Op) {
+		#[cfg(feature = "inline-ops")]
+		if let Repr::Empty = self.repr {
+			self.repr = Repr::One(op);
+			return;
+		}
+		self.open().push(op);
+	}
+
+	pub(crate) fn insert_first(&mut self, op: Op) {
+		#[cfg(feature = "inline-ops")]
+		if let Repr::Empty = self.repr {
+			self.repr = Repr::One(op);
+			return;
+		}
+		self.open().insert(0, op);
+	}
+
+	/// Turns this list into a `Vec` which can be added to, if it isn't one already, and returns it.
+	fn open(&mut self) -> &mut Vec<Op> {
+		if !matches!(self.repr, Repr::Open(_)) {
+			self.repr = Repr::Open(core::mem::take(self).into_vec());
+		}
+		match &mut self.repr {
+			Repr::Open(ops) => ops,
+			_ => unreachable!("list should have just been opened"),
+		}
+	}
+
+	pub(crate) fn into_vec(self) -> Vec<Op> {
+		match self.repr {
+			#[cfg(feature = "inline-ops")]
+			Repr::Empty => Vec::new(),
+			#[cfg(feature = "inline-ops")]
+			Repr::One(op) => {
+				let mut ops = Vec::with_capacity(2);
+				ops.push(op);
+				ops
+			}
+			Repr::Open(ops) => ops,
+			Repr::Sealed(ops) => ops.into_vec(),
+		}
+	}
+
+	/// Returns the number of bytes of heap memory this list has allocated to hold its operations.
+	pub(crate) fn heap_size(&self) -> usize {
+		match &self.repr {
+			#[cfg(feature = "inline-ops")]
+			Repr::Empty | Repr::One(_) => 0,
+			Repr::Open(ops) => ops.capacity() * size_of::<Op>(),
+			Repr::Sealed(ops) => size_of_val::<[Op]>(ops),
+		}
+	}
+
+	/// Moves the operations of this list into a boxed slice, releasing any spare capacity.
+	pub(crate) fn seal(&mut self) {
+		if let Repr::Open(ops) = &mut self.repr {
+			self.repr = Repr::Sealed(core::mem::take(ops).into_boxed_slice());
+		}
+	}
+}
+
+impl<Op> From<Vec<Op>> for OpList<Op> {
+	fn from(ops: Vec<Op>) -> Self {
+		#[cfg(not(feature = "inline-ops"))]
+		{
+			Self {
+				repr: Repr::Open(ops),
+			}
+		}
+		#[cfg(feature = "inline-ops")]
+		{
+			let mut list = Self::default();
+			ops.into_iter().for_each(|op| list.push(op));
+			list
+		}
+	}
+}
+
+// An empty list does not contain any `Op`. As the `Default` derive macro assumes that we want a
+// trait bound on `Op` no matter what, we have to manually implement `Default`.
+impl<Op> Default for OpList<Op> {
+	fn default() -> Self {
+		Self {
+			#[cfg(not(feature = "inline-ops"))]
+			repr: Repr::Open(Vec::new()),
+			#[cfg(feature = "inline-ops")]
+			repr: Repr::Empty,
+		}
+	}
+}
+
+// The remaining traits are implemented in terms of `as_slice`, so that lists compare and hash the
+// same no matter how they're represented.
+
+impl<Op: fmt::Debug> fmt::Debug for OpList<Op> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		self.as_slice().fmt(f)
+	}
+}
+
+impl<Op: Hash> Hash for OpList<Op> {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.as_slice().hash(state);
+	}
+}
+
+impl<Op: PartialEq> PartialEq for OpList<Op> {
+	fn eq(&self, other: &Self) -> bool {
+		self.as_slice() == other.as_slice()
+	}
+}
+
+impl<Op: Eq> Eq for OpList<Op> {}
+
+impl<Op: PartialOrd> PartialOrd for OpList<Op> {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		self.as_slice().partial_cmp(other.as_slice())
+	}
+}
+
+impl<Op: Ord> Ord for OpList<Op> {
+	fn cmp(&self, other: &Self) -> Ordering {
+		self.as_slice().cmp(other.as_slice())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn sealing_drops_spare_capacity() {
+		let mut list = OpList::from(Vec::with_capacity(16));
+		(0..3).for_each(|op: u32| list.push(op));
+		list.seal();
+		assert!(matches!(list.repr, Repr::Sealed(_)));
+		assert_eq!(list.heap_size(), 3 * size_of::<u32>());
+
+		list.insert_first(10);
+		list.push(20);
+		assert_eq!(list.as_slice(), [10, 0, 1, 2, 20]);
+	}
+}
@@ -0,0 +1,72 @@
+//! Ready-made operations for common kinds of objects.
+//!
+//! Each operation holds whatever it needs to be inverted, and implements
+//! [`InvertibleOperation`](crate::InvertibleOperation), so it can be recorded with
+//! [`UndoRedo::commit_invertible`](crate::UndoRedo::commit_invertible). Those which also implement
+//! [`MergeableOperation`](crate::MergeableOperation) can be recorded with
+//! [`UndoRedo::commit_merging`](crate::UndoRedo::commit_merging).
+
+pub mod ecs;
+pub mod grid;
+pub mod map;
+pub mod numeric;
+pub mod text;
+pub mod transform;
+pub mod tree;
+pub mod vec;
+
+/// Checks shared by the tests of each kind of operation.
+#[cfg(test)]
+mod testing {
+	use alloc::vec::Vec;
+	use core::fmt::Debug;
+
+	use crate::{InvertibleOperation, MergeableOperation, UndoRedo};
+
+	/// Applies `op` to a copy of `target`, and checks that applying its inverse brings back
+	/// `target`. Returns the changed copy.
+	pub(super) fn assert_round_trips<For, Op>(target: &For, op: &Op) -> For
+	where
+		For: Clone + PartialEq + Debug,
+		Op: InvertibleOperation<For>,
+	{
+		let mut changed = target.clone();
+		op.apply(&mut changed);
+		let mut restored = changed.clone();
+		op.inverse().apply(&mut restored);
+		assert_eq!(restored, *target);
+		changed
+	}
+
+	/// Commits `ops` to copies of `target`, once merging them into as few actions as possible,
+	/// and once as separate actions. Checks that both reach the same state, and that undoing each
+	/// step of the merged history matches undoing the separate actions it was merged from.
+	/// Returns the number of actions the merged history has.
+	pub(super) fn assert_merges_like_separate<For, Op>(target: &For, ops: &[Op]) -> usize
+	where
+		For: Clone + PartialEq + Debug,
+		Op: InvertibleOperation<For> + MergeableOperation + Clone,
+	{
+		let (mut merged, mut separate) = (target.clone(), target.clone());
+		let (mut merged_history, mut separate_history) = (UndoRedo::new(), UndoRedo::new());
+		// The position in the separate history that each merged action ends at.
+		let mut ends = Vec::new();
+		for op in ops {
+			merged_history.commit_merging(op.clone(), &mut merged);
+			separate_history.commit_invertible(op.clone(), &mut separate);
+			ends.truncate(merged_history.position() - 1);
+			ends.push(separate_history.position());
+		}
+		assert_eq!(merged, separate);
+
+		for (position, &end) in ends.iter().enumerate().rev() {
+			separate_history.jump_to(end, &mut separate).unwrap();
+			assert_eq!(merged, separate);
+			merged_history.jump_to(position, &mut merged).unwrap();
+		}
+		separate_history.jump_to(0, &mut separate).unwrap();
+		assert_eq!(merged, *target);
+		assert_eq!(separate, *target);
+		ends.len()
+	}
+}
@@ -0,0 +1,192 @@
+//! Operations on the components of entities, for entity-component-system (ECS) worlds.
+//!
+//! Operations reach components through [`ComponentWorld`], which can be implemented for any ECS
+//! library's world. Each operation captures the component's previous value (if any) when it is
+//! created, so that it can be undone.
+
+use alloc::collections::BTreeMap;
+#[cfg(feature = "std")]
+use core::hash::{BuildHasher, Hash};
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+use crate::{InvertibleOperation, MergeableOperation, Operation};
+
+/// A world which stores components of type `C` for entities identified by `Entity`.
+///
+/// This is implemented for maps from entities to components, which can act as the storage for
+/// one kind of component.
+pub trait ComponentWorld<Entity, C> {
+	/// Returns `entity`'s component, if it has one.
+	fn get_component(&self, entity: &Entity) -> Option<&C>;
+
+	/// Gives `entity` the component `component`, replacing any it already had.
+	fn insert_component(&mut self, entity: &Entity, component: C);
+
+	/// Removes `entity`'s component, if it has one.
+	fn remove_component(&mut self, entity: &Entity);
+}
+
+impl<Entity: Ord + Clone, C> ComponentWorld<Entity, C> for BTreeMap<Entity, C> {
+	fn get_component(&self, entity: &Entity) -> Option<&C> {
+		self.get(entity)
+	}
+
+	fn insert_component(&mut self, entity: &Entity, component: C) {
+		self.insert(entity.clone(), component);
+	}
+
+	fn remove_component(&mut self, entity: &Entity) {
+		self.remove(entity);
+	}
+}
+
+#[cfg(feature = "std")]
+impl<Entity: Eq + Hash + Clone, C, S: BuildHasher> ComponentWorld<Entity, C>
+	for HashMap<Entity, C, S>
+{
+	fn get_component(&self, entity: &Entity) -> Option<&C> {
+		self.get(entity)
+	}
+
+	fn insert_component(&mut self, entity: &Entity, component: C) {
+		self.insert(entity.clone(), component);
+	}
+
+	fn remove_component(&mut self, entity: &Entity) {
+		self.remove(entity);
+	}
+}
+
+/// A change to one entity's component of type `C`: inserting, removing, or modifying it.
+///
+/// `old` and `new` are the component before and after the change, where `None` means the entity
+/// has no such component. Applying this sets the component to `new`.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct ComponentEdit<Entity, C> {
+	/// The entity whose component is changed.
+	pub entity: Entity,
+	/// The component before the change.
+	pub old: Option<C>,
+	/// The component after the change.
+	pub new: Option<C>,
+}
+
+impl<Entity, C: Clone> ComponentEdit<Entity, C> {
+	/// Creates an edit which gives `entity` the component `component`, capturing any component it
+	/// has in `world` now.
+	pub fn insert<W: ComponentWorld<Entity, C>>(world: &W, entity: Entity, component: C) -> Self {
+		Self {
+			old: world.get_component(&entity).cloned(),
+			entity,
+			new: Some(component),
+		}
+	}
+
+	/// Creates an edit which removes `entity`'s component, capturing the component it has in
+	/// `world` now.
+	///
+	/// Returns `None` if `entity` has no such component.
+	pub fn remove<W: ComponentWorld<Entity, C>>(world: &W, entity: Entity) -> Option<Self> {
+		let old = world.get_component(&entity).cloned()?;
+		Some(Self {
+			entity,
+			old: Some(old),
+			new: None,
+		})
+	}
+
+	/// Creates an edit which changes `entity`'s component in `world` using `modify`, capturing the
+	/// component from before the change.
+	///
+	/// `world` is left unchanged; `modify` is given a copy of the component. Returns `None` if
+	/// `entity` has no such component.
+	pub fn modify<W: ComponentWorld<Entity, C>>(
+		world: &W,
+		entity: Entity,
+		modify: impl FnOnce(&mut C),
+	) -> Option<Self> {
+		let old = world.get_component(&entity).cloned()?;
+		let mut new = old.clone();
+		modify(&mut new);
+		Some(Self {
+			entity,
+			old: Some(old),
+			new: Some(new),
+		})
+	}
+}
+
+impl<Entity, C: Clone, W: ComponentWorld<Entity, C>> Operation<W> for ComponentEdit<Entity, C> {
+	fn apply(&self, item: &mut W) {
+		match &self.new {
+			Some(component) => item.insert_component(&self.entity, component.clone()),
+			None => item.remove_component(&self.entity),
+		}
+	}
+}
+
+impl<Entity: Clone, C: Clone, W: ComponentWorld<Entity, C>> InvertibleOperation<W>
+	for ComponentEdit<Entity, C>
+{
+	fn inverse(&self) -> Self {
+		Self {
+			entity: self.entity.clone(),
+			old: self.new.clone(),
+			new: self.old.clone(),
+		}
+	}
+}
+
+/// Merges consecutive edits to the same entity's component, such as while dragging it around.
+impl<Entity: PartialEq, C> MergeableOperation for ComponentEdit<Entity, C> {
+	fn merge(&mut self, next: Self) -> Result<(), Self> {
+		if next.entity != self.entity {
+			return Err(next);
+		}
+		self.new = next.new;
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use alloc::vec::Vec;
+
+	use super::*;
+	use crate::ops::testing::{assert_merges_like_separate, assert_round_trips};
+
+	#[derive(Clone, Debug, PartialEq)]
+	struct Position(i32, i32);
+
+	fn world() -> BTreeMap<u32, Position> {
+		BTreeMap::from([(1, Position(0, 0)), (2, Position(5, 5))])
+	}
+
+	#[test]
+	fn edits_round_trip() {
+		let world = world();
+		let inserted =
+			assert_round_trips(&world, &ComponentEdit::insert(&world, 3, Position(1, 1)));
+		assert_eq!(inserted.get(&3), Some(&Position(1, 1)));
+		let removed = assert_round_trips(&world, &ComponentEdit::remove(&world, 1).unwrap());
+		assert_eq!(removed.get(&1), None);
+		let modified = ComponentEdit::modify(&world, 2, |position| position.0 += 1).unwrap();
+		let modified = assert_round_trips(&world, &modified);
+		assert_eq!(modified.get(&2), Some(&Position(6, 5)));
+		assert_eq!(ComponentEdit::remove(&world, 3), None);
+	}
+
+	#[test]
+	fn dragging_merges_per_entity() {
+		let world = world();
+		let mut moved = world.clone();
+		let mut edits = Vec::new();
+		for entity in [1, 1, 1, 2, 2] {
+			let edit = ComponentEdit::modify(&moved, entity, |position| position.0 += 1).unwrap();
+			edit.apply(&mut moved);
+			edits.push(edit);
+		}
+		assert_eq!(assert_merges_like_separate(&world, &edits), 2);
+	}
+}
@@ -0,0 +1,497 @@
+//! Operations on 2D grids of cells, such as spreadsheets and tile maps.
+//!
+//! Operations reach the grid through [`GridAccess`]. Cells are addressed by row and column,
+//! starting from 0, and the cells of a region are listed row by row.
+
+use alloc::vec::Vec;
+
+use crate::{InvertibleOperation, MergeableOperation, Operation};
+
+/// A grid of cells, which can have rows and columns inserted and removed.
+pub trait GridAccess {
+	/// The type of the grid's cells.
+	type Cell: Clone;
+
+	/// Returns the number of rows.
+	fn rows(&self) -> usize;
+
+	/// Returns the number of columns.
+	fn columns(&self) -> usize;
+
+	/// Returns the cell at `row` and `column`, or `None` if it is out of bounds.
+	fn cell(&self, row: usize, column: usize) -> Option<&Self::Cell>;
+
+	/// Replaces the cell at `row` and `column` with `cell`.
+	fn set_cell(&mut self, row: usize, column: usize, cell: Self::Cell);
+
+	/// Inserts a row at `index`, made of `cells`, shifting later rows down.
+	fn insert_row(&mut self, index: usize, cells: Vec<Self::Cell>);
+
+	/// Removes the row at `index`, shifting later rows up.
+	fn remove_row(&mut self, index: usize);
+
+	/// Inserts a column at `index`, made of `cells`, shifting later columns right.
+	fn insert_column(&mut self, index: usize, cells: Vec<Self::Cell>);
+
+	/// Removes the column at `index`, shifting later columns left.
+	fn remove_column(&mut self, index: usize);
+}
+
+/// A rectangular region of a grid.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub struct Region {
+	/// The top row of the region.
+	pub row: usize,
+	/// The leftmost column of the region.
+	pub column: usize,
+	/// The number of rows in the region.
+	pub rows: usize,
+	/// The number of columns in the region.
+	pub columns: usize,
+}
+
+impl Region {
+	/// Returns a region covering the single cell at `row` and `column`.
+	pub const fn cell(row: usize, column: usize) -> Self {
+		Self {
+			row,
+			column,
+			rows: 1,
+			columns: 1,
+		}
+	}
+
+	/// Returns the number of cells in the region.
+	pub const fn len(&self) -> usize {
+		self.rows * self.columns
+	}
+
+	/// Returns `true` if the region has no cells.
+	pub const fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Returns `true` if every cell of `other` is within this region.
+	pub const fn contains(&self, other: &Self) -> bool {
+		other.row >= self.row
+			&& other.column >= self.column
+			&& other.row + other.rows <= self.row + self.rows
+			&& other.column + other.columns <= self.column + self.columns
+	}
+
+	/// Returns the positions of the cells in the region, row by row.
+	fn positions(self) -> impl Iterator<Item = (usize, usize)> {
+		(self.row..self.row + self.rows).flat_map(move |row| {
+			(self.column..self.column + self.columns).map(move |col| (row, col))
+		})
+	}
+
+	/// Returns the index of the cell at `row` and `column` among the cells of the region, which
+	/// must contain it.
+	fn offset_of(&self, row: usize, column: usize) -> usize {
+		(row - self.row) * self.columns + (column - self.column)
+	}
+
+	/// Returns the smallest region containing both this region and `other`, if it has no cells
+	/// outside of them.
+	fn union(&self, other: &Self) -> Option<Self> {
+		if self.contains(other) {
+			return Some(*self);
+		}
+		if other.contains(self) {
+			return Some(*other);
+		}
+		let same_columns = self.column == other.column && self.columns == other.columns;
+		let same_rows = self.row == other.row && self.rows == other.rows;
+		if same_columns && other.row == self.row + self.rows {
+			Some(Self {
+				rows: self.rows + other.rows,
+				..*self
+			})
+		} else if same_rows && other.column == self.column + self.columns {
+			Some(Self {
+				columns: self.columns + other.columns,
+				..*self
+			})
+		} else {
+			None
+		}
+	}
+}
+
+/// An edit to a grid whose cells are of type `Cell`.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum GridEdit<Cell> {
+	/// Replaces the cells of `region`, which are `old`, with `new`.
+	SetCells {
+		/// The cells to replace.
+		region: Region,
+		/// The cells being replaced, row by row.
+		old: Vec<Cell>,
+		/// The cells to replace them with, row by row.
+		new: Vec<Cell>,
+	},
+	/// Inserts a row at `index`, made of `cells`.
+	InsertRow {
+		/// Where to insert the row.
+		index: usize,
+		/// The cells of the row.
+		cells: Vec<Cell>,
+	},
+	/// Removes the row at `index`, which is made of `cells`.
+	RemoveRow {
+		/// Where the row is.
+		index: usize,
+		/// The cells of the row.
+		cells: Vec<Cell>,
+	},
+	/// Inserts a column at `index`, made of `cells`.
+	InsertColumn {
+		/// Where to insert the column.
+		index: usize,
+		/// The cells of the column.
+		cells: Vec<Cell>,
+	},
+	/// Removes the column at `index`, which is made of `cells`.
+	RemoveColumn {
+		/// Where the column is.
+		index: usize,
+		/// The cells of the column.
+		cells: Vec<Cell>,
+	},
+}
+
+impl<Cell: Clone> GridEdit<Cell> {
+	/// Creates an edit which replaces the cells of `region` in `grid` with `new` (listed row by
+	/// row), capturing the cells being replaced.
+	///
+	/// Returns `None` if `region` is out of bounds, or `new` does not have one cell for each cell
+	/// of `region`.
+	pub fn set_region<G>(grid: &G, region: Region, new: Vec<Cell>) -> Option<Self>
+	where
+		G: GridAccess<Cell = Cell>,
+	{
+		if new.len() != region.len() {
+			return None;
+		}
+		let old = region
+			.positions()
+			.map(|(row, column)| grid.cell(row, column).cloned())
+			.collect::<Option<Vec<_>>>()?;
+		Some(Self::SetCells { region, old, new })
+	}
+
+	/// Creates an edit which replaces the cell at `row` and `column` of `grid` with `cell`,
+	/// capturing the cell being replaced.
+	///
+	/// Returns `None` if the cell is out of bounds.
+	pub fn set_cell<G>(grid: &G, row: usize, column: usize, cell: Cell) -> Option<Self>
+	where
+		G: GridAccess<Cell = Cell>,
+	{
+		Self::set_region(grid, Region::cell(row, column), alloc::vec![cell])
+	}
+
+	/// Creates an edit which fills every cell of `region` in `grid` with `cell`, capturing the
+	/// cells being replaced.
+	///
+	/// Returns `None` if `region` is out of bounds.
+	pub fn fill<G>(grid: &G, region: Region, cell: Cell) -> Option<Self>
+	where
+		G: GridAccess<Cell = Cell>,
+	{
+		Self::set_region(grid, region, alloc::vec![cell; region.len()])
+	}
+
+	/// Creates an edit which removes the row at `index` of `grid`, capturing its cells.
+	///
+	/// Returns `None` if the row is out of bounds.
+	pub fn remove_row<G>(grid: &G, index: usize) -> Option<Self>
+	where
+		G: GridAccess<Cell = Cell>,
+	{
+		let cells = (0..grid.columns())
+			.map(|column| grid.cell(index, column).cloned())
+			.collect::<Option<Vec<_>>>()?;
+		Some(Self::RemoveRow { index, cells })
+	}
+
+	/// Creates an edit which removes the column at `index` of `grid`, capturing its cells.
+	///
+	/// Returns `None` if the column is out of bounds.
+	pub fn remove_column<G>(grid: &G, index: usize) -> Option<Self>
+	where
+		G: GridAccess<Cell = Cell>,
+	{
+		let cells = (0..grid.rows())
+			.map(|row| grid.cell(row, index).cloned())
+			.collect::<Option<Vec<_>>>()?;
+		Some(Self::RemoveColumn { index, cells })
+	}
+}
+
+impl<G: GridAccess> Operation<G> for GridEdit<G::Cell> {
+	fn apply(&self, item: &mut G) {
+		match self {
+			Self::SetCells { region, new, .. } => {
+				for ((row, column), cell) in region.positions().zip(new) {
+					item.set_cell(row, column, cell.clone());
+				}
+			}
+			Self::InsertRow { index, cells } => item.insert_row(*index, cells.clone()),
+			Self::RemoveRow { index, .. } => item.remove_row(*index),
+			Self::InsertColumn { index, cells } => item.insert_column(*index, cells.clone()),
+			Self::RemoveColumn { index, .. } => item.remove_column(*index),
+		}
+	}
+}
+
+impl<G: GridAccess> InvertibleOperation<G> for GridEdit<G::Cell> {
+	fn inverse(&self) -> Self {
+		match self {
+			Self::SetCells { region, old, new } => Self::SetCells {
+				region: *region,
+				old: new.clone(),
+				new: old.clone(),
+			},
+			Self::InsertRow { index, cells } => Self::RemoveRow {
+				index: *index,
+				cells: cells.clone(),
+			},
+			Self::RemoveRow { index, cells } => Self::InsertRow {
+				index: *index,
+				cells: cells.clone(),
+			},
+			Self::InsertColumn { index, cells } => Self::RemoveColumn {
+				index: *index,
+				cells: cells.clone(),
+			},
+			Self::RemoveColumn { index, cells } => Self::InsertColumn {
+				index: *index,
+				cells: cells.clone(),
+			},
+		}
+	}
+}
+
+/// Merges consecutive changes to cells, when one region contains the other, or the two regions
+/// are next to each other and together form a rectangle (such as when filling cells one at a time
+/// by dragging). Other edits are not merged.
+impl<Cell: Clone> MergeableOperation for GridEdit<Cell> {
+	fn merge(&mut self, next: Self) -> Result<(), Self> {
+		let (
+			Self::SetCells { region, old, new },
+			Self::SetCells {
+				region: next_region,
+				old: next_old,
+				new: next_new,
+			},
+		) = (&mut *self, &next)
+		else {
+			return Err(next);
+		};
+		let Some(merged) = region.union(next_region) else {
+			return Err(next);
+		};
+
+		// Each cell's old value comes from the first edit to touch it, and its new value from the
+		// last edit to touch it.
+		let cell_of = |row, column| {
+			if next_region.contains(&Region::cell(row, column)) {
+				let offset = next_region.offset_of(row, column);
+				let old = if region.contains(&Region::cell(row, column)) {
+					&old[region.offset_of(row, column)]
+				} else {
+					&next_old[offset]
+				};
+				(old.clone(), next_new[offset].clone())
+			} else {
+				let offset = region.offset_of(row, column);
+				(old[offset].clone(), new[offset].clone())
+			}
+		};
+		let (merged_old, merged_new) = merged
+			.positions()
+			.map(|(row, column)| cell_of(row, column))
+			.unzip();
+		*self = Self::SetCells {
+			region: merged,
+			old: merged_old,
+			new: merged_new,
+		};
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use alloc::vec;
+
+	use super::*;
+	use crate::ops::testing::{assert_merges_like_separate, assert_round_trips};
+
+	/// A grid stored row by row.
+	#[derive(Clone, Debug, PartialEq)]
+	struct Sheet {
+		columns: usize,
+		cells: Vec<Vec<u8>>,
+	}
+
+	impl GridAccess for Sheet {
+		type Cell = u8;
+
+		fn rows(&self) -> usize {
+			self.cells.len()
+		}
+
+		fn columns(&self) -> usize {
+			self.columns
+		}
+
+		fn cell(&self, row: usize, column: usize) -> Option<&u8> {
+			self.cells.get(row)?.get(column)
+		}
+
+		fn set_cell(&mut self, row: usize, column: usize, cell: u8) {
+			self.cells[row][column] = cell;
+		}
+
+		fn insert_row(&mut self, index: usize, cells: Vec<u8>) {
+			self.cells.insert(index, cells);
+		}
+
+		fn remove_row(&mut self, index: usize) {
+			self.cells.remove(index);
+		}
+
+		fn insert_column(&mut self, index: usize, cells: Vec<u8>) {
+			for (row, cell) in self.cells.iter_mut().zip(cells) {
+				row.insert(index, cell);
+			}
+			self.columns += 1;
+		}
+
+		fn remove_column(&mut self, index: usize) {
+			for row in &mut self.cells {
+				row.remove(index);
+			}
+			self.columns -= 1;
+		}
+	}
+
+	/// Returns a 3×3 grid numbered 1 to 9, row by row.
+	fn sheet() -> Sheet {
+		Sheet {
+			columns: 3,
+			cells: vec![vec![1, 2, 3], vec![4, 5, 6], vec![7, 8, 9]],
+		}
+	}
+
+	fn region(row: usize, column: usize, rows: usize, columns: usize) -> Region {
+		Region {
+			row,
+			column,
+			rows,
+			columns,
+		}
+	}
+
+	#[test]
+	fn edits_round_trip() {
+		let sheet = sheet();
+		let filled = assert_round_trips(
+			&sheet,
+			&GridEdit::fill(&sheet, region(1, 1, 2, 2), 0).unwrap(),
+		);
+		assert_eq!(filled.cells, [[1, 2, 3], [4, 0, 0], [7, 0, 0]]);
+		let set = assert_round_trips(&sheet, &GridEdit::set_cell(&sheet, 0, 2, 30).unwrap());
+		assert_eq!(set.cells[0], [1, 2, 30]);
+
+		let without_row = assert_round_trips(&sheet, &GridEdit::remove_row(&sheet, 1).unwrap());
+		assert_eq!(without_row.cells, [[1, 2, 3], [7, 8, 9]]);
+		let without_column =
+			assert_round_trips(&sheet, &GridEdit::remove_column(&sheet, 0).unwrap());
+		assert_eq!(without_column.cells, [[2, 3], [5, 6], [8, 9]]);
+		let with_row = GridEdit::InsertRow {
+			index: 3,
+			cells: vec![0; 3],
+		};
+		assert_eq!(assert_round_trips(&sheet, &with_row).cells[3], [0; 3]);
+		let with_column = GridEdit::InsertColumn {
+			index: 1,
+			cells: vec![0; 3],
+		};
+		assert_eq!(
+			assert_round_trips(&sheet, &with_column).cells[0],
+			[1, 0, 2, 3]
+		);
+
+		assert_eq!(GridEdit::fill(&sheet, region(2, 2, 2, 1), 0), None);
+		assert_eq!(
+			GridEdit::set_region(&sheet, region(0, 0, 1, 2), vec![0]),
+			None
+		);
+	}
+
+	/// Returns edits which set each of `cells` in turn, each captured from the grid as the
+	/// edits before it left it.
+	fn edits_setting(cells: &[(usize, usize, u8)]) -> Vec<GridEdit<u8>> {
+		let mut grid = sheet();
+		cells
+			.iter()
+			.map(|&(row, column, cell)| {
+				let edit = GridEdit::set_cell(&grid, row, column, cell).unwrap();
+				edit.apply(&mut grid);
+				edit
+			})
+			.collect()
+	}
+
+	#[test]
+	fn dragging_merges_into_rectangles() {
+		// Across a row, then down the column after it.
+		let edits = edits_setting(&[(0, 0, 0), (0, 1, 0), (0, 2, 0), (1, 0, 0), (1, 1, 0)]);
+		assert_eq!(assert_merges_like_separate(&sheet(), &edits), 2);
+
+		let mut grid = sheet();
+		let column = GridEdit::fill(&grid, region(0, 1, 2, 1), 0).unwrap();
+		column.apply(&mut grid);
+		let next_column = GridEdit::fill(&grid, region(0, 2, 2, 1), 10).unwrap();
+		next_column.apply(&mut grid);
+		// The same cell again, within the merged region.
+		let inner = GridEdit::set_cell(&grid, 1, 1, 20).unwrap();
+		let edits = [column, next_column, inner];
+		assert_eq!(assert_merges_like_separate(&sheet(), &edits), 1);
+	}
+
+	#[test]
+	fn overlapping_regions_keep_first_old_cells() {
+		let mut grid = sheet();
+		let inner = GridEdit::set_cell(&grid, 1, 1, 0).unwrap();
+		inner.apply(&mut grid);
+		let outer = GridEdit::fill(&grid, region(0, 0, 3, 3), 7).unwrap();
+
+		let mut merged = inner.clone();
+		merged.merge(outer.clone()).unwrap();
+		let GridEdit::SetCells { old, .. } = &merged else {
+			panic!("cell edits should merge into a cell edit");
+		};
+		assert_eq!(*old, [1, 2, 3, 4, 5, 6, 7, 8, 9]);
+		assert_eq!(assert_merges_like_separate(&sheet(), &[inner, outer]), 1);
+	}
+
+	#[test]
+	fn other_edits_do_not_merge() {
+		let edits = edits_setting(&[(0, 0, 0), (2, 2, 0)]);
+		assert_eq!(assert_merges_like_separate(&sheet(), &edits), 2);
+		let sheet = sheet();
+		let rows = [
+			GridEdit::remove_row(&sheet, 0).unwrap(),
+			GridEdit::InsertRow {
+				index: 0,
+				cells: vec![1, 2, 3],
+			},
+		];
+		assert_eq!(assert_merges_like_separate(&sheet, &rows), 2);
+	}
+}
@@ -0,0 +1,289 @@
+//! Operations on a `BTreeMap` or `HashMap`.
+
+use alloc::collections::BTreeMap;
+#[cfg(feature = "std")]
+use core::hash::{BuildHasher, Hash};
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+use crate::{InvertibleOperation, MergeableOperation, Operation};
+
+/// An edit to one entry of a map from `K` to `V`.
+///
+/// The constructors take the entry's current value (such as from `map.get(&key)`), which they
+/// capture so that the edit can be undone.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum MapEdit<K, V> {
+	/// Sets the entry for `key` to `value`, where it was `previous` (or absent, if `None`).
+	Insert {
+		/// The key of the entry.
+		key: K,
+		/// The value to insert.
+		value: V,
+		/// The value the entry had before, if any.
+		previous: Option<V>,
+	},
+	/// Removes the entry for `key`, whose value is `value`.
+	Remove {
+		/// The key of the entry.
+		key: K,
+		/// The value being removed.
+		value: V,
+	},
+	/// Replaces `old`, the value of the existing entry for `key`, with `new`.
+	Replace {
+		/// The key of the entry.
+		key: K,
+		/// The value being replaced.
+		old: V,
+		/// The value to replace it with.
+		new: V,
+	},
+}
+
+impl<K, V: Clone> MapEdit<K, V> {
+	/// Creates an edit which sets the entry for `key` to `value`. `current` is the entry's current
+	/// value, if it has one.
+	pub fn insert(key: K, value: V, current: Option<&V>) -> Self {
+		Self::Insert {
+			key,
+			value,
+			previous: current.cloned(),
+		}
+	}
+
+	/// Creates an edit which removes the entry for `key`. `current` is the entry's current value.
+	///
+	/// Returns `None` if there is no entry for `key` (if `current` is `None`).
+	pub fn remove(key: K, current: Option<&V>) -> Option<Self> {
+		Some(Self::Remove {
+			key,
+			value: current?.clone(),
+		})
+	}
+
+	/// Creates an edit which replaces the value of the entry for `key` with `new`. `current` is
+	/// the entry's current value.
+	///
+	/// Returns `None` if there is no entry for `key` (if `current` is `None`).
+	pub fn replace(key: K, new: V, current: Option<&V>) -> Option<Self> {
+		Some(Self::Replace {
+			key,
+			old: current?.clone(),
+			new,
+		})
+	}
+}
+
+impl<K, V> MapEdit<K, V> {
+	/// Returns the key of the entry this edits.
+	pub fn key(&self) -> &K {
+		match self {
+			Self::Insert { key, .. } | Self::Remove { key, .. } | Self::Replace { key, .. } => key,
+		}
+	}
+
+	/// Splits this edit into its key, and the entry's value before and after it.
+	fn into_states(self) -> (K, Option<V>, Option<V>) {
+		match self {
+			Self::Insert {
+				key,
+				value,
+				previous,
+			} => (key, previous, Some(value)),
+			Self::Remove { key, value } => (key, Some(value), None),
+			Self::Replace { key, old, new } => (key, Some(old), Some(new)),
+		}
+	}
+
+	/// Creates an edit which changes the entry for `key` from `before` to `after`, or returns
+	/// `None` if the entry would be absent both before and after.
+	fn from_states(key: K, before: Option<V>, after: Option<V>) -> Option<Self> {
+		match (before, after) {
+			(previous, Some(value)) => Some(Self::Insert {
+				key,
+				value,
+				previous,
+			}),
+			(Some(value), None) => Some(Self::Remove { key, value }),
+			(None, None) => None,
+		}
+	}
+
+	/// Returns the value the entry has before this edit, or `None` if there is no entry.
+	fn before(&self) -> Option<&V> {
+		match self {
+			Self::Insert { previous, .. } => previous.as_ref(),
+			Self::Remove { value, .. } | Self::Replace { old: value, .. } => Some(value),
+		}
+	}
+
+	/// Returns the value the entry has after this edit, or `None` if the entry is removed.
+	fn after(&self) -> Option<&V> {
+		match self {
+			Self::Insert { value, .. } | Self::Replace { new: value, .. } => Some(value),
+			Self::Remove { .. } => None,
+		}
+	}
+
+	/// Like [`Self::after`], but returns the value mutably.
+	fn after_mut(&mut self) -> Option<&mut V> {
+		match self {
+			Self::Insert { value, .. } | Self::Replace { new: value, .. } => Some(value),
+			Self::Remove { .. } => None,
+		}
+	}
+
+	/// Returns an edit which reverts this one.
+	fn invert(&self) -> Self
+	where
+		K: Clone,
+		V: Clone,
+	{
+		match self {
+			Self::Insert {
+				key,
+				value,
+				previous: Some(previous),
+			} => Self::Replace {
+				key: key.clone(),
+				old: value.clone(),
+				new: previous.clone(),
+			},
+			Self::Insert {
+				key,
+				value,
+				previous: None,
+			} => Self::Remove {
+				key: key.clone(),
+				value: value.clone(),
+			},
+			Self::Remove { key, value } => Self::Insert {
+				key: key.clone(),
+				value: value.clone(),
+				previous: None,
+			},
+			Self::Replace { key, old, new } => Self::Replace {
+				key: key.clone(),
+				old: new.clone(),
+				new: old.clone(),
+			},
+		}
+	}
+}
+
+impl<K: Ord + Clone, V: Clone> Operation<BTreeMap<K, V>> for MapEdit<K, V> {
+	fn apply(&self, item: &mut BTreeMap<K, V>) {
+		match self.after() {
+			Some(value) => {
+				item.insert(self.key().clone(), value.clone());
+			}
+			None => {
+				item.remove(self.key());
+			}
+		}
+	}
+}
+
+impl<K: Ord + Clone, V: Clone> InvertibleOperation<BTreeMap<K, V>> for MapEdit<K, V> {
+	fn inverse(&self) -> Self {
+		self.invert()
+	}
+}
+
+#[cfg(feature = "std")]
+impl<K: Eq + Hash + Clone, V: Clone, S: BuildHasher> Operation<HashMap<K, V, S>> for MapEdit<K, V> {
+	fn apply(&self, item: &mut HashMap<K, V, S>) {
+		match self.after() {
+			Some(value) => {
+				item.insert(self.key().clone(), value.clone());
+			}
+			None => {
+				item.remove(self.key());
+			}
+		}
+	}
+}
+
+#[cfg(feature = "std")]
+impl<K: Eq + Hash + Clone, V: Clone, S: BuildHasher> InvertibleOperation<HashMap<K, V, S>>
+	for MapEdit<K, V>
+{
+	fn inverse(&self) -> Self {
+		self.invert()
+	}
+}
+
+/// Merges consecutive edits to the same key into one edit, from the entry's value before the first
+/// to its value after the last. Edits which would cancel out (inserting a new entry, then removing
+/// it) are not merged.
+impl<K: PartialEq + Clone, V: Clone> MergeableOperation for MapEdit<K, V> {
+	fn merge(&mut self, next: Self) -> Result<(), Self> {
+		if next.key() != self.key() || (self.before().is_none() && next.after().is_none()) {
+			return Err(next);
+		}
+		let (_, _, after) = next.into_states();
+		match (self.after_mut(), after) {
+			(Some(slot), Some(value)) => *slot = value,
+			(_, after) => {
+				let (key, before, _) = self.clone().into_states();
+				*self = Self::from_states(key, before, after)
+					.expect("the entry should exist either before or after the merged edit");
+			}
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::ops::testing::{assert_merges_like_separate, assert_round_trips};
+
+	fn map() -> BTreeMap<&'static str, i32> {
+		BTreeMap::from([("a", 1), ("b", 2)])
+	}
+
+	#[test]
+	fn edits_round_trip() {
+		let map = map();
+		let inserted = assert_round_trips(&map, &MapEdit::insert("c", 3, map.get("c")));
+		assert_eq!(inserted.get("c"), Some(&3));
+		let overwritten = assert_round_trips(&map, &MapEdit::insert("a", 5, map.get("a")));
+		assert_eq!(overwritten.get("a"), Some(&5));
+		let removed = assert_round_trips(&map, &MapEdit::remove("a", map.get("a")).unwrap());
+		assert_eq!(removed.get("a"), None);
+		let replaced = assert_round_trips(&map, &MapEdit::replace("b", 4, map.get("b")).unwrap());
+		assert_eq!(replaced.get("b"), Some(&4));
+		assert_eq!(MapEdit::remove("c", map.get("c")), None);
+	}
+
+	#[test]
+	#[cfg(feature = "std")]
+	fn edits_round_trip_on_hash_maps() {
+		let map: HashMap<_, _> = map().into_iter().collect();
+		let removed = assert_round_trips(&map, &MapEdit::remove("a", map.get("a")).unwrap());
+		assert_eq!(removed.len(), 1);
+	}
+
+	#[test]
+	fn edits_to_same_key_merge() {
+		let edits = [
+			MapEdit::replace("a", 2, Some(&1)).unwrap(),
+			MapEdit::replace("a", 3, Some(&2)).unwrap(),
+			MapEdit::remove("a", Some(&3)).unwrap(),
+			MapEdit::insert("a", 4, None),
+			MapEdit::insert("b", 5, Some(&2)),
+		];
+		assert_eq!(assert_merges_like_separate(&map(), &edits), 2);
+	}
+
+	#[test]
+	fn cancelling_edits_are_not_merged() {
+		let edits = [
+			MapEdit::insert("c", 3, None),
+			MapEdit::remove("c", Some(&3)).unwrap(),
+		];
+		assert_eq!(assert_merges_like_separate(&map(), &edits), 2);
+	}
+}
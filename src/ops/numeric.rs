@@ -0,0 +1,99 @@
+//! Operations which change a number by an amount, rather than setting it.
+//!
+//! Each operation's inverse is worked out from the amount, so no value from before the operation
+//! needs to be stored. To change a number within a larger object, wrap the operation in a
+//! [`Lens`](crate::Lens).
+
+use core::ops::{Add, Div, Mul, Sub};
+
+use crate::{InvertibleOperation, MergeableOperation, Operation};
+
+/// A change to a number of type `T` by an amount.
+///
+/// Inverting a change does not always restore the exact value from before it. Floating-point
+/// arithmetic may round the result, integer arithmetic may overflow, and integer division
+/// discards remainders. Multiplying by zero cannot be inverted at all.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Delta<T> {
+	/// Adds the amount.
+	Add(T),
+	/// Subtracts the amount.
+	Sub(T),
+	/// Multiplies by the amount.
+	Mul(T),
+	/// Divides by the amount.
+	Div(T),
+}
+
+impl<T> Operation<T> for Delta<T>
+where
+	T: Copy + Add<Output = T> + Sub<Output = T> + Mul<Output = T> + Div<Output = T>,
+{
+	fn apply(&self, item: &mut T) {
+		*item = match *self {
+			Self::Add(amount) => *item + amount,
+			Self::Sub(amount) => *item - amount,
+			Self::Mul(amount) => *item * amount,
+			Self::Div(amount) => *item / amount,
+		};
+	}
+}
+
+impl<T> InvertibleOperation<T> for Delta<T>
+where
+	T: Copy + Add<Output = T> + Sub<Output = T> + Mul<Output = T> + Div<Output = T>,
+{
+	fn inverse(&self) -> Self {
+		match *self {
+			Self::Add(amount) => Self::Sub(amount),
+			Self::Sub(amount) => Self::Add(amount),
+			Self::Mul(amount) => Self::Div(amount),
+			Self::Div(amount) => Self::Mul(amount),
+		}
+	}
+}
+
+/// Merges consecutive changes of the same kind, by adding the amounts of additions and
+/// subtractions, and multiplying the amounts of multiplications and divisions.
+impl<T> MergeableOperation for Delta<T>
+where
+	T: Copy + Add<Output = T> + Mul<Output = T>,
+{
+	fn merge(&mut self, next: Self) -> Result<(), Self> {
+		*self = match (*self, next) {
+			(Self::Add(a), Self::Add(b)) => Self::Add(a + b),
+			(Self::Sub(a), Self::Sub(b)) => Self::Sub(a + b),
+			(Self::Mul(a), Self::Mul(b)) => Self::Mul(a * b),
+			(Self::Div(a), Self::Div(b)) => Self::Div(a * b),
+			_ => return Err(next),
+		};
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::ops::testing::{assert_merges_like_separate, assert_round_trips};
+
+	#[test]
+	fn deltas_round_trip() {
+		assert_eq!(assert_round_trips(&10, &Delta::Add(5)), 15);
+		assert_eq!(assert_round_trips(&10, &Delta::Sub(5)), 5);
+		assert_eq!(assert_round_trips(&10, &Delta::Mul(3)), 30);
+		assert_eq!(assert_round_trips(&1.5, &Delta::Div(0.5)), 3.0);
+	}
+
+	#[test]
+	fn same_kinds_merge() {
+		let edits = [Delta::Add(1), Delta::Add(2), Delta::Sub(4), Delta::Sub(1)];
+		assert_eq!(assert_merges_like_separate(&0, &edits), 2);
+		let edits = [
+			Delta::Mul(2.0),
+			Delta::Mul(4.0),
+			Delta::Div(0.5),
+			Delta::Div(2.0),
+		];
+		assert_eq!(assert_merges_like_separate(&1.0, &edits), 2);
+	}
+}
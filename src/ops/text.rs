@@ -0,0 +1,226 @@
+//! Operations on a `String`.
+//!
+//! Positions are byte offsets, which must lie on character boundaries. To work in characters
+//! instead, convert positions with [`char_to_byte`].
+
+use alloc::string::String;
+use core::ops::Range;
+
+use crate::{InvertibleOperation, MergeableOperation, Operation};
+
+/// An edit to a `String`.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum TextEdit {
+	/// Inserts `text` at byte offset `at`.
+	Insert {
+		/// Where to insert the text.
+		at: usize,
+		/// The text to insert.
+		text: String,
+	},
+	/// Deletes `text`, which starts at byte offset `at`.
+	Delete {
+		/// Where the deleted text starts.
+		at: usize,
+		/// The text to delete.
+		text: String,
+	},
+	/// Replaces `old`, which starts at byte offset `at`, with `new`.
+	Replace {
+		/// Where the replaced text starts.
+		at: usize,
+		/// The text being replaced.
+		old: String,
+		/// The text to replace it with.
+		new: String,
+	},
+}
+
+impl TextEdit {
+	/// Creates an edit which inserts `text` at byte offset `at`.
+	pub fn insert(at: usize, text: impl Into<String>) -> Self {
+		Self::Insert {
+			at,
+			text: text.into(),
+		}
+	}
+
+	/// Creates an edit which deletes the bytes in `range` of `target`, capturing the deleted text
+	/// so that the deletion can be undone.
+	///
+	/// # Panics
+	/// Panics if `range` is out of bounds of `target`, or does not lie on character boundaries.
+	pub fn delete(target: &str, range: Range<usize>) -> Self {
+		Self::Delete {
+			at: range.start,
+			text: String::from(&target[range]),
+		}
+	}
+
+	/// Creates an edit which replaces the bytes in `range` of `target` with `new`, capturing the
+	/// replaced text so that the replacement can be undone.
+	///
+	/// # Panics
+	/// See [`Self::delete`].
+	pub fn replace(target: &str, range: Range<usize>, new: impl Into<String>) -> Self {
+		Self::Replace {
+			at: range.start,
+			old: String::from(&target[range]),
+			new: new.into(),
+		}
+	}
+}
+
+impl Operation<String> for TextEdit {
+	fn apply(&self, item: &mut String) {
+		match self {
+			Self::Insert { at, text } => item.insert_str(*at, text),
+			Self::Delete { at, text } => {
+				item.replace_range(*at..*at + text.len(), "");
+			}
+			Self::Replace { at, old, new } => item.replace_range(*at..*at + old.len(), new),
+		}
+	}
+}
+
+impl InvertibleOperation<String> for TextEdit {
+	fn inverse(&self) -> Self {
+		match self {
+			Self::Insert { at, text } => Self::Delete {
+				at: *at,
+				text: text.clone(),
+			},
+			Self::Delete { at, text } => Self::Insert {
+				at: *at,
+				text: text.clone(),
+			},
+			Self::Replace { at, old, new } => Self::Replace {
+				at: *at,
+				old: new.clone(),
+				new: old.clone(),
+			},
+		}
+	}
+}
+
+/// Merges edits made while typing: insertions directly after one another, and deletions with
+/// Backspace or Delete from the same place. An insertion that starts a new word (whitespace
+/// followed by anything else) is not merged, so that undoing removes one word at a time.
+impl MergeableOperation for TextEdit {
+	fn merge(&mut self, next: Self) -> Result<(), Self> {
+		match (self, next) {
+			(
+				Self::Insert { at, text },
+				Self::Insert {
+					at: next_at,
+					text: next_text,
+				},
+			) if next_at == *at + text.len() && !starts_word(text, &next_text) => {
+				text.push_str(&next_text);
+				Ok(())
+			}
+			// Pressing Delete repeatedly removes text from the same place.
+			(
+				Self::Delete { at, text },
+				Self::Delete {
+					at: next_at,
+					text: next_text,
+				},
+			) if next_at == *at => {
+				text.push_str(&next_text);
+				Ok(())
+			}
+			// Pressing Backspace repeatedly removes text just before the last deletion.
+			(
+				Self::Delete { at, text },
+				Self::Delete {
+					at: next_at,
+					text: mut next_text,
+				},
+			) if next_at + next_text.len() == *at => {
+				next_text.push_str(text);
+				*at = next_at;
+				*text = next_text;
+				Ok(())
+			}
+			(_, next) => Err(next),
+		}
+	}
+}
+
+/// Returns `true` if appending `next` to `text` starts a new word.
+fn starts_word(text: &str, next: &str) -> bool {
+	let ends_with_space = text.chars().next_back().is_some_and(char::is_whitespace);
+	let next_is_space = next.chars().next().is_some_and(char::is_whitespace);
+	ends_with_space && !next_is_space
+}
+
+/// Returns the byte offset of the character at `char_index` in `text`, or the length of `text` if
+/// `char_index` is the number of characters in it.
+///
+/// Returns `None` if `char_index` is greater than the number of characters in `text`.
+pub fn char_to_byte(text: &str, char_index: usize) -> Option<usize> {
+	text.char_indices()
+		.map(|(byte, _)| byte)
+		.chain([text.len()])
+		.nth(char_index)
+}
+
+#[cfg(test)]
+mod tests {
+	use alloc::vec::Vec;
+
+	use super::*;
+	use crate::ops::testing::{assert_merges_like_separate, assert_round_trips};
+
+	#[test]
+	fn edits_round_trip() {
+		let text = String::from("héllo world");
+		let inserted = assert_round_trips(&text, &TextEdit::insert(7, "big "));
+		assert_eq!(inserted, "héllo big world");
+		let deleted = assert_round_trips(&text, &TextEdit::delete(&text, 0..7));
+		assert_eq!(deleted, "world");
+		let replaced = assert_round_trips(&text, &TextEdit::replace(&text, 7..12, "there"));
+		assert_eq!(replaced, "héllo there");
+	}
+
+	#[test]
+	fn typing_merges_by_word() {
+		let typed = ["h", "i", " ", "t", "h", "e", "r", "e"];
+		let mut at = 0;
+		let edits: Vec<_> = typed
+			.iter()
+			.map(|text| {
+				let edit = TextEdit::insert(at, *text);
+				at += text.len();
+				edit
+			})
+			.collect();
+		assert_eq!(assert_merges_like_separate(&String::new(), &edits), 2);
+	}
+
+	#[test]
+	fn deleting_merges() {
+		let text = String::from("abcdef");
+		// Pressing Delete at 1 twice, then Backspace from 1 once.
+		let edits = [
+			TextEdit::delete(&text, 1..2),
+			TextEdit::delete("acdef", 1..2),
+			TextEdit::delete("adef", 0..1),
+		];
+		assert_eq!(assert_merges_like_separate(&text, &edits), 1);
+
+		let apart = [
+			TextEdit::delete(&text, 0..1),
+			TextEdit::delete("bcdef", 3..4),
+		];
+		assert_eq!(assert_merges_like_separate(&text, &apart), 2);
+	}
+
+	#[test]
+	fn converts_char_positions() {
+		assert_eq!(char_to_byte("héllo", 2), Some(3));
+		assert_eq!(char_to_byte("héllo", 5), Some(6));
+		assert_eq!(char_to_byte("héllo", 6), None);
+	}
+}
@@ -0,0 +1,195 @@
+//! Operations which move, rotate, and scale objects in 2D, such as the shapes of a drawing.
+
+use crate::{InvertibleOperation, MergeableOperation, Operation};
+
+/// An object which can be moved, rotated, and scaled in 2D.
+///
+/// How each transformation is applied (such as which point an object rotates around) is up to the
+/// implementation, but applying the inverse of a transformation (see [`Transform2d`]) should undo
+/// it.
+pub trait Transformable {
+	/// A description of the object's whole transformation, such as an affine matrix.
+	type Matrix: Clone;
+
+	/// Moves the object by `dx` and `dy`.
+	fn translate(&mut self, dx: f32, dy: f32);
+
+	/// Rotates the object by `radians`.
+	fn rotate(&mut self, radians: f32);
+
+	/// Scales the object by `sx` horizontally and `sy` vertically.
+	fn scale(&mut self, sx: f32, sy: f32);
+
+	/// Returns the object's whole transformation.
+	fn matrix(&self) -> Self::Matrix;
+
+	/// Replaces the object's whole transformation with `matrix`.
+	fn set_matrix(&mut self, matrix: &Self::Matrix);
+}
+
+/// A transformation of a [`Transformable`] object whose matrix is of type `M`.
+///
+/// Inverting a scale by zero, or by a very small amount, does not restore the object.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Transform2d<M> {
+	/// Moves the object.
+	Translate {
+		/// The horizontal distance.
+		dx: f32,
+		/// The vertical distance.
+		dy: f32,
+	},
+	/// Rotates the object by an angle in radians.
+	Rotate(f32),
+	/// Scales the object.
+	Scale {
+		/// The horizontal factor.
+		sx: f32,
+		/// The vertical factor.
+		sy: f32,
+	},
+	/// Replaces the object's matrix `old` with `new`.
+	SetMatrix {
+		/// The matrix before the operation.
+		old: M,
+		/// The matrix after the operation.
+		new: M,
+	},
+}
+
+impl<M> Transform2d<M> {
+	/// Creates an operation which replaces `target`'s matrix with `new`, capturing its current
+	/// matrix.
+	pub fn set_matrix<T: Transformable<Matrix = M>>(target: &T, new: M) -> Self {
+		Self::SetMatrix {
+			old: target.matrix(),
+			new,
+		}
+	}
+}
+
+impl<T: Transformable> Operation<T> for Transform2d<T::Matrix> {
+	fn apply(&self, item: &mut T) {
+		match self {
+			Self::Translate { dx, dy } => item.translate(*dx, *dy),
+			Self::Rotate(radians) => item.rotate(*radians),
+			Self::Scale { sx, sy } => item.scale(*sx, *sy),
+			Self::SetMatrix { new, .. } => item.set_matrix(new),
+		}
+	}
+}
+
+impl<T: Transformable> InvertibleOperation<T> for Transform2d<T::Matrix> {
+	fn inverse(&self) -> Self {
+		match self {
+			Self::Translate { dx, dy } => Self::Translate { dx: -dx, dy: -dy },
+			Self::Rotate(radians) => Self::Rotate(-radians),
+			Self::Scale { sx, sy } => Self::Scale {
+				sx: sx.recip(),
+				sy: sy.recip(),
+			},
+			Self::SetMatrix { old, new } => Self::SetMatrix {
+				old: new.clone(),
+				new: old.clone(),
+			},
+		}
+	}
+}
+
+/// Merges consecutive transformations of the same kind: translations and rotations add up,
+/// scales multiply, and matrix replacements keep the first old matrix and the last new one.
+impl<M> MergeableOperation for Transform2d<M> {
+	fn merge(&mut self, next: Self) -> Result<(), Self> {
+		match (self, next) {
+			(Self::Translate { dx, dy }, Self::Translate { dx: ndx, dy: ndy }) => {
+				*dx += ndx;
+				*dy += ndy;
+			}
+			(Self::Rotate(radians), Self::Rotate(next_radians)) => *radians += next_radians,
+			(Self::Scale { sx, sy }, Self::Scale { sx: nsx, sy: nsy }) => {
+				*sx *= nsx;
+				*sy *= nsy;
+			}
+			(Self::SetMatrix { new, .. }, Self::SetMatrix { new: next_new, .. }) => *new = next_new,
+			(_, next) => return Err(next),
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::ops::testing::{assert_merges_like_separate, assert_round_trips};
+
+	/// A shape whose transformation is kept as separate parts, so that it changes exactly.
+	#[derive(Clone, Debug, Default, PartialEq)]
+	struct Shape {
+		position: (f32, f32),
+		angle: f32,
+		scale: (f32, f32),
+	}
+
+	impl Transformable for Shape {
+		type Matrix = Self;
+
+		fn translate(&mut self, dx: f32, dy: f32) {
+			self.position.0 += dx;
+			self.position.1 += dy;
+		}
+
+		fn rotate(&mut self, radians: f32) {
+			self.angle += radians;
+		}
+
+		fn scale(&mut self, sx: f32, sy: f32) {
+			self.scale.0 *= sx;
+			self.scale.1 *= sy;
+		}
+
+		fn matrix(&self) -> Self {
+			self.clone()
+		}
+
+		fn set_matrix(&mut self, matrix: &Self) {
+			self.clone_from(matrix);
+		}
+	}
+
+	fn shape() -> Shape {
+		Shape {
+			scale: (1.0, 1.0),
+			..Shape::default()
+		}
+	}
+
+	#[test]
+	fn transforms_round_trip() {
+		let shape = shape();
+		let moved = assert_round_trips(&shape, &Transform2d::Translate { dx: 1.5, dy: -2.0 });
+		assert_eq!(moved.position, (1.5, -2.0));
+		let rotated = assert_round_trips(&shape, &Transform2d::Rotate(0.25));
+		assert_eq!(rotated.angle, 0.25);
+		let scaled = assert_round_trips(&shape, &Transform2d::Scale { sx: 2.0, sy: 0.5 });
+		assert_eq!(scaled.scale, (2.0, 0.5));
+		let set = Transform2d::set_matrix(&shape, moved.clone());
+		assert_eq!(assert_round_trips(&shape, &set), moved);
+	}
+
+	#[test]
+	fn same_kinds_merge() {
+		let mut later = shape();
+		later.angle = 1.0;
+		let edits = [
+			Transform2d::set_matrix(&shape(), later.clone()),
+			Transform2d::set_matrix(&later, shape()),
+			Transform2d::Translate { dx: 1.0, dy: 0.0 },
+			Transform2d::Translate { dx: 0.5, dy: 2.0 },
+			Transform2d::Rotate(0.5),
+			Transform2d::Rotate(0.25),
+			Transform2d::Scale { sx: 2.0, sy: 4.0 },
+			Transform2d::Scale { sx: 0.5, sy: 2.0 },
+		];
+		assert_eq!(assert_merges_like_separate(&shape(), &edits), 4);
+	}
+}
@@ -0,0 +1,286 @@
+//! Operations on tree-structured objects, such as scene graphs, outliners, and documents.
+//!
+//! Operations reach the tree through [`TreeAccess`], which can be implemented for any tree whose
+//! nodes keep their identifiers when they are removed and inserted again.
+
+use crate::{InvertibleOperation, Operation};
+
+/// A tree whose nodes can be inserted, removed, and moved, along with all of their descendants.
+pub trait TreeAccess {
+	/// Identifies a node. A node must keep its identifier when it is removed and inserted again.
+	type NodeId: Clone;
+	/// A node along with all of its descendants, detached from the tree.
+	type Subtree: Clone;
+
+	/// Returns the parent of `node`, or `None` if it is a root or does not exist.
+	fn parent(&self, node: &Self::NodeId) -> Option<Self::NodeId>;
+
+	/// Returns the position of `node` among its parent's children, or `None` if it is a root or
+	/// does not exist.
+	fn child_index(&self, node: &Self::NodeId) -> Option<usize>;
+
+	/// Returns a copy of `node` and all of its descendants, or `None` if it does not exist.
+	fn subtree(&self, node: &Self::NodeId) -> Option<Self::Subtree>;
+
+	/// Returns the identifier of the node at the root of `subtree`.
+	fn subtree_root(subtree: &Self::Subtree) -> Self::NodeId;
+
+	/// Inserts `subtree` as a child of `parent`, at position `index` among its children.
+	fn insert_subtree(&mut self, parent: &Self::NodeId, index: usize, subtree: Self::Subtree);
+
+	/// Removes `node` and all of its descendants.
+	fn remove_subtree(&mut self, node: &Self::NodeId);
+
+	/// Moves `node` (with its descendants) to be a child of `parent`, at position `index` among
+	/// its children once `node` has been removed from its current place.
+	fn move_node(&mut self, node: &Self::NodeId, parent: &Self::NodeId, index: usize);
+}
+
+/// Where a node is within a tree: which node it is a child of, and its position among that node's
+/// children.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct Place<Id> {
+	/// The node's parent.
+	pub parent: Id,
+	/// The node's position among its parent's children.
+	pub index: usize,
+}
+
+/// An edit to a tree whose nodes are identified by `Id`, and whose detached subtrees are
+/// `Subtree`s.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum TreeEdit<Id, Subtree> {
+	/// Inserts `subtree` at `place`.
+	Insert {
+		/// Where to insert the subtree.
+		place: Place<Id>,
+		/// The node to insert, along with its descendants.
+		subtree: Subtree,
+	},
+	/// Removes the node at the root of `subtree`, which is at `place`, along with its descendants.
+	Remove {
+		/// Where the node is.
+		place: Place<Id>,
+		/// The node being removed, along with its descendants.
+		subtree: Subtree,
+	},
+	/// Moves `node` from `from` to `to`. This both reparents and reorders nodes.
+	Move {
+		/// The node to move.
+		node: Id,
+		/// Where the node is before the move.
+		from: Place<Id>,
+		/// Where the node is after the move.
+		to: Place<Id>,
+	},
+}
+
+impl<Id: Clone, Subtree> TreeEdit<Id, Subtree> {
+	/// Creates an edit which removes `node` and its descendants from `tree`, capturing them so
+	/// that the removal can be undone.
+	///
+	/// Returns `None` if `node` does not exist, or is a root.
+	pub fn remove<T>(tree: &T, node: &Id) -> Option<Self>
+	where
+		T: TreeAccess<NodeId = Id, Subtree = Subtree>,
+	{
+		Some(Self::Remove {
+			place: place_of(tree, node)?,
+			subtree: tree.subtree(node)?,
+		})
+	}
+
+	/// Creates an edit which moves `node` to be a child of `parent` in `tree`, at position `index`
+	/// among its children once `node` has been removed from its current place.
+	///
+	/// Returns `None` if `node` does not exist, or is a root.
+	pub fn move_to<T>(tree: &T, node: Id, parent: Id, index: usize) -> Option<Self>
+	where
+		T: TreeAccess<NodeId = Id, Subtree = Subtree>,
+	{
+		Some(Self::Move {
+			from: place_of(tree, &node)?,
+			node,
+			to: Place { parent, index },
+		})
+	}
+}
+
+/// Returns where `node` is within `tree`, or `None` if it does not exist, or is a root.
+fn place_of<T: TreeAccess>(tree: &T, node: &T::NodeId) -> Option<Place<T::NodeId>> {
+	Some(Place {
+		parent: tree.parent(node)?,
+		index: tree.child_index(node)?,
+	})
+}
+
+impl<T: TreeAccess> Operation<T> for TreeEdit<T::NodeId, T::Subtree> {
+	fn apply(&self, item: &mut T) {
+		match self {
+			Self::Insert { place, subtree } => {
+				item.insert_subtree(&place.parent, place.index, subtree.clone());
+			}
+			Self::Remove { subtree, .. } => item.remove_subtree(&T::subtree_root(subtree)),
+			Self::Move { node, to, .. } => item.move_node(node, &to.parent, to.index),
+		}
+	}
+}
+
+impl<T: TreeAccess> InvertibleOperation<T> for TreeEdit<T::NodeId, T::Subtree> {
+	fn inverse(&self) -> Self {
+		match self {
+			Self::Insert { place, subtree } => Self::Remove {
+				place: place.clone(),
+				subtree: subtree.clone(),
+			},
+			Self::Remove { place, subtree } => Self::Insert {
+				place: place.clone(),
+				subtree: subtree.clone(),
+			},
+			Self::Move { node, from, to } => Self::Move {
+				node: node.clone(),
+				from: to.clone(),
+				to: from.clone(),
+			},
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use alloc::{collections::BTreeMap, vec, vec::Vec};
+
+	use super::*;
+	use crate::ops::testing::assert_round_trips;
+
+	/// A tree stored as each node's list of children.
+	#[derive(Clone, Debug, PartialEq)]
+	struct Outline(BTreeMap<u32, Vec<u32>>);
+
+	#[derive(Clone, Debug, PartialEq)]
+	struct Subtree {
+		id: u32,
+		children: Vec<Subtree>,
+	}
+
+	impl Outline {
+		fn detach(&mut self, node: u32) {
+			for children in self.0.values_mut() {
+				children.retain(|&child| child != node);
+			}
+		}
+
+		fn forget(&mut self, node: u32) {
+			for child in self.0.remove(&node).unwrap_or_default() {
+				self.forget(child);
+			}
+		}
+
+		fn adopt(&mut self, subtree: Subtree) {
+			let children = subtree.children.iter().map(|child| child.id).collect();
+			self.0.insert(subtree.id, children);
+			subtree
+				.children
+				.into_iter()
+				.for_each(|child| self.adopt(child));
+		}
+	}
+
+	impl TreeAccess for Outline {
+		type NodeId = u32;
+		type Subtree = Subtree;
+
+		fn parent(&self, node: &u32) -> Option<u32> {
+			self.0
+				.iter()
+				.find(|(_, children)| children.contains(node))
+				.map(|(&parent, _)| parent)
+		}
+
+		fn child_index(&self, node: &u32) -> Option<usize> {
+			self.0
+				.values()
+				.find_map(|children| children.iter().position(|child| child == node))
+		}
+
+		fn subtree(&self, node: &u32) -> Option<Subtree> {
+			Some(Subtree {
+				id: *node,
+				children: self
+					.0
+					.get(node)?
+					.iter()
+					.filter_map(|child| self.subtree(child))
+					.collect(),
+			})
+		}
+
+		fn subtree_root(subtree: &Subtree) -> u32 {
+			subtree.id
+		}
+
+		fn insert_subtree(&mut self, parent: &u32, index: usize, subtree: Subtree) {
+			self.0.entry(*parent).or_default().insert(index, subtree.id);
+			self.adopt(subtree);
+		}
+
+		fn remove_subtree(&mut self, node: &u32) {
+			self.detach(*node);
+			self.forget(*node);
+		}
+
+		fn move_node(&mut self, node: &u32, parent: &u32, index: usize) {
+			self.detach(*node);
+			self.0.entry(*parent).or_default().insert(index, *node);
+		}
+	}
+
+	/// Returns a tree where 0 has the children 1 and 2, and 1 has the child 3.
+	fn outline() -> Outline {
+		Outline(BTreeMap::from([
+			(0, vec![1, 2]),
+			(1, vec![3]),
+			(2, vec![]),
+			(3, vec![]),
+		]))
+	}
+
+	#[test]
+	fn edits_round_trip() {
+		let tree = outline();
+		let insert = TreeEdit::Insert {
+			place: Place {
+				parent: 2,
+				index: 0,
+			},
+			subtree: Subtree {
+				id: 4,
+				children: vec![Subtree {
+					id: 5,
+					children: vec![],
+				}],
+			},
+		};
+		let inserted = assert_round_trips(&tree, &insert);
+		assert_eq!(inserted.0[&2], [4]);
+		assert_eq!(inserted.0[&4], [5]);
+
+		let removed = assert_round_trips(&tree, &TreeEdit::remove(&tree, &1).unwrap());
+		assert_eq!(
+			removed,
+			Outline(BTreeMap::from([(0, vec![2]), (2, vec![])]))
+		);
+
+		let moved = assert_round_trips(&tree, &TreeEdit::move_to(&tree, 3, 0, 2).unwrap());
+		assert_eq!(moved.0[&0], [1, 2, 3]);
+		assert_eq!(moved.0[&1], []);
+	}
+
+	#[test]
+	fn roots_cannot_be_removed_or_moved() {
+		let tree = outline();
+		assert_eq!(TreeEdit::remove(&tree, &0), None);
+		assert_eq!(TreeEdit::move_to(&tree, 0, 1, 0), None);
+		assert_eq!(TreeEdit::remove(&tree, &9), None);
+	}
+}
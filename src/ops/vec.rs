@@ -0,0 +1,167 @@
+//! Operations on a `Vec`.
+
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::{InvertibleOperation, Operation};
+
+/// An edit to a `Vec<T>`.
+///
+/// Indices refer to the vector as it is when the edit is applied, so the edits of an action should
+/// be created in the order they are applied. Applying an edit whose indices are out of bounds
+/// panics.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum VecEdit<T> {
+	/// Inserts `value` at `index`, shifting later elements along.
+	Insert {
+		/// Where to insert the element.
+		index: usize,
+		/// The element to insert.
+		value: T,
+	},
+	/// Removes the element at `index`, which is `value`, shifting later elements back.
+	Remove {
+		/// Where the element to remove is.
+		index: usize,
+		/// The element being removed.
+		value: T,
+	},
+	/// Swaps the elements at `a` and `b`.
+	Swap {
+		/// The index of one element.
+		a: usize,
+		/// The index of the other element.
+		b: usize,
+	},
+	/// Removes the element at `from`, then inserts it at `to`.
+	Move {
+		/// Where the element is moved from.
+		from: usize,
+		/// Where the element is moved to, after it has been removed.
+		to: usize,
+	},
+	/// Replaces the elements starting at `at`, which are `removed`, with `inserted`.
+	Splice {
+		/// Where the replaced elements start.
+		at: usize,
+		/// The elements being replaced.
+		removed: Vec<T>,
+		/// The elements to replace them with.
+		inserted: Vec<T>,
+	},
+}
+
+impl<T: Clone> VecEdit<T> {
+	/// Creates an edit which removes the element at `index` of `target`, capturing the element so
+	/// that the removal can be undone.
+	///
+	/// Returns `None` if `index` is out of bounds.
+	pub fn remove(target: &[T], index: usize) -> Option<Self> {
+		let value = target.get(index)?.clone();
+		Some(Self::Remove { index, value })
+	}
+
+	/// Creates an edit which replaces the elements in `range` of `target` with `inserted`,
+	/// capturing the replaced elements so that the replacement can be undone.
+	///
+	/// Returns `None` if `range` is out of bounds.
+	pub fn splice(target: &[T], range: Range<usize>, inserted: impl Into<Vec<T>>) -> Option<Self> {
+		let at = range.start;
+		let removed = target.get(range)?.to_vec();
+		Some(Self::Splice {
+			at,
+			removed,
+			inserted: inserted.into(),
+		})
+	}
+}
+
+impl<T: Clone> Operation<Vec<T>> for VecEdit<T> {
+	fn apply(&self, item: &mut Vec<T>) {
+		match self {
+			Self::Insert { index, value } => item.insert(*index, value.clone()),
+			Self::Remove { index, .. } => {
+				item.remove(*index);
+			}
+			Self::Swap { a, b } => item.swap(*a, *b),
+			Self::Move { from, to } => {
+				let value = item.remove(*from);
+				item.insert(*to, value);
+			}
+			Self::Splice {
+				at,
+				removed,
+				inserted,
+			} => {
+				item.splice(*at..*at + removed.len(), inserted.iter().cloned());
+			}
+		}
+	}
+}
+
+impl<T: Clone> InvertibleOperation<Vec<T>> for VecEdit<T> {
+	fn inverse(&self) -> Self {
+		match self {
+			Self::Insert { index, value } => Self::Remove {
+				index: *index,
+				value: value.clone(),
+			},
+			Self::Remove { index, value } => Self::Insert {
+				index: *index,
+				value: value.clone(),
+			},
+			Self::Swap { a, b } => Self::Swap { a: *a, b: *b },
+			Self::Move { from, to } => Self::Move {
+				from: *to,
+				to: *from,
+			},
+			Self::Splice {
+				at,
+				removed,
+				inserted,
+			} => Self::Splice {
+				at: *at,
+				removed: inserted.clone(),
+				inserted: removed.clone(),
+			},
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use alloc::vec;
+
+	use super::*;
+	use crate::ops::testing::assert_round_trips;
+
+	#[test]
+	fn edits_round_trip() {
+		let items = vec!['a', 'b', 'c', 'd'];
+		let edits = [
+			(
+				VecEdit::Insert {
+					index: 4,
+					value: 'e',
+				},
+				vec!['a', 'b', 'c', 'd', 'e'],
+			),
+			(VecEdit::remove(&items, 1).unwrap(), vec!['a', 'c', 'd']),
+			(VecEdit::Swap { a: 0, b: 3 }, vec!['d', 'b', 'c', 'a']),
+			(VecEdit::Move { from: 0, to: 2 }, vec!['b', 'c', 'a', 'd']),
+			(
+				VecEdit::splice(&items, 1..3, ['x']).unwrap(),
+				vec!['a', 'x', 'd'],
+			),
+		];
+		for (edit, expected) in edits {
+			assert_eq!(assert_round_trips(&items, &edit), expected);
+		}
+	}
+
+	#[test]
+	fn out_of_bounds_edits_are_not_created() {
+		assert_eq!(VecEdit::remove(&[1], 1), None);
+		assert_eq!(VecEdit::splice(&[1], 0..2, []), None);
+	}
+}
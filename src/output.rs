@@ -0,0 +1,143 @@
+use alloc::vec::Vec;
+
+use crate::{Action, HistoryStorage, UndoRedo, UndoRedoError};
+
+/// Like [`Operation`](crate::Operation), but produces an output each time it is applied, such as
+/// the ID of an object it re-created.
+///
+/// Use [`UndoRedo::undo_collecting`] and [`UndoRedo::redo_collecting`] to receive the outputs.
+pub trait OutputOperation<For> {
+	/// The value produced by applying this operation.
+	type Output;
+
+	fn apply(&self, item: &mut For) -> Self::Output;
+}
+
+impl<Op> Action<Op> {
+	/// Like [`Self::apply`], but for [`OutputOperation`]s. The output of each operation is added to
+	/// `outputs`, in order.
+	pub fn apply_collecting<For>(&self, apply_to: &mut For, outputs: &mut impl Extend<Op::Output>)
+	where
+		Op: OutputOperation<For>,
+	{
+		outputs.extend(self.apply_ops().iter().map(|op| op.apply(apply_to)));
+	}
+
+	/// Like [`Self::revert`], but for [`OutputOperation`]s. The output of each operation is added
+	/// to `outputs`, in order.
+	pub fn revert_collecting<For>(&self, apply_to: &mut For, outputs: &mut impl Extend<Op::Output>)
+	where
+		Op: OutputOperation<For>,
+	{
+		outputs.extend(self.revert_ops().iter().map(|op| op.apply(apply_to)));
+	}
+}
+
+impl<Op, S: HistoryStorage<Op>> UndoRedo<Op, S> {
+	/// Like [`Self::redo`], but for [`OutputOperation`]s. Returns the output of each operation
+	/// applied, in order.
+	///
+	/// # Errors
+	/// See [`Self::redo`].
+	///
+	/// # Panics
+	/// See [`Self::redo`].
+	pub fn redo_collecting<For>(
+		&mut self,
+		apply_to: &mut For,
+	) -> Result<Vec<Op::Output>, UndoRedoError>
+	where
+		Op: OutputOperation<For>,
+	{
+		let action = self
+			.actions
+			.get(self.tapehead)
+			.ok_or(UndoRedoError::NothingToDo)?;
+		self.tapehead = self
+			.tapehead
+			.checked_add(1)
+			.expect("tapehead should not be at usize::MAX");
+
+		let mut outputs = Vec::new();
+		action.apply_collecting(apply_to, &mut outputs);
+		Ok(outputs)
+	}
+
+	/// Like [`Self::undo`], but for [`OutputOperation`]s. Returns the output of each operation
+	/// applied, in order.
+	///
+	/// # Errors
+	/// See [`Self::undo`].
+	pub fn undo_collecting<For>(
+		&mut self,
+		apply_to: &mut For,
+	) -> Result<Vec<Op::Output>, UndoRedoError>
+	where
+		Op: OutputOperation<For>,
+	{
+		let index = self
+			.tapehead
+			.checked_sub(1)
+			.ok_or(UndoRedoError::NothingToDo)?;
+		let action = self.actions.get(index).ok_or(UndoRedoError::NothingToDo)?;
+		self.tapehead = index;
+
+		let mut outputs = Vec::new();
+		action.revert_collecting(apply_to, &mut outputs);
+		Ok(outputs)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use alloc::{string::String, vec};
+
+	use super::*;
+
+	/// Adds or removes a name at the end of a list, returning the index it was added at or removed
+	/// from.
+	#[derive(Debug)]
+	enum Edit {
+		Push(&'static str),
+		Pop,
+	}
+
+	impl OutputOperation<Vec<String>> for Edit {
+		type Output = usize;
+
+		fn apply(&self, item: &mut Vec<String>) -> usize {
+			match self {
+				Self::Push(name) => item.push(String::from(*name)),
+				Self::Pop => drop(item.pop()),
+			}
+			item.len()
+		}
+	}
+
+	#[test]
+	fn collects_outputs_in_order() {
+		let mut action = Action::default();
+		action
+			.add_redo_operation(Edit::Push("a"))
+			.add_redo_operation(Edit::Push("b"))
+			.add_undo_operation(Edit::Pop)
+			.add_undo_operation(Edit::Pop);
+		let mut history = UndoRedo::with_empty_storage(vec![action]);
+		let mut names = Vec::new();
+
+		assert_eq!(history.redo_collecting(&mut names), Ok(vec![1, 2]));
+		assert_eq!(names, ["a", "b"]);
+		assert_eq!(
+			history.redo_collecting(&mut names),
+			Err(UndoRedoError::NothingToDo)
+		);
+
+		assert_eq!(history.undo_collecting(&mut names), Ok(vec![1, 0]));
+		assert!(names.is_empty());
+		assert_eq!(history.position(), 0);
+		assert_eq!(
+			history.undo_collecting(&mut names),
+			Err(UndoRedoError::NothingToDo)
+		);
+	}
+}
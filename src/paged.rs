@@ -0,0 +1,508 @@
+use core::{
+	error, fmt,
+	marker::PhantomData,
+	ops::Range,
+	sync::atomic::{AtomicUsize, Ordering},
+};
+use std::{
+	env,
+	fs::{self, File},
+	io::{self, BufReader, Read, Seek, SeekFrom, Write},
+	path::{Path, PathBuf},
+	process,
+};
+
+use crate::{
+	Action, Operation, PersistError, PersistOperation, UndoRedo, UndoRedoError,
+	persist::{HEADER_LEN, Header, Input, SKIPPED_BIT, write_action},
+};
+
+/// A source of actions that have not yet been loaded into a [`PagedUndoRedo`].
+pub trait ActionLoader<Op> {
+	/// Loads the actions within `range`, in order from oldest to newest.
+	///
+	/// Indices are positions within the full history, as it was when it was saved.
+	///
+	/// # Errors
+	/// Returns an error if the actions could not be loaded.
+	fn load_range(&mut self, range: Range<usize>) -> Result<Vec<Action<Op>>, PersistError>;
+}
+
+/// An [`ActionLoader`] which actions can also be moved into, to free up the memory they use.
+pub trait ActionSpiller<Op>: ActionLoader<Op> {
+	/// Stores `actions`, which begin at index `start` within the full history, so that they can
+	/// later be loaded using [`ActionLoader::load_range`]. Any actions stored at index `start` or
+	/// later are replaced.
+	///
+	/// # Errors
+	/// Returns an error if the actions could not be stored.
+	fn spill(&mut self, start: usize, actions: &[Action<Op>]) -> Result<(), PersistError>;
+}
+
+/// An undo-redo history where only a window of actions around the current position is kept in
+/// memory, and older actions are pulled in from an [`ActionLoader`] as they're reached.
+#[derive(Debug)]
+pub struct PagedUndoRedo<Op, L> {
+	history: UndoRedo<Op>,
+	/// How many actions come before the first action in `self.history`, and have yet to be
+	/// loaded.
+	unloaded: usize,
+	/// The save point of the full history, if it lies among the unloaded actions.
+	unloaded_save_point: Option<usize>,
+	/// How many actions to load at once when reaching the beginning of `self.history`.
+	page_size: usize,
+	loader: L,
+}
+
+impl<Op, L: ActionLoader<Op>> PagedUndoRedo<Op, L> {
+	/// Creates a paged history from the loaded portion of a history, and a loader for the
+	/// `unloaded` actions that come before it.
+	///
+	/// Each time the beginning of `history` is reached, up to `page_size` more actions are loaded.
+	pub fn new(history: UndoRedo<Op>, unloaded: usize, page_size: usize, loader: L) -> Self {
+		Self {
+			history,
+			unloaded,
+			unloaded_save_point: None,
+			page_size: page_size.max(1),
+			loader,
+		}
+	}
+
+	/// Returns the portion of the history which is currently loaded.
+	pub fn loaded(&self) -> &UndoRedo<Op> {
+		&self.history
+	}
+
+	/// Returns the number of actions which have yet to be loaded.
+	pub fn unloaded_len(&self) -> usize {
+		self.unloaded
+	}
+
+	/// Loads up to one page of the actions that come before the loaded portion of the history,
+	/// returning how many were loaded.
+	///
+	/// # Errors
+	/// Returns an error if the loader fails to load the actions.
+	pub fn load_older(&mut self) -> Result<usize, PersistError> {
+		let start = self.unloaded.saturating_sub(self.page_size);
+		let mut older = self.loader.load_range(start..self.unloaded)?;
+		let loaded = older.len();
+
+		older.append(&mut self.history.actions);
+		self.history.actions = older;
+		self.history.tapehead += loaded;
+		self.history.save_point = self
+			.history
+			.save_point
+			.map(|save_point| save_point + loaded);
+		if let Some(save_point) = self
+			.unloaded_save_point
+			.filter(|&save_point| save_point >= start)
+		{
+			self.history.save_point = Some(save_point - start);
+			self.unloaded_save_point = None;
+		}
+		self.unloaded = start;
+		Ok(loaded)
+	}
+
+	/// Resets the undo-redo history to its default state, including any unloaded actions.
+	pub fn clear_history(&mut self) {
+		self.history.clear_history();
+		self.unloaded = 0;
+		self.unloaded_save_point = None;
+	}
+
+	/// See [`UndoRedo::create_action`].
+	///
+	/// # Panics
+	/// See [`UndoRedo::create_action`].
+	pub fn create_action(&mut self) -> &mut Action<Op> {
+		self.history.create_action()
+	}
+
+	/// See [`UndoRedo::redo`].
+	///
+	/// # Errors
+	/// See [`UndoRedo::redo`].
+	///
+	/// # Panics
+	/// See [`UndoRedo::redo`].
+	pub fn redo<For>(&mut self, apply_to: &mut For) -> Result<(), UndoRedoError>
+	where
+		Op: Operation<For>,
+	{
+		self.history.redo(apply_to)
+	}
+
+	/// See [`UndoRedo::undo`]. If the beginning of the loaded actions has been reached, another
+	/// page of actions is loaded first.
+	///
+	/// # Errors
+	/// * Returns `PagedError::UndoRedo` if there is nothing to revert.
+	/// * Returns `PagedError::Load` if more actions were needed, but could not be loaded.
+	pub fn undo<For>(&mut self, apply_to: &mut For) -> Result<(), PagedError>
+	where
+		Op: Operation<For>,
+	{
+		if self.history.tapehead == 0 && self.unloaded > 0 {
+			self.load_older()?;
+		}
+		Ok(self.history.undo(apply_to)?)
+	}
+}
+
+impl<Op, L: ActionSpiller<Op>> PagedUndoRedo<Op, L> {
+	/// Moves all but the newest `keep` applied actions out of memory and into the spiller,
+	/// returning how many were moved. They will be loaded again as they're reached by
+	/// [`Self::undo`].
+	///
+	/// Call this periodically (such as after creating an action) to keep the memory used by a
+	/// long history bounded.
+	///
+	/// # Errors
+	/// Returns an error if the spiller fails to store the actions. The actions are kept in memory
+	/// in this case.
+	pub fn spill_older(&mut self, keep: usize) -> Result<usize, PersistError> {
+		let count = self.history.tapehead.saturating_sub(keep);
+		if count == 0 {
+			return Ok(0);
+		}
+
+		self.loader
+			.spill(self.unloaded, &self.history.actions[..count])?;
+		self.history.actions.drain(..count);
+		self.history.tapehead -= count;
+		match self.history.save_point {
+			Some(save_point) if save_point < count => {
+				self.unloaded_save_point = Some(self.unloaded + save_point);
+				self.history.save_point = None;
+			}
+			Some(save_point) => self.history.save_point = Some(save_point - count),
+			None => {}
+		}
+		self.unloaded += count;
+		Ok(count)
+	}
+}
+
+impl<Op: PersistOperation> PagedUndoRedo<Op, SpillFile<Op>> {
+	/// Creates an empty paged history which spills actions into a new temporary file.
+	///
+	/// # Errors
+	/// Returns an error if the temporary file could not be created.
+	pub fn with_spill_file(page_size: usize) -> Result<Self, PersistError> {
+		Ok(Self::new(
+			UndoRedo::default(),
+			0,
+			page_size,
+			SpillFile::create_temp()?,
+		))
+	}
+}
+
+impl<Op: PersistOperation> UndoRedo<Op> {
+	/// Loads a history previously saved with [`Self::save_to_path`], keeping only up to
+	/// `page_size` applied actions in memory. Older actions are read from the file as they're
+	/// needed.
+	///
+	/// Unapplied actions are always loaded.
+	///
+	/// # Errors
+	/// See [`Self::load_from_path`].
+	pub fn load_paged_from_path(
+		path: impl AsRef<Path>,
+		page_size: usize,
+	) -> Result<PagedUndoRedo<Op, FileActionLoader<Op>>, PersistError> {
+		let (mut loader, header) = FileActionLoader::open(path)?;
+		let start = header.tapehead.saturating_sub(page_size);
+		let actions = loader.load_range(start..loader.len())?;
+
+		let history = UndoRedo {
+			actions,
+			_op: PhantomData,
+			tapehead: header.tapehead - start,
+			save_point: header
+				.save_point
+				.and_then(|save_point| save_point.checked_sub(start)),
+			#[cfg(feature = "async")]
+			partial: None,
+		};
+		let mut paged = PagedUndoRedo::new(history, start, page_size, loader);
+		paged.unloaded_save_point = header.save_point.filter(|&save_point| save_point < start);
+		Ok(paged)
+	}
+}
+
+/// An [`ActionLoader`] which reads actions from a file written by [`UndoRedo::save_to_path`].
+#[derive(Debug)]
+pub struct FileActionLoader<Op> {
+	file: BufReader<File>,
+	/// The byte offset of each action within the file, followed by the offset of the end of the
+	/// last action.
+	offsets: Vec<u64>,
+	_op: PhantomData<fn() -> Op>,
+}
+
+impl<Op: PersistOperation> FileActionLoader<Op> {
+	/// Opens the file at `path` and indexes where each action is, without decoding them. Returns
+	/// the loader along with the file's header.
+	fn open(path: impl AsRef<Path>) -> Result<(Self, Header), PersistError> {
+		let mut file = BufReader::new(File::open(path)?);
+		let mut header_bytes = [0; HEADER_LEN];
+		read_exact(&mut file, &mut header_bytes)?;
+		let header = Input(&header_bytes).header::<Op>()?;
+
+		let mut offsets = Vec::new();
+		for _ in 0..header.count {
+			offsets.push(file.stream_position()?);
+			let mut tag = [0];
+			read_exact(&mut file, &mut tag)?;
+			match tag[0] & !SKIPPED_BIT {
+				0 => {}
+				1 => skip_bytes(&mut file)?,
+				_ => return Err(PersistError::Corrupt),
+			}
+			for _ in 0..2 {
+				for _ in 0..read_len(&mut file)? {
+					skip_bytes(&mut file)?;
+				}
+			}
+		}
+		offsets.push(file.stream_position()?);
+
+		let loader = Self {
+			file,
+			offsets,
+			_op: PhantomData,
+		};
+		Ok((loader, header))
+	}
+
+	/// The number of actions in the file.
+	fn len(&self) -> usize {
+		self.offsets.len() - 1
+	}
+}
+
+impl<Op: PersistOperation> ActionLoader<Op> for FileActionLoader<Op> {
+	fn load_range(&mut self, range: Range<usize>) -> Result<Vec<Action<Op>>, PersistError> {
+		read_actions(&mut self.file, &self.offsets, range)
+	}
+}
+
+/// An [`ActionSpiller`] which stores actions in a temporary file. The file is deleted when this
+/// is dropped.
+#[derive(Debug)]
+pub struct SpillFile<Op> {
+	file: File,
+	path: PathBuf,
+	/// The byte offset of each stored action within the file, followed by the offset of the end
+	/// of the last stored action.
+	offsets: Vec<u64>,
+	_op: PhantomData<fn() -> Op>,
+}
+
+impl<Op> SpillFile<Op> {
+	/// Creates a new, empty file in the system's temporary directory to spill actions into.
+	///
+	/// # Errors
+	/// Returns an error if the file could not be created.
+	pub fn create_temp() -> Result<Self, PersistError> {
+		static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+		let name = format!(
+			"michis_undo_redo-{}-{}.spill",
+			process::id(),
+			COUNTER.fetch_add(1, Ordering::Relaxed)
+		);
+		let path = env::temp_dir().join(name);
+		let file = File::options()
+			.read(true)
+			.write(true)
+			.create_new(true)
+			.open(&path)?;
+
+		Ok(Self {
+			file,
+			path,
+			offsets: vec![0],
+			_op: PhantomData,
+		})
+	}
+}
+
+impl<Op: PersistOperation> ActionLoader<Op> for SpillFile<Op> {
+	fn load_range(&mut self, range: Range<usize>) -> Result<Vec<Action<Op>>, PersistError> {
+		read_actions(&mut self.file, &self.offsets, range)
+	}
+}
+
+impl<Op: PersistOperation> ActionSpiller<Op> for SpillFile<Op> {
+	fn spill(&mut self, start: usize, actions: &[Action<Op>]) -> Result<(), PersistError> {
+		let Some(&start_offset) = self.offsets.get(start) else {
+			// There would be a gap between the actions already stored and the new ones.
+			return Err(PersistError::Corrupt);
+		};
+
+		let mut bytes = Vec::new();
+		let mut offsets = Vec::new();
+		for action in actions {
+			write_action(&mut bytes, action);
+			offsets.push(start_offset + bytes.len() as u64);
+		}
+
+		self.file.set_len(start_offset)?;
+		self.file.seek(SeekFrom::Start(start_offset))?;
+		self.file.write_all(&bytes)?;
+
+		self.offsets.truncate(start + 1);
+		self.offsets.extend(offsets);
+		Ok(())
+	}
+}
+
+impl<Op> Drop for SpillFile<Op> {
+	fn drop(&mut self) {
+		// There's nothing useful to do if this fails; at worst, the file is left behind.
+		let _ = fs::remove_file(&self.path);
+	}
+}
+
+/// Reads the actions within `range` from `file`, given the offsets of each action within it.
+fn read_actions<Op: PersistOperation>(
+	file: &mut (impl Read + Seek),
+	offsets: &[u64],
+	range: Range<usize>,
+) -> Result<Vec<Action<Op>>, PersistError> {
+	if range.is_empty() {
+		return Ok(Vec::new());
+	}
+	let (Some(&start), Some(&end)) = (offsets.get(range.start), offsets.get(range.end)) else {
+		return Err(PersistError::Corrupt);
+	};
+
+	let len = usize::try_from(end - start).map_err(|_| PersistError::Corrupt)?;
+	let mut bytes = vec![0; len];
+	file.seek(SeekFrom::Start(start))?;
+	read_exact(file, &mut bytes)?;
+
+	let mut input = Input(&bytes);
+	range.map(|_| input.action()).collect()
+}
+
+/// Like [`Read::read_exact`], but treats reaching the end of the file as corruption.
+fn read_exact(file: &mut impl Read, buf: &mut [u8]) -> Result<(), PersistError> {
+	file.read_exact(buf).map_err(|e| match e.kind() {
+		io::ErrorKind::UnexpectedEof => PersistError::Corrupt,
+		_ => PersistError::Io(e),
+	})
+}
+
+fn read_len(file: &mut impl Read) -> Result<u64, PersistError> {
+	let mut bytes = [0; 8];
+	read_exact(file, &mut bytes)?;
+	Ok(u64::from_le_bytes(bytes))
+}
+
+fn skip_bytes(file: &mut BufReader<File>) -> Result<(), PersistError> {
+	let len = read_len(file)?;
+	let len = i64::try_from(len).map_err(|_| PersistError::Corrupt)?;
+	file.seek_relative(len)?;
+	Ok(())
+}
+
+/// An error indicating an issue with performing an undo on a [`PagedUndoRedo`].
+#[derive(Debug)]
+pub enum PagedError {
+	UndoRedo(UndoRedoError),
+	Load(PersistError),
+}
+
+impl From<UndoRedoError> for PagedError {
+	fn from(value: UndoRedoError) -> Self {
+		Self::UndoRedo(value)
+	}
+}
+
+impl From<PersistError> for PagedError {
+	fn from(value: PersistError) -> Self {
+		Self::Load(value)
+	}
+}
+
+impl fmt::Display for PagedError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::UndoRedo(e) => write!(f, "{e}"),
+			Self::Load(e) => write!(f, "failed to load older actions: {e}"),
+		}
+	}
+}
+
+impl error::Error for PagedError {
+	fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+		match self {
+			Self::UndoRedo(e) => Some(e),
+			Self::Load(e) => Some(e),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[derive(Clone, Debug, PartialEq)]
+	struct Add(i32);
+
+	impl Operation<i32> for Add {
+		fn apply(&self, item: &mut i32) {
+			*item += self.0;
+		}
+	}
+
+	impl PersistOperation for Add {
+		const SCHEMA_VERSION: u32 = 1;
+
+		fn encode(&self, out: &mut Vec<u8>) {
+			out.extend(self.0.to_le_bytes());
+		}
+
+		fn decode(bytes: &[u8]) -> Option<Self> {
+			Some(Self(i32::from_le_bytes(bytes.try_into().ok()?)))
+		}
+	}
+
+	#[test]
+	fn loads_named_skipped_actions_in_pages() {
+		let mut history = UndoRedo::<Add>::new();
+		let mut target = 0;
+		for value in 1..=5 {
+			let mut action = Action::default();
+			action.add_redo_operation(Add(value));
+			action.add_undo_operation(Add(-value));
+			if value % 2 == 0 {
+				action.set_name(value);
+			}
+			history.commit_action(action, &mut target);
+		}
+		history.set_skipped(1, true, &mut target).unwrap();
+		history.set_skipped(2, true, &mut target).unwrap();
+
+		let path = env::temp_dir().join(format!(
+			"michis_undo_redo-{}-paged-test.history",
+			process::id()
+		));
+		history.save_to_path(&path).unwrap();
+		let mut paged = UndoRedo::<Add>::load_paged_from_path(&path, 2).unwrap();
+		fs::remove_file(&path).unwrap();
+
+		assert_eq!(paged.unloaded_len(), 3);
+		while paged.unloaded_len() > 0 {
+			paged.load_older().unwrap();
+		}
+		assert_eq!(*paged.loaded(), history);
+	}
+}
@@ -0,0 +1,174 @@
+use alloc::sync::Arc;
+use core::ops::Deref;
+
+use crate::{Action, HistoryStorage, UndoRedo};
+
+/// Data held by an operation, which is either shared with something else (such as the buffer an
+/// editor is working on) or owned by the operation alone.
+///
+/// Sharing the data lets large operations be created without cloning it. Once the action holding
+/// an operation is no longer the newest one, the data can be [promoted](Self::promote) to owned
+/// data, so that the rest of the program can change the shared data without cloning it. See
+/// [`PromoteOperation`] and [`UndoRedo::create_action_promoting`].
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OwnedOrBorrowed<T> {
+	/// The data is held in an `Arc`, which may be shared with something else.
+	Shared(Arc<T>),
+	/// The data is owned by this value alone.
+	Owned(T),
+}
+
+impl<T> OwnedOrBorrowed<T> {
+	/// Returns `true` if the data is shared with anything else.
+	pub fn is_shared(&self) -> bool {
+		match self {
+			Self::Shared(shared) => Arc::strong_count(shared) > 1,
+			Self::Owned(_) => false,
+		}
+	}
+
+	/// Makes sure the data is not shared with anything else, cloning it if it is.
+	pub fn promote(&mut self)
+	where
+		T: Clone,
+	{
+		self.to_mut();
+	}
+
+	/// Returns the data mutably, [promoting](Self::promote) it first. This is useful for merging
+	/// further changes into an existing operation.
+	pub fn to_mut(&mut self) -> &mut T
+	where
+		T: Clone,
+	{
+		match self {
+			Self::Shared(shared) => Arc::make_mut(shared),
+			Self::Owned(owned) => owned,
+		}
+	}
+
+	/// Returns the data as an owned value, cloning it if it is still shared.
+	pub fn into_owned(self) -> T
+	where
+		T: Clone,
+	{
+		match self {
+			Self::Shared(shared) => Arc::unwrap_or_clone(shared),
+			Self::Owned(owned) => owned,
+		}
+	}
+}
+
+impl<T> Deref for OwnedOrBorrowed<T> {
+	type Target = T;
+
+	fn deref(&self) -> &T {
+		match self {
+			Self::Shared(shared) => shared,
+			Self::Owned(owned) => owned,
+		}
+	}
+}
+
+impl<T> From<Arc<T>> for OwnedOrBorrowed<T> {
+	fn from(value: Arc<T>) -> Self {
+		Self::Shared(value)
+	}
+}
+
+/// An operation which may hold [`OwnedOrBorrowed`] data, and can promote it to owned data.
+pub trait PromoteOperation {
+	/// Makes any data this operation shares with something else owned by it alone.
+	fn promote(&mut self);
+}
+
+impl<Op: PromoteOperation> Action<Op> {
+	/// [Promotes](PromoteOperation::promote) the data of every operation in this action, and
+	/// [seals](Self::seal) it.
+	pub fn seal_promoting(&mut self) {
+		self.apply_ops
+			.as_mut_slice()
+			.iter_mut()
+			.for_each(PromoteOperation::promote);
+		self.revert_ops
+			.as_mut_slice()
+			.iter_mut()
+			.for_each(PromoteOperation::promote);
+		self.seal();
+	}
+}
+
+impl<Op: PromoteOperation, S: HistoryStorage<Op>> UndoRedo<Op, S> {
+	/// Like [`Self::create_action`], but also [promotes](PromoteOperation::promote) the data of the
+	/// action that is sealed by creating the new one. Use this in place of
+	/// [`Self::create_action`], so that only the newest action shares data with anything else.
+	///
+	/// An action shared with another history (see [`HistoryStorage::get_mut`]) is left as it is.
+	///
+	/// # Panics
+	/// See [`Self::create_action`].
+	pub fn create_action_promoting(&mut self) -> &mut Action<Op> {
+		if let Some(newest) = self
+			.tapehead
+			.checked_sub(1)
+			.and_then(|index| self.actions.get_mut(index))
+		{
+			newest.seal_promoting();
+		}
+		self.create_action()
+	}
+
+	/// [Promotes and seals](Action::seal_promoting) every action except the newest, which may
+	/// still be added to. This is meant for bringing an existing history up to date, such as one
+	/// built with [`Self::create_action`]; to keep a history promoted as it grows, use
+	/// [`Self::create_action_promoting`] instead, which only promotes one action each time.
+	///
+	/// Actions shared with another history (see [`HistoryStorage::get_mut`]) are left as they are.
+	pub fn promote_history(&mut self) {
+		let newest = self.actions.len().saturating_sub(1);
+		for index in 0..newest {
+			if let Some(action) = self.actions.get_mut(index) {
+				action.seal_promoting();
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use alloc::string::String;
+
+	use super::*;
+	use crate::Operation;
+
+	#[derive(Clone, Debug, PartialEq)]
+	struct Replace(OwnedOrBorrowed<String>);
+
+	impl Operation<String> for Replace {
+		fn apply(&self, item: &mut String) {
+			item.clone_from(&self.0);
+		}
+	}
+
+	impl PromoteOperation for Replace {
+		fn promote(&mut self) {
+			self.0.promote();
+		}
+	}
+
+	#[test]
+	fn creating_action_promotes_sealed_action() {
+		let buffer = Arc::new(String::from("large buffer"));
+		let mut history = UndoRedo::new();
+		history
+			.create_action_promoting()
+			.add_redo_operation(Replace(Arc::clone(&buffer).into()));
+		history.redo(&mut String::new()).unwrap();
+		assert_eq!(Arc::strong_count(&buffer), 2);
+
+		history.create_action_promoting();
+		assert_eq!(Arc::strong_count(&buffer), 1);
+		let promoted = history.undo_action().unwrap();
+		assert!(promoted.apply_ops().iter().all(|op| !op.0.is_shared()));
+	}
+}
@@ -0,0 +1,843 @@
+use core::{error, fmt};
+use std::{
+	fs::{self, File},
+	io::{self, BufReader, BufWriter, Read, Write},
+	path::Path,
+};
+
+use crate::{Action, HistoryStorage, UndoRedo};
+
+/// Bytes written at the start of every persisted history, used to recognize our own files.
+const MAGIC: [u8; 4] = *b"MURH";
+/// The version of the container format written by this crate. This is separate from
+/// [`PersistOperation::SCHEMA_VERSION`], which covers the encoding of the user's operations.
+const FORMAT_VERSION: u32 = 1;
+
+/// An [`Operation`](crate::Operation) that can be encoded to and decoded from bytes, allowing a
+/// history made of it to be saved and loaded.
+pub trait PersistOperation: Sized {
+	/// The version of the encoding produced by [`Self::encode`].
+	///
+	/// This should be changed whenever the encoding changes in a way that older data can no longer
+	/// be decoded. Loading a history saved with a different version will fail with
+	/// [`PersistError::SchemaMismatch`].
+	const SCHEMA_VERSION: u32;
+
+	/// Appends an encoded form of this operation to `out`.
+	fn encode(&self, out: &mut Vec<u8>);
+
+	/// Decodes an operation previously encoded by [`Self::encode`].
+	///
+	/// Returns `None` if `bytes` is not a valid encoding.
+	fn decode(bytes: &[u8]) -> Option<Self>;
+}
+
+/// Which actions from a history should be saved.
+///
+/// Whatever the scope, the saved history is self-contained: loading it gives a history of just the
+/// saved actions, with the position and save point moved to match. The actions left out are not
+/// recorded, so the loaded history can only be undone as far as the first saved action, and
+/// expects its object to be in the state it was in before that action.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub enum PersistScope {
+	/// Every action.
+	#[default]
+	All,
+	/// Only the actions that are currently applied.
+	Applied,
+	/// Only the most recent actions, up to the given number of them.
+	LastN(usize),
+	/// Only the actions after the point marked by [`UndoRedo::mark_save_point`]. If there is no
+	/// save point, every action is saved.
+	///
+	/// This is a snapshot of those actions, not a delta. Loading it does not merge them into a
+	/// history saved earlier, so save with [`Self::All`] as well if the earlier actions are needed.
+	SinceSavePoint,
+}
+
+impl<Op: PersistOperation, S: HistoryStorage<Op> + Default> UndoRedo<Op, S> {
+	/// Saves the entire history, including the current position within it, to the file at `path`.
+	///
+	/// If the file already exists, it is replaced. The history is written to a temporary file
+	/// next to it first, which is then renamed into place, so that `path` never holds a partially
+	/// written history.
+	///
+	/// # Errors
+	/// Returns `PersistError::Io` if the file could not be created or written to.
+	pub fn save_to_path(&self, path: impl AsRef<Path>) -> Result<(), PersistError> {
+		self.save_to_path_with_scope(path, PersistScope::All)
+	}
+
+	/// Loads a history previously saved with [`Self::save_to_path`].
+	///
+	/// # Errors
+	/// * Returns `PersistError::Io` if the file could not be opened or read from.
+	/// * Returns `PersistError::Corrupt` if the file is not a valid saved history.
+	/// * Returns `PersistError::UnsupportedFormat` if the file was written by an incompatible
+	///   version of this crate.
+	/// * Returns `PersistError::SchemaMismatch` if the file's operations were encoded with a
+	///   different [`PersistOperation::SCHEMA_VERSION`].
+	pub fn load_from_path(path: impl AsRef<Path>) -> Result<Self, PersistError> {
+		Self::load_from_reader(BufReader::new(File::open(path)?))
+	}
+
+	/// Saves the actions selected by `scope` to the file at `path`, as [`Self::save_to_path`]
+	/// does.
+	///
+	/// When loaded, the saved actions form a history of their own, with the current position
+	/// clamped to within them.
+	///
+	/// # Errors
+	/// Returns `PersistError::Io` if the file could not be created or written to.
+	pub fn save_to_path_with_scope(
+		&self,
+		path: impl AsRef<Path>,
+		scope: PersistScope,
+	) -> Result<(), PersistError> {
+		write_atomically(path.as_ref(), |writer| {
+			self.save_to_writer_with_scope(writer, scope)
+		})
+	}
+
+	/// Writes the entire history, including the current position within it, to `writer`.
+	///
+	/// # Errors
+	/// Returns `PersistError::Io` if writing fails.
+	pub fn save_to_writer(&self, writer: impl Write) -> Result<(), PersistError> {
+		self.save_to_writer_with_scope(writer, PersistScope::All)
+	}
+
+	/// Writes the actions selected by `scope` to `writer`.
+	///
+	/// # Errors
+	/// Returns `PersistError::Io` if writing fails.
+	pub fn save_to_writer_with_scope(
+		&self,
+		mut writer: impl Write,
+		scope: PersistScope,
+	) -> Result<(), PersistError> {
+		writer.write_all(&self.encode_history(scope))?;
+		Ok(())
+	}
+
+	/// Reads a history previously written with [`Self::save_to_writer`].
+	///
+	/// # Errors
+	/// See [`Self::load_from_path`].
+	pub fn load_from_reader(mut reader: impl Read) -> Result<Self, PersistError> {
+		let mut bytes = Vec::new();
+		reader.read_to_end(&mut bytes)?;
+		Self::decode_history(&bytes)
+	}
+
+	pub(crate) fn encode_history(&self, scope: PersistScope) -> Vec<u8> {
+		let len = self.actions.len();
+		let start = match scope {
+			PersistScope::All => 0,
+			PersistScope::Applied => 0,
+			PersistScope::LastN(count) => len.saturating_sub(count),
+			PersistScope::SinceSavePoint => self.save_point.unwrap_or(0),
+		};
+		let end = match scope {
+			PersistScope::Applied => self.tapehead,
+			_ => len,
+		};
+		let actions = self.iter_actions().skip(start).take(end - start);
+		let tapehead = self.tapehead.clamp(start, end) - start;
+		// Stored as one more than the save point, with 0 meaning there is no save point.
+		let save_point = self
+			.save_point
+			.filter(|save_point| (start..=end).contains(save_point))
+			.map_or(0, |save_point| save_point - start + 1);
+
+		let mut out = Vec::new();
+		write_preamble::<Op>(&mut out, MAGIC);
+		write_len(&mut out, tapehead);
+		write_len(&mut out, save_point);
+		write_len(&mut out, end - start);
+
+		for action in actions {
+			write_action(&mut out, action);
+		}
+
+		out
+	}
+
+	pub(crate) fn decode_history(bytes: &[u8]) -> Result<Self, PersistError> {
+		let mut input = Input(bytes);
+		let Header {
+			tapehead,
+			save_point,
+			count,
+		} = input.header::<Op>()?;
+
+		let mut history = Self {
+			tapehead,
+			save_point,
+			..Self::default()
+		};
+		let mut evicted = 0;
+		for _ in 0..count {
+			evicted += history.actions.push(input.action()?);
+		}
+		history.forget_evicted(evicted);
+
+		if !input.0.is_empty() {
+			return Err(PersistError::Corrupt);
+		}
+
+		Ok(history)
+	}
+}
+
+/// Creates (or replaces) the file at `path` with what `write` writes. It is written to a temporary
+/// file in the same directory first, which is then renamed into place, so that `path` is never
+/// left partially written. If anything fails, the temporary file is removed.
+pub(crate) fn write_atomically(
+	path: &Path,
+	write: impl FnOnce(&mut BufWriter<File>) -> Result<(), PersistError>,
+) -> Result<(), PersistError> {
+	let mut temp_name = path
+		.file_name()
+		.ok_or_else(|| io::Error::from(io::ErrorKind::InvalidInput))?
+		.to_os_string();
+	temp_name.push(".tmp");
+	let temp_path = path.with_file_name(temp_name);
+
+	let result = File::create(&temp_path)
+		.map_err(PersistError::from)
+		.and_then(|file| {
+			let mut writer = BufWriter::new(file);
+			write(&mut writer)?;
+			let file = writer
+				.into_inner()
+				.map_err(io::IntoInnerError::into_error)?;
+			file.sync_all()?;
+			fs::rename(&temp_path, path)?;
+			Ok(())
+		});
+	if result.is_err() {
+		// The temporary file may not exist, and either way, the original error is more useful.
+		let _ = fs::remove_file(&temp_path);
+	}
+	result
+}
+
+/// Writes the magic bytes and versions which begin every persisted history.
+pub(crate) fn write_preamble<Op: PersistOperation>(out: &mut Vec<u8>, magic: [u8; 4]) {
+	out.extend_from_slice(&magic);
+	out.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+	out.extend_from_slice(&Op::SCHEMA_VERSION.to_le_bytes());
+}
+
+pub(crate) fn write_action<Op: PersistOperation>(out: &mut Vec<u8>, action: &Action<Op>) {
+	// Whether the action is skipped is stored in the second bit of the name's tag, so that
+	// histories saved before skipping existed can still be read.
+	let skipped_bit = if action.skipped { SKIPPED_BIT } else { 0 };
+	match action.name.as_deref() {
+		Some(name) => {
+			out.push(1 | skipped_bit);
+			write_bytes(out, name.as_bytes());
+		}
+		None => out.push(skipped_bit),
+	}
+	write_ops(out, action.apply_ops.as_slice());
+	write_ops(out, action.revert_ops.as_slice());
+}
+
+/// Set in the tag of an action's name if the action is skipped.
+pub(crate) const SKIPPED_BIT: u8 = 0b10;
+
+pub(crate) fn write_name(out: &mut Vec<u8>, name: Option<&str>) {
+	match name {
+		Some(name) => {
+			out.push(1);
+			write_bytes(out, name.as_bytes());
+		}
+		None => out.push(0),
+	}
+}
+
+pub(crate) fn write_u64(out: &mut Vec<u8>, value: u64) {
+	out.extend_from_slice(&value.to_le_bytes());
+}
+
+pub(crate) fn write_len(out: &mut Vec<u8>, len: usize) {
+	// `usize` is at most 64 bits on every platform Rust supports.
+	write_u64(out, len as u64);
+}
+
+pub(crate) fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+	write_len(out, bytes.len());
+	out.extend_from_slice(bytes);
+}
+
+pub(crate) fn write_ops<Op: PersistOperation>(out: &mut Vec<u8>, ops: &[Op]) {
+	write_len(out, ops.len());
+	let mut buf = Vec::new();
+	for op in ops {
+		buf.clear();
+		op.encode(&mut buf);
+		write_bytes(out, &buf);
+	}
+}
+
+/// The length in bytes of the header produced by [`UndoRedo::encode_history`].
+pub(crate) const HEADER_LEN: usize = MAGIC.len() + 4 + 4 + 8 + 8 + 8;
+
+/// The parts of a persisted history's header that describe the history.
+pub(crate) struct Header {
+	pub(crate) tapehead: usize,
+	pub(crate) save_point: Option<usize>,
+	/// The number of actions that follow the header.
+	pub(crate) count: usize,
+}
+
+/// The not-yet-read remainder of a persisted history.
+pub(crate) struct Input<'a>(pub(crate) &'a [u8]);
+
+impl<'a> Input<'a> {
+	/// Reads and validates the header of a persisted history.
+	pub(crate) fn header<Op: PersistOperation>(&mut self) -> Result<Header, PersistError> {
+		self.preamble::<Op>(MAGIC)?;
+
+		let tapehead = self.len()?;
+		let save_point = self.len()?.checked_sub(1);
+		let count = self.len()?;
+		if tapehead > count || save_point.is_some_and(|save_point| save_point > count) {
+			return Err(PersistError::Corrupt);
+		}
+		Ok(Header {
+			tapehead,
+			save_point,
+			count,
+		})
+	}
+
+	/// Reads and validates the magic bytes and versions written by [`write_preamble`].
+	pub(crate) fn preamble<Op: PersistOperation>(
+		&mut self,
+		magic: [u8; 4],
+	) -> Result<(), PersistError> {
+		if self.take(magic.len())? != magic {
+			return Err(PersistError::Corrupt);
+		}
+		let format = self.u32()?;
+		if format != FORMAT_VERSION {
+			return Err(PersistError::UnsupportedFormat(format));
+		}
+		let schema = self.u32()?;
+		if schema != Op::SCHEMA_VERSION {
+			return Err(PersistError::SchemaMismatch {
+				expected: Op::SCHEMA_VERSION,
+				found: schema,
+			});
+		}
+		Ok(())
+	}
+
+	pub(crate) fn action<Op: PersistOperation>(&mut self) -> Result<Action<Op>, PersistError> {
+		let tag = self.take(1)?[0];
+		let name = self.tagged_name(tag & !SKIPPED_BIT)?;
+		let apply_ops = self.ops()?;
+		let revert_ops = self.ops()?;
+		Ok(Action {
+			name,
+			apply_ops: apply_ops.into(),
+			revert_ops: revert_ops.into(),
+			skipped: tag & SKIPPED_BIT != 0,
+		})
+	}
+
+	/// Reads a name written by [`write_name`].
+	pub(crate) fn name(&mut self) -> Result<Option<String>, PersistError> {
+		let tag = self.take(1)?[0];
+		self.tagged_name(tag)
+	}
+
+	/// Reads the rest of a name, after its tag.
+	fn tagged_name(&mut self, tag: u8) -> Result<Option<String>, PersistError> {
+		match tag {
+			0 => Ok(None),
+			1 => {
+				let bytes = self.bytes()?;
+				let name = core::str::from_utf8(bytes).map_err(|_| PersistError::Corrupt)?;
+				Ok(Some(name.to_owned()))
+			}
+			_ => Err(PersistError::Corrupt),
+		}
+	}
+
+	pub(crate) fn take(&mut self, len: usize) -> Result<&'a [u8], PersistError> {
+		if len > self.0.len() {
+			return Err(PersistError::Corrupt);
+		}
+		let (taken, rest) = self.0.split_at(len);
+		self.0 = rest;
+		Ok(taken)
+	}
+
+	pub(crate) fn u32(&mut self) -> Result<u32, PersistError> {
+		let bytes = self
+			.take(4)?
+			.try_into()
+			.map_err(|_| PersistError::Corrupt)?;
+		Ok(u32::from_le_bytes(bytes))
+	}
+
+	pub(crate) fn u64(&mut self) -> Result<u64, PersistError> {
+		let bytes = self
+			.take(8)?
+			.try_into()
+			.map_err(|_| PersistError::Corrupt)?;
+		Ok(u64::from_le_bytes(bytes))
+	}
+
+	pub(crate) fn len(&mut self) -> Result<usize, PersistError> {
+		usize::try_from(self.u64()?).map_err(|_| PersistError::Corrupt)
+	}
+
+	pub(crate) fn bytes(&mut self) -> Result<&'a [u8], PersistError> {
+		let len = self.len()?;
+		self.take(len)
+	}
+
+	pub(crate) fn ops<Op: PersistOperation>(&mut self) -> Result<Vec<Op>, PersistError> {
+		let count = self.len()?;
+		let mut ops = Vec::new();
+		for _ in 0..count {
+			let op = Op::decode(self.bytes()?).ok_or(PersistError::Corrupt)?;
+			ops.push(op);
+		}
+		Ok(ops)
+	}
+}
+
+/// An error indicating an issue with saving or loading a history.
+#[derive(Debug)]
+pub enum PersistError {
+	/// An I/O error occurred while reading or writing.
+	Io(io::Error),
+	/// The data is not a valid saved history.
+	Corrupt,
+	/// The data was saved by a version of this crate using an incompatible format.
+	UnsupportedFormat(u32),
+	/// The data's operations were encoded with a different [`PersistOperation::SCHEMA_VERSION`]
+	/// than the one expected.
+	SchemaMismatch { expected: u32, found: u32 },
+}
+
+impl From<io::Error> for PersistError {
+	fn from(value: io::Error) -> Self {
+		Self::Io(value)
+	}
+}
+
+impl fmt::Display for PersistError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Io(e) => write!(f, "I/O error: {e}"),
+			Self::Corrupt => write!(f, "saved history is corrupt"),
+			Self::UnsupportedFormat(found) => {
+				write!(f, "saved history uses unsupported format version {found}")
+			}
+			Self::SchemaMismatch { expected, found } => write!(
+				f,
+				"saved history uses operation schema version {found}, expected {expected}"
+			),
+		}
+	}
+}
+
+impl error::Error for PersistError {
+	fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+		match self {
+			Self::Io(e) => Some(e),
+			_ => None,
+		}
+	}
+}
+
+/// A compression scheme that saved histories can be run through.
+///
+/// This crate provides [`Lz77`], a small scheme that needs no dependencies. For better
+/// compression, implement this for a wrapper around whichever compression library your application
+/// already uses (such as `zstd` or `flate2`).
+#[cfg(feature = "compression")]
+pub trait Compression {
+	/// Compresses `data`.
+	///
+	/// # Errors
+	/// Returns an error if `data` could not be compressed.
+	fn compress(&self, data: &[u8]) -> io::Result<Vec<u8>>;
+
+	/// Decompresses `data`, which was previously produced by [`Self::compress`].
+	///
+	/// # Errors
+	/// Returns an error if `data` could not be decompressed.
+	fn decompress(&self, data: &[u8]) -> io::Result<Vec<u8>>;
+}
+
+/// A built-in [`Compression`] scheme based on LZ77, which replaces repeated runs of bytes with
+/// references to their earlier occurrence.
+///
+/// Saved histories repeat themselves a lot, as similar operations encode similarly, so this
+/// usually shrinks them well. It does no entropy coding, so `zstd` or `deflate` will do better.
+#[cfg(feature = "compression")]
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub struct Lz77;
+
+#[cfg(feature = "compression")]
+impl Lz77 {
+	/// The shortest run of bytes worth replacing with a reference.
+	const MIN_MATCH: usize = 4;
+	/// The longest run of bytes a single reference can cover.
+	const MAX_MATCH: usize = Self::MIN_MATCH + 0x7F;
+	/// The most bytes a single literal run can hold.
+	const MAX_LITERALS: usize = 0x80;
+	/// The furthest back a reference can point.
+	const MAX_OFFSET: usize = u16::MAX as usize;
+	/// The number of bits used to index the table of recently seen sequences.
+	const HASH_BITS: u32 = 14;
+
+	/// Returns where in the table of recently seen sequences to look up `bytes`.
+	fn hash(bytes: [u8; 4]) -> usize {
+		(u32::from_le_bytes(bytes).wrapping_mul(0x9E37_79B1) >> (32 - Self::HASH_BITS)) as usize
+	}
+
+	/// Writes `literals` to `out` as runs of at most [`Self::MAX_LITERALS`] bytes, each preceded
+	/// by its length minus one.
+	fn write_literals(out: &mut Vec<u8>, literals: &[u8]) {
+		for run in literals.chunks(Self::MAX_LITERALS) {
+			// Truncation can't happen, as runs are at most `MAX_LITERALS` long.
+			out.push((run.len() - 1) as u8);
+			out.extend_from_slice(run);
+		}
+	}
+}
+
+// The compressed format is the length of the original data (as a little-endian `u64`), followed by
+// a series of tokens, each starting with a control byte:
+// * `0b0nnn_nnnn`: `n + 1` bytes follow, to be copied as they are.
+// * `0b1nnn_nnnn`: a little-endian `u16` offset follows. `n + MIN_MATCH` bytes are copied from
+//   that far back in the output, one at a time, so the copy may overlap the bytes it produces.
+#[cfg(feature = "compression")]
+impl Compression for Lz77 {
+	fn compress(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+		let mut out = Vec::with_capacity(data.len() / 2 + 8);
+		out.extend_from_slice(&(data.len() as u64).to_le_bytes());
+
+		let mut recent = vec![usize::MAX; 1 << Self::HASH_BITS];
+		let (mut position, mut literals_start) = (0, 0);
+		while let Some(&sequence) = data
+			.get(position..)
+			.and_then(<[u8]>::first_chunk::<{ Self::MIN_MATCH }>)
+		{
+			let candidate = core::mem::replace(&mut recent[Self::hash(sequence)], position);
+			let matched = match data.get(candidate..) {
+				Some(earlier) if position - candidate <= Self::MAX_OFFSET => earlier
+					.iter()
+					.zip(&data[position..])
+					.take(Self::MAX_MATCH)
+					.take_while(|(a, b)| a == b)
+					.count(),
+				_ => 0,
+			};
+			if matched < Self::MIN_MATCH {
+				position += 1;
+				continue;
+			}
+
+			Self::write_literals(&mut out, &data[literals_start..position]);
+			// Truncation can't happen, as matches and offsets are limited to fit.
+			out.push(0x80 | (matched - Self::MIN_MATCH) as u8);
+			out.extend_from_slice(&((position - candidate) as u16).to_le_bytes());
+			position += matched;
+			literals_start = position;
+		}
+		Self::write_literals(&mut out, &data[literals_start..]);
+		Ok(out)
+	}
+
+	fn decompress(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+		let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid LZ77 data");
+		let (len, mut rest) = data.split_first_chunk().ok_or_else(invalid)?;
+		let len = usize::try_from(u64::from_le_bytes(*len)).map_err(|_| invalid())?;
+
+		// The length is only trusted as far as the compressed data could produce it.
+		let mut out = Vec::with_capacity(len.min(rest.len().saturating_mul(Self::MAX_MATCH)));
+		while let Some((&control, tail)) = rest.split_first() {
+			if control & 0x80 == 0 {
+				let count = usize::from(control) + 1;
+				let literals = tail.get(..count).ok_or_else(invalid)?;
+				out.extend_from_slice(literals);
+				rest = &tail[count..];
+			} else {
+				let (offset, tail) = tail.split_first_chunk().ok_or_else(invalid)?;
+				let offset = usize::from(u16::from_le_bytes(*offset));
+				let start = out
+					.len()
+					.checked_sub(offset)
+					.filter(|_| offset > 0)
+					.ok_or_else(invalid)?;
+				let count = usize::from(control & 0x7F) + Self::MIN_MATCH;
+				for index in start..start + count {
+					out.push(out[index]);
+				}
+				rest = tail;
+			}
+			if out.len() > len {
+				return Err(invalid());
+			}
+		}
+		if out.len() != len {
+			return Err(invalid());
+		}
+		Ok(out)
+	}
+}
+
+#[cfg(feature = "compression")]
+impl<Op: PersistOperation, S: HistoryStorage<Op> + Default> UndoRedo<Op, S> {
+	/// Like [`Self::save_to_path_with_scope`], but runs the saved history through `compression`
+	/// first.
+	///
+	/// # Errors
+	/// Returns `PersistError::Io` if compression fails, or if the file could not be created or
+	/// written to.
+	pub fn save_to_path_compressed(
+		&self,
+		path: impl AsRef<Path>,
+		scope: PersistScope,
+		compression: &impl Compression,
+	) -> Result<(), PersistError> {
+		write_atomically(path.as_ref(), |writer| {
+			self.save_to_writer_compressed(writer, scope, compression)
+		})
+	}
+
+	/// Loads a history previously saved with [`Self::save_to_path_compressed`].
+	///
+	/// `compression` must be the same scheme the history was saved with.
+	///
+	/// # Errors
+	/// Returns `PersistError::Io` if decompression fails, and otherwise the same errors as
+	/// [`Self::load_from_path`].
+	pub fn load_from_path_compressed(
+		path: impl AsRef<Path>,
+		compression: &impl Compression,
+	) -> Result<Self, PersistError> {
+		Self::load_from_reader_compressed(BufReader::new(File::open(path)?), compression)
+	}
+
+	/// Like [`Self::save_to_writer_with_scope`], but runs the saved history through `compression`
+	/// first.
+	///
+	/// # Errors
+	/// Returns `PersistError::Io` if compression or writing fails.
+	pub fn save_to_writer_compressed(
+		&self,
+		mut writer: impl Write,
+		scope: PersistScope,
+		compression: &impl Compression,
+	) -> Result<(), PersistError> {
+		let encoded = self.encode_history(scope);
+		writer.write_all(&compression.compress(&encoded)?)?;
+		Ok(())
+	}
+
+	/// Reads a history previously written with [`Self::save_to_writer_compressed`].
+	///
+	/// # Errors
+	/// See [`Self::load_from_path_compressed`].
+	pub fn load_from_reader_compressed(
+		mut reader: impl Read,
+		compression: &impl Compression,
+	) -> Result<Self, PersistError> {
+		let mut bytes = Vec::new();
+		reader.read_to_end(&mut bytes)?;
+		Self::decode_history(&compression.decompress(&bytes)?)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{env, process};
+
+	use super::*;
+	use crate::Operation;
+
+	#[derive(Clone, Debug, PartialEq)]
+	struct Add(i32);
+
+	impl Operation<i32> for Add {
+		fn apply(&self, item: &mut i32) {
+			*item += self.0;
+		}
+	}
+
+	impl PersistOperation for Add {
+		const SCHEMA_VERSION: u32 = 1;
+
+		fn encode(&self, out: &mut Vec<u8>) {
+			out.extend_from_slice(&self.0.to_le_bytes());
+		}
+
+		fn decode(bytes: &[u8]) -> Option<Self> {
+			Some(Self(i32::from_le_bytes(bytes.try_into().ok()?)))
+		}
+	}
+
+	fn temp_path(name: &str) -> std::path::PathBuf {
+		env::temp_dir().join(format!("michis_undo_redo-{}-{name}.history", process::id()))
+	}
+
+	fn history() -> UndoRedo<Add> {
+		let mut history = UndoRedo::default();
+		let mut target = 0;
+		for value in 1..=3 {
+			history
+				.create_action()
+				.add_redo_operation(Add(value))
+				.add_undo_operation(Add(-value));
+			history.redo(&mut target).unwrap();
+		}
+		history.undo(&mut target).unwrap();
+		history
+	}
+
+	#[test]
+	fn round_trips_named_skipped_actions() {
+		let mut history = history();
+		let mut target = 1 + 2;
+		history.actions[0].set_name("first");
+		history.actions[2].set_name("ünïcödé");
+		history.set_skipped(1, true, &mut target).unwrap();
+		history.set_skipped(2, true, &mut target).unwrap();
+		history.mark_save_point();
+
+		let mut bytes = Vec::new();
+		history.save_to_writer(&mut bytes).unwrap();
+		let loaded = UndoRedo::<Add>::load_from_reader(bytes.as_slice()).unwrap();
+		assert_eq!(loaded, history);
+		assert!(loaded.is_at_save_point());
+		assert!(loaded.actions[2].is_skipped());
+	}
+
+	#[test]
+	fn save_replaces_file_without_leftovers() {
+		let history = history();
+		let path = temp_path("replace-test");
+		history.save_to_path(&path).unwrap();
+		history
+			.save_to_path_with_scope(&path, PersistScope::LastN(1))
+			.unwrap();
+		let loaded = UndoRedo::<Add>::load_from_path(&path);
+		let leftover = path.with_extension("history.tmp").exists();
+		fs::remove_file(&path).unwrap();
+
+		assert_eq!(loaded.unwrap().actions, history.actions[2..]);
+		assert!(!leftover);
+	}
+
+	#[test]
+	#[cfg(feature = "compression")]
+	fn lz77_round_trips() {
+		let repetitive: Vec<u8> = (0..10_000u32).flat_map(|i| (i % 7).to_le_bytes()).collect();
+		let noisy: Vec<u8> = (0..5_000u32)
+			.map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8)
+			.collect();
+		for data in [
+			&[][..],
+			b"a",
+			b"aaaaaaaaaaaaaaaaaaaaaa",
+			&repetitive,
+			&noisy,
+		] {
+			let compressed = Lz77.compress(data).unwrap();
+			assert_eq!(Lz77.decompress(&compressed).unwrap(), data);
+		}
+		assert!(Lz77.compress(&repetitive).unwrap().len() < repetitive.len() / 10);
+	}
+
+	#[test]
+	#[cfg(feature = "compression")]
+	fn lz77_rejects_invalid_data() {
+		let mut compressed = Lz77.compress(b"abcdabcdabcdabcd").unwrap();
+		assert!(
+			Lz77.decompress(&compressed[..compressed.len() - 1])
+				.is_err()
+		);
+		compressed[0] += 1;
+		assert!(Lz77.decompress(&compressed).is_err());
+		// A reference to before the start of the output.
+		let reference = [1, 0, 0, 0, 0, 0, 0, 0, 0x80, 1, 0];
+		assert!(Lz77.decompress(&reference).is_err());
+	}
+
+	#[test]
+	#[cfg(feature = "compression")]
+	fn lz77_saves_histories() {
+		let history = history();
+		let mut bytes = Vec::new();
+		history
+			.save_to_writer_compressed(&mut bytes, PersistScope::All, &Lz77)
+			.unwrap();
+		let loaded = UndoRedo::<Add>::load_from_reader_compressed(bytes.as_slice(), &Lz77);
+		assert_eq!(loaded.unwrap(), history);
+	}
+
+	#[cfg(feature = "compression")]
+	struct Reverse {
+		fail: bool,
+	}
+
+	#[cfg(feature = "compression")]
+	impl Compression for Reverse {
+		fn compress(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+			if self.fail {
+				return Err(io::Error::other("compression failed"));
+			}
+			Ok(data.iter().rev().copied().collect())
+		}
+
+		fn decompress(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+			Ok(data.iter().rev().copied().collect())
+		}
+	}
+
+	#[test]
+	#[cfg(feature = "compression")]
+	fn compressed_save_uses_scope() {
+		let history = history();
+		let path = temp_path("compressed-scope-test");
+		let compression = Reverse { fail: false };
+		history
+			.save_to_path_compressed(&path, PersistScope::Applied, &compression)
+			.unwrap();
+		let loaded = UndoRedo::<Add>::load_from_path_compressed(&path, &compression).unwrap();
+		fs::remove_file(&path).unwrap();
+
+		let mut expected = history.clone();
+		expected.actions.truncate(2);
+		assert_eq!(loaded, expected);
+	}
+
+	#[test]
+	#[cfg(feature = "compression")]
+	fn failed_save_keeps_old_file() {
+		let history = history();
+		let path = temp_path("failed-save-test");
+		history.save_to_path(&path).unwrap();
+
+		let result =
+			history.save_to_path_compressed(&path, PersistScope::All, &Reverse { fail: true });
+		assert!(matches!(result, Err(PersistError::Io(_))));
+		let loaded = UndoRedo::<Add>::load_from_path(&path);
+		let leftover = path.with_extension("history.tmp").exists();
+		fs::remove_file(&path).unwrap();
+
+		assert_eq!(loaded.unwrap(), history);
+		assert!(!leftover);
+	}
+}
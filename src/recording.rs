@@ -0,0 +1,127 @@
+use alloc::{
+	string::{String, ToString},
+	vec::Vec,
+};
+use core::ops::Range;
+
+use crate::{Action, HistoryStorage, InvertibleOperation, UndoRedo, UndoRedoError};
+
+/// A sequence of operations taken from a range of actions in a history (see
+/// [`UndoRedo::record_macro`]), which can be played back on any object they apply to, as a single
+/// new action.
+///
+/// The operations are those performed when applying the actions. For a macro to have the same
+/// effect on other objects, its operations should describe changes relative to an object's state
+/// (such as "move by 10"), rather than absolute ones (such as "move to 10").
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub struct Macro<Op> {
+	name: Option<String>,
+	ops: Vec<Op>,
+}
+
+impl<Op> Macro<Op> {
+	/// Creates an unnamed macro from `ops`, which are applied in order.
+	pub const fn new(ops: Vec<Op>) -> Self {
+		Self { name: None, ops }
+	}
+
+	/// Returns the name of this macro, if it has one.
+	pub fn get_name(&self) -> Option<&str> {
+		self.name.as_deref()
+	}
+
+	/// Sets the name of this macro, which is given to each action it is played back as.
+	pub fn set_name(&mut self, new_name: impl ToString) -> &mut Self {
+		self.name = Some(new_name.to_string());
+		self
+	}
+
+	/// Returns the operations of this macro, in the order they are applied.
+	pub fn ops(&self) -> &[Op] {
+		&self.ops
+	}
+
+	/// Returns the operations of this macro, in the order they are applied.
+	pub fn into_ops(self) -> Vec<Op> {
+		self.ops
+	}
+
+	/// Returns the number of operations in this macro.
+	pub fn len(&self) -> usize {
+		self.ops.len()
+	}
+
+	/// Returns `true` if this macro has no operations.
+	pub fn is_empty(&self) -> bool {
+		self.ops.is_empty()
+	}
+
+	/// Returns an action which applies this macro's operations, and reverts them with their
+	/// inverses (see [`Action::add_invertible_operation`]). The action is given this macro's name.
+	pub fn to_action<For>(&self) -> Action<Op>
+	where
+		Op: InvertibleOperation<For> + Clone,
+	{
+		let mut action = Action::default();
+		if let Some(name) = &self.name {
+			action.set_name(name);
+		}
+		for op in &self.ops {
+			action.add_invertible_operation(op.clone());
+		}
+		action
+	}
+
+	/// Applies this macro to `apply_to`, as a single new action (see [`Self::to_action`]) added at
+	/// the current point in `history`, as [`UndoRedo::create_action`] would.
+	///
+	/// The action is returned so that more can be done with it.
+	///
+	/// # Panics
+	/// See [`UndoRedo::create_action`].
+	pub fn play<'history, For, S>(
+		&self,
+		history: &'history mut UndoRedo<Op, S>,
+		apply_to: &mut For,
+	) -> &'history mut Action<Op>
+	where
+		Op: InvertibleOperation<For> + Clone,
+		S: HistoryStorage<Op>,
+	{
+		let action = self.to_action();
+		action.apply(apply_to);
+		history.push_applied(action)
+	}
+}
+
+impl<Op, S: HistoryStorage<Op>> UndoRedo<Op, S> {
+	/// Returns a macro named `name`, made of the operations performed when applying each action
+	/// within `range`, in order. Skipped actions (see [`Action::set_skipped`]) are left out.
+	///
+	/// The actions don't need to be applied, so unapplied actions can also be recorded.
+	///
+	/// # Errors
+	/// Returns `UndoRedoError::OutOfBounds` if `range` is reversed, or extends past the end of the
+	/// history.
+	pub fn record_macro(
+		&self,
+		range: Range<usize>,
+		name: impl ToString,
+	) -> Result<Macro<Op>, UndoRedoError>
+	where
+		Op: Clone,
+	{
+		if range.start > range.end || range.end > self.actions.len() {
+			return Err(UndoRedoError::OutOfBounds);
+		}
+		let ops = self
+			.iter_actions()
+			.skip(range.start)
+			.take(range.len())
+			.flat_map(|action| action.apply_ops().iter().cloned())
+			.collect();
+		let mut recorded = Macro::new(ops);
+		recorded.set_name(name);
+		Ok(recorded)
+	}
+}
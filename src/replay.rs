@@ -0,0 +1,175 @@
+use core::{error, fmt};
+
+use crate::{HistoryStorage, Operation, UndoRedo};
+
+impl<Op, S: HistoryStorage<Op>> UndoRedo<Op, S> {
+	/// Applies every applied action, in order, to a copy of `initial`, and checks that the result
+	/// equals the expected state. This is intended for turning recorded sessions into regression
+	/// tests.
+	///
+	/// `expected` is called with a position in history, and returns the state the object should be
+	/// in after that many actions (such as a snapshot recorded along with the session, or the state
+	/// of a simpler reference model). It's called with the current position first, then, if that
+	/// doesn't match, with the positions needed to bisect for the first mismatching action.
+	///
+	/// Neither this history nor `initial` is changed.
+	///
+	/// # Errors
+	/// Returns the first action whose effect doesn't match, if the result doesn't equal the
+	/// expected state. See [`ReplayMismatch`].
+	pub fn check_replay<For>(
+		&self,
+		initial: &For,
+		mut expected: impl FnMut(usize) -> For,
+	) -> Result<(), ReplayMismatch>
+	where
+		Op: Operation<For>,
+		For: Clone + PartialEq,
+	{
+		let mut matches = |position| self.replayed(initial, position) == expected(position);
+		if matches(self.tapehead) {
+			return Ok(());
+		}
+		if !matches(0) {
+			return Err(ReplayMismatch { action: None });
+		}
+
+		// The state before the first action matches, and the state after the last one doesn't, so
+		// the first mismatching action lies between them.
+		let (mut matching, mut mismatching) = (0, self.tapehead);
+		while mismatching - matching > 1 {
+			let middle = matching + (mismatching - matching) / 2;
+			if matches(middle) {
+				matching = middle;
+			} else {
+				mismatching = middle;
+			}
+		}
+		Err(ReplayMismatch {
+			action: Some(matching),
+		})
+	}
+
+	/// Performs [`Self::check_replay`], panicking if it fails.
+	///
+	/// # Panics
+	/// Panics if replaying the applied actions onto `initial` doesn't result in the expected state,
+	/// naming the first mismatching action.
+	pub fn assert_replay<For>(&self, initial: &For, expected: impl FnMut(usize) -> For)
+	where
+		Op: Operation<For>,
+		For: Clone + PartialEq,
+	{
+		if let Err(mismatch) = self.check_replay(initial, expected) {
+			panic!("{mismatch}");
+		}
+	}
+
+	/// Returns a copy of `initial` with the first `count` actions applied to it.
+	fn replayed<For>(&self, initial: &For, count: usize) -> For
+	where
+		Op: Operation<For>,
+		For: Clone,
+	{
+		let mut state = initial.clone();
+		for action in self.iter_actions().take(count) {
+			action.apply(&mut state);
+		}
+		state
+	}
+}
+
+/// An error indicating that replaying a history doesn't result in the expected state, as found by
+/// [`UndoRedo::check_replay`].
+///
+/// The mismatching action is found by bisection, which assumes that once the replayed state
+/// differs from the expected state, it differs at every later position too.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct ReplayMismatch {
+	action: Option<usize>,
+}
+
+impl ReplayMismatch {
+	/// Returns the index of the first action whose effect doesn't match the expected state, or
+	/// `None` if `initial` itself doesn't match the expected state.
+	pub fn action(&self) -> Option<usize> {
+		self.action
+	}
+}
+
+impl fmt::Display for ReplayMismatch {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self.action {
+			Some(action) => write!(
+				f,
+				"replaying action {action} does not match the expected state"
+			),
+			None => write!(f, "the initial state does not match the expected state"),
+		}
+	}
+}
+
+impl error::Error for ReplayMismatch {}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::Action;
+
+	#[derive(Clone, Debug, PartialEq)]
+	struct Add(i32);
+
+	impl Operation<i32> for Add {
+		fn apply(&self, item: &mut i32) {
+			*item += self.0;
+		}
+	}
+
+	/// Returns a history which adds 1 through 5, then undoes the last action.
+	fn history() -> UndoRedo<Add> {
+		let mut history = UndoRedo::new();
+		let mut target = 0;
+		for value in 1..=5 {
+			let mut action = Action::default();
+			action.add_redo_operation(Add(value));
+			action.add_undo_operation(Add(-value));
+			history.commit_action(action, &mut target);
+		}
+		history.undo(&mut target).unwrap();
+		history
+	}
+
+	/// Returns the sum of 1 through `position`, as the history should produce.
+	fn triangle(position: usize) -> i32 {
+		(1..=position as i32).sum()
+	}
+
+	#[test]
+	fn matching_replay_passes() {
+		let history = history();
+		assert_eq!(history.check_replay(&0, triangle), Ok(()));
+		history.assert_replay(&0, triangle);
+	}
+
+	#[test]
+	fn finds_first_mismatching_action() {
+		let history = history();
+		// The expected state is off from the third action onwards.
+		let expected = |position| triangle(position) + i32::from(position >= 3);
+		let mismatch = history.check_replay(&0, expected).unwrap_err();
+		assert_eq!(mismatch.action(), Some(2));
+	}
+
+	#[test]
+	fn reports_mismatching_initial_state() {
+		let history = history();
+		let mismatch = history.check_replay(&1, triangle).unwrap_err();
+		assert_eq!(mismatch.action(), None);
+	}
+
+	#[test]
+	#[should_panic = "replaying action 0 does not match the expected state"]
+	fn assert_replay_panics() {
+		history().assert_replay(&0, |position| triangle(position) * 2);
+	}
+}
@@ -0,0 +1,727 @@
+use alloc::{collections::BTreeSet, vec::Vec};
+use core::{error, fmt, ops::Range};
+
+use crate::{Action, HistoryStorage, Operation, UndoRedo, UndoRedoError};
+
+/// An operation which can declare which parts of the object it operates on it touches, so that
+/// dependencies between actions can be found.
+pub trait KeyedOperation {
+	/// Identifies one part of the object being operated on, such as an entity ID or a range of
+	/// lines.
+	type Key: Ord;
+
+	/// Returns the keys of every part of the object this operation reads or changes.
+	fn touched_keys(&self) -> impl Iterator<Item = Self::Key>;
+}
+
+/// An operation which can be reordered past other operations, in the style of Darcs patches.
+pub trait CommuteOperation: Sized {
+	/// Given `self` followed by `other`, returns versions of the two which can be performed in the
+	/// opposite order (`other` first) with the same overall effect, as `(other, self)`.
+	///
+	/// Returns `None` if the two cannot be reordered, such as when `other` depends on a change made
+	/// by `self`.
+	fn commute(&self, other: &Self) -> Option<(Self, Self)>;
+}
+
+/// What [`UndoRedo::undo_action_at_checked`] and [`UndoRedo::set_skipped_checked`] should do when
+/// later actions depend on the action being changed.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub enum DependencyPolicy {
+	/// Change nothing, and return the conflicting actions.
+	#[default]
+	Refuse,
+	/// Change the dependent actions too.
+	Cascade,
+}
+
+/// Describes the later actions which would be affected by selectively undoing or skipping an
+/// action, as returned by [`UndoRedo::conflicts_of`].
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct ConflictReport<K> {
+	conflicts: Vec<Conflict<K>>,
+}
+
+// An empty report holds no keys. As the `Default` derive macro assumes that we want a trait bound
+// on `K` no matter what, we have to manually implement `Default`.
+impl<K> Default for ConflictReport<K> {
+	fn default() -> Self {
+		Self {
+			conflicts: Vec::new(),
+		}
+	}
+}
+
+impl<K> ConflictReport<K> {
+	/// Returns `true` if no later actions conflict.
+	pub fn is_empty(&self) -> bool {
+		self.conflicts.is_empty()
+	}
+
+	/// Returns the number of later actions which conflict.
+	pub fn len(&self) -> usize {
+		self.conflicts.len()
+	}
+
+	/// Returns each conflicting action, in order from oldest to newest.
+	pub fn conflicts(&self) -> &[Conflict<K>] {
+		&self.conflicts
+	}
+
+	/// Returns the indices of the conflicting actions, in order from oldest to newest.
+	pub fn indices(&self) -> impl DoubleEndedIterator<Item = usize> + '_ {
+		self.conflicts.iter().map(Conflict::index)
+	}
+}
+
+/// A later action which conflicts with an action being selectively undone or skipped.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Conflict<K> {
+	index: usize,
+	keys: BTreeSet<K>,
+}
+
+impl<K> Conflict<K> {
+	/// Returns the index of the conflicting action.
+	pub fn index(&self) -> usize {
+		self.index
+	}
+
+	/// Returns the keys which the conflicting action touches, and which were also touched by the
+	/// action being undone or by an earlier conflicting action. These are why it conflicts.
+	pub fn keys(&self) -> &BTreeSet<K> {
+		&self.keys
+	}
+}
+
+/// An error indicating an issue with selectively undoing or skipping an action.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SelectiveUndoError<K> {
+	/// The history itself could not perform the request.
+	History(UndoRedoError),
+	/// Later actions conflict with the action, so nothing was done.
+	Conflicts(ConflictReport<K>),
+}
+
+impl<K> From<UndoRedoError> for SelectiveUndoError<K> {
+	fn from(value: UndoRedoError) -> Self {
+		Self::History(value)
+	}
+}
+
+impl<K> fmt::Display for SelectiveUndoError<K> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::History(e) => write!(f, "{e}"),
+			Self::Conflicts(report) => {
+				write!(
+					f,
+					"later actions depend on this action ({} in total)",
+					report.len()
+				)
+			}
+		}
+	}
+}
+
+impl<K: fmt::Debug> error::Error for SelectiveUndoError<K> {
+	fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+		match self {
+			Self::History(e) => Some(e),
+			Self::Conflicts(_) => None,
+		}
+	}
+}
+
+impl<Op> Action<Op> {
+	/// Given `self` followed by `other`, returns versions of the two which can be applied in the
+	/// opposite order with the same overall effect (and likewise reverted), as `(other, self)`.
+	/// Each keeps its name.
+	///
+	/// Returns `None` if any operation of one cannot be reordered past an operation of the other.
+	pub fn commute(&self, other: &Self) -> Option<(Self, Self)>
+	where
+		Op: CommuteOperation + Clone,
+	{
+		let (other_apply, self_apply) =
+			commute_ops(self.apply_ops.as_slice(), other.apply_ops.as_slice())?;
+		// Reverting performs `other` first, so after reordering, it's `self` that is reverted
+		// first.
+		let (self_revert, other_revert) =
+			commute_ops(other.revert_ops.as_slice(), self.revert_ops.as_slice())?;
+
+		Some((
+			Self {
+				name: other.name.clone(),
+				apply_ops: other_apply.into(),
+				revert_ops: other_revert.into(),
+				skipped: other.skipped,
+			},
+			Self {
+				name: self.name.clone(),
+				apply_ops: self_apply.into(),
+				revert_ops: self_revert.into(),
+				skipped: self.skipped,
+			},
+		))
+	}
+
+	/// Returns the keys touched by any of this action's operations, in either direction.
+	pub fn touched_keys(&self) -> BTreeSet<Op::Key>
+	where
+		Op: KeyedOperation,
+	{
+		self.apply_ops
+			.as_slice()
+			.iter()
+			.chain(self.revert_ops.as_slice())
+			.flat_map(KeyedOperation::touched_keys)
+			.collect()
+	}
+}
+
+impl<Op, S: HistoryStorage<Op>> UndoRedo<Op, S> {
+	/// Undoes the applied action at `index` alone, leaving every action after it applied.
+	///
+	/// Rather than moving back through history, this commits a new action (as
+	/// [`Self::commit_action`] would) which is the [inverse](Action::inverse) of the one at
+	/// `index`. The original action stays in history, and undoing the new action brings its changes
+	/// back.
+	///
+	/// This is only correct if no later action depends on the changes made by the one being
+	/// undone. For example, undoing the creation of an object that a later action moved will leave
+	/// the object in a state the move's own undo does not expect. See
+	/// [`Self::undo_action_at_checked`] for a version which checks for this.
+	///
+	/// # Errors
+	/// Returns `UndoRedoError::OutOfBounds` if there is no applied action at `index`.
+	///
+	/// # Panics
+	/// See [`Self::create_action`].
+	pub fn undo_action_at<For>(
+		&mut self,
+		index: usize,
+		apply_to: &mut For,
+	) -> Result<(), UndoRedoError>
+	where
+		Op: Operation<For> + Clone,
+	{
+		let compensating = self
+			.actions
+			.get(index)
+			.filter(|_| index < self.tapehead)
+			.ok_or(UndoRedoError::OutOfBounds)?
+			.inverse();
+		self.commit_action(compensating, apply_to);
+		Ok(())
+	}
+
+	/// Moves the applied action at `index` to just before the current position in history, by
+	/// [commuting](Action::commute) it past each applied action after it. The object being operated
+	/// on is not changed.
+	///
+	/// Following this with [`Self::undo`] undoes the action alone, without leaving behind a
+	/// compensating action as [`Self::undo_action_at`] does.
+	///
+	/// # Errors
+	/// * Returns `UndoRedoError::OutOfBounds` if there is no applied action at `index`, or if any
+	///   of the actions involved are shared with another history (see
+	///   [`HistoryStorage::get_mut`]).
+	/// * Returns `UndoRedoError::DoesNotCommute` if the action cannot be reordered past one of the
+	///   actions after it.
+	///
+	/// The history is left unchanged if an error is returned.
+	pub fn commute_to_end(&mut self, index: usize) -> Result<(), UndoRedoError>
+	where
+		Op: CommuteOperation + Clone,
+	{
+		let mut moving = self
+			.actions
+			.get(index)
+			.filter(|_| index < self.tapehead)
+			.ok_or(UndoRedoError::OutOfBounds)?
+			.clone();
+		let mut reordered = Vec::new();
+		for later in index + 1..self.tapehead {
+			let action = self.actions.get(later).ok_or(UndoRedoError::OutOfBounds)?;
+			let (earlier, moved) = moving
+				.commute(action)
+				.ok_or(UndoRedoError::DoesNotCommute)?;
+			reordered.push(earlier);
+			moving = moved;
+		}
+		reordered.push(moving);
+
+		if (index..self.tapehead).any(|index| self.actions.get_mut(index).is_none()) {
+			return Err(UndoRedoError::OutOfBounds);
+		}
+		self.replace_range(index, reordered);
+		Ok(())
+	}
+
+	/// Moves the pending action (one after the current position in history, which would be
+	/// applied by redoing) at `from` so that it ends up at index `to`, shifting the pending actions
+	/// in between over by one. The object being operated on is not changed.
+	///
+	/// The actions are not checked for conflicts; see [`Self::move_pending_checked`] and
+	/// [`Self::move_pending_commuting`] for versions which do.
+	///
+	/// # Errors
+	/// Returns `UndoRedoError::OutOfBounds` if there is no pending action at `from` or `to`, or if
+	/// any of the actions involved are shared with another history (see
+	/// [`HistoryStorage::get_mut`]). The history is left unchanged in this case.
+	pub fn move_pending(&mut self, from: usize, to: usize) -> Result<(), UndoRedoError> {
+		let range = self.pending_range(from, to)?;
+		let mut moved: Vec<_> = range
+			.clone()
+			.filter_map(|index| self.actions.get_mut(index).map(core::mem::take))
+			.collect();
+		if from < to {
+			moved.rotate_left(1);
+		} else {
+			moved.rotate_right(1);
+		}
+		self.replace_range(range.start, moved);
+		Ok(())
+	}
+
+	/// Like [`Self::move_pending`], but refuses to move the action past any action which touches
+	/// the same keys (see [`Action::touched_keys`]), as their order may matter.
+	///
+	/// # Errors
+	/// * Returns `UndoRedoError::DoesNotCommute` if the action touches the same keys as one of the
+	///   actions it would be moved past.
+	/// * See [`Self::move_pending`] for the other errors.
+	///
+	/// The history is left unchanged if an error is returned.
+	pub fn move_pending_checked(&mut self, from: usize, to: usize) -> Result<(), UndoRedoError>
+	where
+		Op: KeyedOperation,
+	{
+		let range = self.pending_range(from, to)?;
+		let keys = self
+			.actions
+			.get(from)
+			.ok_or(UndoRedoError::OutOfBounds)?
+			.touched_keys();
+		let conflicts = range
+			.filter(|&index| index != from)
+			.filter_map(|index| self.actions.get(index))
+			.any(|action| !action.touched_keys().is_disjoint(&keys));
+		if conflicts {
+			return Err(UndoRedoError::DoesNotCommute);
+		}
+		self.move_pending(from, to)
+	}
+
+	/// Like [`Self::move_pending`], but [commutes](Action::commute) the action past each action
+	/// between `from` and `to`, so that the actions are rewritten to keep the same overall effect
+	/// in their new order.
+	///
+	/// # Errors
+	/// * Returns `UndoRedoError::DoesNotCommute` if the action cannot be reordered past one of the
+	///   actions it would be moved past.
+	/// * See [`Self::move_pending`] for the other errors.
+	///
+	/// The history is left unchanged if an error is returned.
+	pub fn move_pending_commuting(&mut self, from: usize, to: usize) -> Result<(), UndoRedoError>
+	where
+		Op: CommuteOperation + Clone,
+	{
+		let range = self.pending_range(from, to)?;
+		let mut moving = self
+			.actions
+			.get(from)
+			.ok_or(UndoRedoError::OutOfBounds)?
+			.clone();
+		let mut reordered = Vec::new();
+		if from < to {
+			for later in from + 1..=to {
+				let action = self.actions.get(later).ok_or(UndoRedoError::OutOfBounds)?;
+				let (earlier, moved) = moving
+					.commute(action)
+					.ok_or(UndoRedoError::DoesNotCommute)?;
+				reordered.push(earlier);
+				moving = moved;
+			}
+			reordered.push(moving);
+		} else {
+			for earlier in (to..from).rev() {
+				let action = self
+					.actions
+					.get(earlier)
+					.ok_or(UndoRedoError::OutOfBounds)?;
+				let (moved, later) = action
+					.commute(&moving)
+					.ok_or(UndoRedoError::DoesNotCommute)?;
+				reordered.push(later);
+				moving = moved;
+			}
+			reordered.push(moving);
+			reordered.reverse();
+		}
+		self.replace_range(range.start, reordered);
+		Ok(())
+	}
+
+	/// Undoes the most recent applied action which only touches `scope`, skipping over any later
+	/// actions that don't, so that (for example) each layer of a drawing can have its own undo.
+	///
+	/// The action is [commuted](Self::commute_to_end) past the later actions, then undone with
+	/// [`Self::undo`], so it can be redone as usual. An action touches only `scope` if every key
+	/// returned by [`Action::touched_keys`] is equal to it.
+	///
+	/// # Errors
+	/// * Returns `UndoRedoError::NothingToDo` if no applied action touches only `scope`.
+	/// * See [`Self::commute_to_end`] for the other errors. Nothing is undone in those cases.
+	pub fn undo_in_scope<For>(
+		&mut self,
+		scope: &Op::Key,
+		apply_to: &mut For,
+	) -> Result<(), UndoRedoError>
+	where
+		Op: Operation<For> + KeyedOperation + CommuteOperation + Clone,
+	{
+		let index = (0..self.tapehead)
+			.rev()
+			.find(|&index| {
+				self.actions.get(index).is_some_and(|action| {
+					let keys = action.touched_keys();
+					!keys.is_empty() && keys.iter().all(|key| key == scope)
+				})
+			})
+			.ok_or(UndoRedoError::NothingToDo)?;
+		self.commute_to_end(index)?;
+		self.undo(apply_to)
+	}
+
+	/// Undoes the most recent applied action by committing its [inverse](Action::inverse) as a new
+	/// action, rather than moving back through history. History only ever grows this way, as is
+	/// needed when it doubles as an audit log, and the undo can itself be undone.
+	///
+	/// As the inverse becomes the most recent action, calling this twice in a row reverts the first
+	/// call. To soft-undo older actions, use [`Self::undo_action_at`].
+	///
+	/// # Errors
+	/// Returns `UndoRedoError::NothingToDo` if no actions are applied.
+	///
+	/// # Panics
+	/// See [`Self::create_action`].
+	pub fn soft_undo<For>(&mut self, apply_to: &mut For) -> Result<(), UndoRedoError>
+	where
+		Op: Operation<For> + Clone,
+	{
+		let index = self
+			.tapehead
+			.checked_sub(1)
+			.ok_or(UndoRedoError::NothingToDo)?;
+		self.undo_action_at(index, apply_to)
+	}
+
+	/// Returns the indices of the applied actions after `index` which depend on the action at
+	/// `index`, in order from oldest to newest.
+	///
+	/// An action depends on another if it touches any of the same keys, or if it depends on an
+	/// action which does. See [`Self::conflicts_of`] for why each action depends on it.
+	pub fn dependents_of(&self, index: usize) -> Vec<usize>
+	where
+		Op: KeyedOperation,
+	{
+		self.conflicts_of(index).indices().collect()
+	}
+
+	/// Like [`Self::dependents_of`], but also reports which keys cause each dependency, so that
+	/// (for example) the user can be asked whether to undo the dependent actions too.
+	pub fn conflicts_of(&self, index: usize) -> ConflictReport<Op::Key>
+	where
+		Op: KeyedOperation,
+	{
+		let mut report = ConflictReport::default();
+		let Some(action) = self.actions.get(index) else {
+			return report;
+		};
+		let mut keys = action.touched_keys();
+		for later in index + 1..self.tapehead {
+			let Some(action) = self.actions.get(later) else {
+				break;
+			};
+			let (shared, new): (BTreeSet<_>, BTreeSet<_>) = action
+				.touched_keys()
+				.into_iter()
+				.partition(|key| keys.contains(key));
+			if !shared.is_empty() {
+				report.conflicts.push(Conflict {
+					index: later,
+					keys: shared,
+				});
+				keys.extend(new);
+			}
+		}
+		report
+	}
+
+	/// Like [`Self::undo_action_at`], but first checks whether any later actions depend on the one
+	/// being undone (see [`Self::conflicts_of`]), and handles them according to `policy`.
+	///
+	/// When cascading, a single action is committed which undoes the dependent actions (newest
+	/// first) and then the action at `index`, so that all of them can be brought back at once.
+	///
+	/// Returns the actions which depend on the one at `index`, which were undone along with it.
+	///
+	/// # Errors
+	/// * Returns `SelectiveUndoError::Conflicts` if `policy` is [`DependencyPolicy::Refuse`] and
+	///   any later actions depend on the one at `index`.
+	/// * Returns `SelectiveUndoError::History` with `UndoRedoError::OutOfBounds` if there is no
+	///   applied action at `index`.
+	///
+	/// # Panics
+	/// See [`Self::create_action`].
+	pub fn undo_action_at_checked<For>(
+		&mut self,
+		index: usize,
+		apply_to: &mut For,
+		policy: DependencyPolicy,
+	) -> Result<ConflictReport<Op::Key>, SelectiveUndoError<Op::Key>>
+	where
+		Op: Operation<For> + KeyedOperation + Clone,
+	{
+		if index >= self.tapehead {
+			return Err(UndoRedoError::OutOfBounds.into());
+		}
+		let report = self.conflicts_of(index);
+		if report.is_empty() {
+			self.undo_action_at(index, apply_to)?;
+			return Ok(report);
+		}
+		if policy == DependencyPolicy::Refuse {
+			return Err(SelectiveUndoError::Conflicts(report));
+		}
+
+		let mut compensating = Action::default();
+		let undone = || core::iter::once(index).chain(report.indices());
+		for action in undone().rev().filter_map(|index| self.actions.get(index)) {
+			for op in action.revert_ops() {
+				compensating.add_redo_operation(op.clone());
+			}
+		}
+		for action in undone().filter_map(|index| self.actions.get(index)) {
+			for op in action.apply_ops() {
+				compensating.add_undo_operation(op.clone());
+			}
+		}
+		self.commit_action(compensating, apply_to);
+		Ok(report)
+	}
+
+	/// Like [`Self::set_skipped`], but first checks whether any later actions depend on the action
+	/// at `index` (see [`Self::conflicts_of`]), and handles them according to `policy`.
+	///
+	/// When cascading, the dependent actions are skipped or un-skipped along with the one at
+	/// `index`. They are reverted (newest first) before the action at `index` is, and when
+	/// un-skipping, applied again (oldest first) after it, so that each action is applied to the
+	/// state it expects.
+	///
+	/// Returns the actions which depend on the one at `index`, which were changed along with it.
+	/// Unapplied actions have no dependents, as nothing has been applied after them.
+	///
+	/// # Errors
+	/// * Returns `SelectiveUndoError::Conflicts` if `policy` is [`DependencyPolicy::Refuse`], the
+	///   action would be changed, and any later actions depend on it.
+	/// * Returns `SelectiveUndoError::History` with the errors of [`Self::set_skipped`]. Nothing is
+	///   changed in this case.
+	pub fn set_skipped_checked<For>(
+		&mut self,
+		index: usize,
+		skipped: bool,
+		apply_to: &mut For,
+		policy: DependencyPolicy,
+	) -> Result<ConflictReport<Op::Key>, SelectiveUndoError<Op::Key>>
+	where
+		Op: Operation<For> + KeyedOperation,
+	{
+		let action = self.actions.get(index).ok_or(UndoRedoError::OutOfBounds)?;
+		if action.skipped == skipped {
+			return Ok(ConflictReport::default());
+		}
+		let report = self.conflicts_of(index);
+		if report.is_empty() {
+			self.set_skipped(index, skipped, apply_to)?;
+			return Ok(report);
+		}
+		if policy == DependencyPolicy::Refuse {
+			return Err(SelectiveUndoError::Conflicts(report));
+		}
+		let changed = core::iter::once(index).chain(report.indices());
+		if changed
+			.into_iter()
+			.any(|index| self.actions.get_mut(index).is_none())
+		{
+			return Err(UndoRedoError::OutOfBounds.into());
+		}
+
+		// The dependent actions are reverted before the action at `index` changes, and (when
+		// un-skipping) applied again after it.
+		for dependent in report.indices().rev() {
+			self.set_skipped(dependent, true, apply_to)?;
+		}
+		self.set_skipped(index, skipped, apply_to)?;
+		if !skipped {
+			for dependent in report.indices() {
+				self.set_skipped(dependent, false, apply_to)?;
+			}
+		}
+		Ok(report)
+	}
+}
+
+impl<Op, S: HistoryStorage<Op>> UndoRedo<Op, S> {
+	/// Returns the range of indices between the pending actions at `from` and `to`, inclusive,
+	/// after checking that every action in it can be modified.
+	fn pending_range(&mut self, from: usize, to: usize) -> Result<Range<usize>, UndoRedoError> {
+		let pending = self.tapehead..self.actions.len();
+		if !pending.contains(&from) || !pending.contains(&to) {
+			return Err(UndoRedoError::OutOfBounds);
+		}
+		let range = from.min(to)..from.max(to) + 1;
+		if range
+			.clone()
+			.any(|index| self.actions.get_mut(index).is_none())
+		{
+			return Err(UndoRedoError::OutOfBounds);
+		}
+		Ok(range)
+	}
+
+	/// Overwrites the actions starting at `start` with `actions`.
+	fn replace_range(&mut self, start: usize, actions: Vec<Action<Op>>) {
+		for (index, action) in (start..).zip(actions) {
+			if let Some(slot) = self.actions.get_mut(index) {
+				*slot = action;
+			}
+		}
+	}
+}
+
+/// Reorders `first` followed by `second` into `second` followed by `first`, returning the new
+/// `(second, first)`.
+fn commute_ops<Op: CommuteOperation + Clone>(
+	first: &[Op],
+	second: &[Op],
+) -> Option<(Vec<Op>, Vec<Op>)> {
+	let mut first = first.to_vec();
+	let mut second_moved = Vec::with_capacity(second.len());
+	for op in second {
+		// Move this operation from the end of `first` to its start, one operation at a time.
+		let mut moving = op.clone();
+		for earlier in first.iter_mut().rev() {
+			let (moved, reordered) = earlier.commute(&moving)?;
+			*earlier = reordered;
+			moving = moved;
+		}
+		second_moved.push(moving);
+	}
+	Some((second_moved, first))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Adds to one of three counters.
+	#[derive(Clone, Debug, PartialEq)]
+	struct Add(usize, i32);
+
+	impl Operation<[i32; 3]> for Add {
+		fn apply(&self, item: &mut [i32; 3]) {
+			item[self.0] += self.1;
+		}
+	}
+
+	impl KeyedOperation for Add {
+		type Key = usize;
+
+		fn touched_keys(&self) -> impl Iterator<Item = usize> {
+			core::iter::once(self.0)
+		}
+	}
+
+	/// Returns a history where the third action depends on the first.
+	fn history() -> (UndoRedo<Add>, [i32; 3]) {
+		let mut history = UndoRedo::new();
+		let mut counters = [0; 3];
+		for (counter, amount) in [(0, 1), (1, 2), (0, 3)] {
+			let mut action = Action::default();
+			action
+				.add_redo_operation(Add(counter, amount))
+				.add_undo_operation(Add(counter, -amount));
+			history.commit_action(action, &mut counters);
+		}
+		(history, counters)
+	}
+
+	#[test]
+	fn reports_conflicts() {
+		let (history, _) = history();
+		let report = history.conflicts_of(0);
+		assert_eq!(report.indices().collect::<Vec<_>>(), [2]);
+		assert_eq!(report.conflicts()[0].keys(), &BTreeSet::from([0]));
+		assert!(history.conflicts_of(1).is_empty());
+	}
+
+	#[test]
+	fn refusing_changes_nothing() {
+		let (mut history, mut counters) = history();
+		let expected = history.clone();
+
+		let result = history.undo_action_at_checked(0, &mut counters, DependencyPolicy::Refuse);
+		assert_eq!(
+			result,
+			Err(SelectiveUndoError::Conflicts(history.conflicts_of(0)))
+		);
+		let result = history.set_skipped_checked(0, true, &mut counters, DependencyPolicy::Refuse);
+		assert!(matches!(result, Err(SelectiveUndoError::Conflicts(_))));
+		assert_eq!(history, expected);
+		assert_eq!(counters, [4, 2, 0]);
+
+		let report = history
+			.undo_action_at_checked(1, &mut counters, DependencyPolicy::Refuse)
+			.unwrap();
+		assert!(report.is_empty());
+		assert_eq!(counters, [4, 0, 0]);
+	}
+
+	#[test]
+	fn cascading_undo_undoes_dependents() {
+		let (mut history, mut counters) = history();
+		let report = history
+			.undo_action_at_checked(0, &mut counters, DependencyPolicy::Cascade)
+			.unwrap();
+		assert_eq!(report.indices().collect::<Vec<_>>(), [2]);
+		assert_eq!(counters, [0, 2, 0]);
+
+		history.undo(&mut counters).unwrap();
+		assert_eq!(counters, [4, 2, 0]);
+	}
+
+	#[test]
+	fn cascading_skip_skips_dependents() {
+		let (mut history, mut counters) = history();
+		let report = history
+			.set_skipped_checked(0, true, &mut counters, DependencyPolicy::Cascade)
+			.unwrap();
+		assert_eq!(report.indices().collect::<Vec<_>>(), [2]);
+		assert_eq!(counters, [0, 2, 0]);
+		let skipped = |history: &UndoRedo<Add>| {
+			(0..3)
+				.map(|index| history.actions[index].is_skipped())
+				.collect::<Vec<_>>()
+		};
+		assert_eq!(skipped(&history), [true, false, true]);
+
+		history
+			.set_skipped_checked(0, false, &mut counters, DependencyPolicy::Cascade)
+			.unwrap();
+		assert_eq!(counters, [4, 2, 0]);
+		assert_eq!(skipped(&history), [false; 3]);
+	}
+}
@@ -0,0 +1,180 @@
+use alloc::{sync::Arc, vec::Vec};
+use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::{Action, HistoryStorage, Operation, UndoRedo, UndoRedoError};
+
+/// An [`UndoRedo`] which can be shared between threads.
+///
+/// Cloning this creates another handle to the same history.
+///
+/// Methods which operate on an object take it as a [`RwLock`]. Whenever both locks are needed, the
+/// history's lock is always taken first, then the object's. Other code that takes both locks
+/// should do the same, to avoid deadlocks.
+///
+/// Locks are taken even if they have been poisoned by a panic on another thread. If that panic
+/// came from an operation, the object may have been left with an action only partially applied.
+#[derive(Debug)]
+pub struct SharedUndoRedo<Op, S = Vec<Action<Op>>> {
+	history: Arc<RwLock<UndoRedo<Op, S>>>,
+}
+
+// A new handle to the same history can be created regardless of what the history holds. As the
+// `Clone` derive macro assumes that we want trait bounds on `Op` and `S` no matter what, we have to
+// manually implement `Clone`.
+impl<Op, S> Clone for SharedUndoRedo<Op, S> {
+	fn clone(&self) -> Self {
+		Self {
+			history: Arc::clone(&self.history),
+		}
+	}
+}
+
+impl<Op, S: Default> Default for SharedUndoRedo<Op, S> {
+	fn default() -> Self {
+		Self::new(UndoRedo::default())
+	}
+}
+
+impl<Op, S> SharedUndoRedo<Op, S> {
+	/// Wraps `history` so it can be shared between threads.
+	pub fn new(history: UndoRedo<Op, S>) -> Self {
+		Self {
+			history: Arc::new(RwLock::new(history)),
+		}
+	}
+
+	/// Locks the history for reading, blocking until it is available.
+	pub fn read(&self) -> RwLockReadGuard<'_, UndoRedo<Op, S>> {
+		self.history.read().unwrap_or_else(PoisonError::into_inner)
+	}
+
+	/// Locks the history for writing, blocking until it is available.
+	pub fn write(&self) -> RwLockWriteGuard<'_, UndoRedo<Op, S>> {
+		self.history.write().unwrap_or_else(PoisonError::into_inner)
+	}
+}
+
+impl<Op, S: HistoryStorage<Op>> SharedUndoRedo<Op, S> {
+	/// Creates a new action, fills it using `build`, then applies it to `target`, all while holding
+	/// the locks on both the history and `target`. No other thread can observe the action before
+	/// it has been applied.
+	///
+	/// See [`UndoRedo::create_action`] for how the action is created.
+	///
+	/// # Panics
+	/// See [`UndoRedo::create_action`] and [`UndoRedo::redo`].
+	pub fn commit<For>(&self, target: &RwLock<For>, build: impl FnOnce(&mut Action<Op>))
+	where
+		Op: Operation<For>,
+	{
+		let mut history = self.write();
+		build(history.create_action());
+
+		let mut target = write_lock(target);
+		history
+			.redo(&mut *target)
+			.expect("an action was just created, so there should be something to redo");
+	}
+
+	/// Locks the history and `target`, then performs [`UndoRedo::redo`].
+	///
+	/// # Errors
+	/// See [`UndoRedo::redo`].
+	pub fn redo<For>(&self, target: &RwLock<For>) -> Result<(), UndoRedoError>
+	where
+		Op: Operation<For>,
+	{
+		let mut history = self.write();
+		history.redo(&mut *write_lock(target))
+	}
+
+	/// Locks the history and `target`, then performs [`UndoRedo::undo`].
+	///
+	/// # Errors
+	/// See [`UndoRedo::undo`].
+	pub fn undo<For>(&self, target: &RwLock<For>) -> Result<(), UndoRedoError>
+	where
+		Op: Operation<For>,
+	{
+		let mut history = self.write();
+		history.undo(&mut *write_lock(target))
+	}
+
+	/// Locks the history and `target`, then performs [`UndoRedo::jump_to`].
+	///
+	/// # Errors
+	/// See [`UndoRedo::jump_to`].
+	pub fn jump_to<For>(&self, position: usize, target: &RwLock<For>) -> Result<(), UndoRedoError>
+	where
+		Op: Operation<For>,
+	{
+		let mut history = self.write();
+		history.jump_to(position, &mut *write_lock(target))
+	}
+}
+
+fn write_lock<For>(target: &RwLock<For>) -> RwLockWriteGuard<'_, For> {
+	target.write().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::thread;
+
+	use super::*;
+
+	#[derive(Debug)]
+	struct Add(i32);
+
+	impl Operation<i32> for Add {
+		fn apply(&self, item: &mut i32) {
+			*item += self.0;
+		}
+	}
+
+	#[test]
+	fn commits_from_many_threads() {
+		let history = SharedUndoRedo::<Add>::default();
+		let target = RwLock::new(0);
+
+		thread::scope(|scope| {
+			for _ in 0..4 {
+				let history = history.clone();
+				let target = &target;
+				scope.spawn(move || {
+					for value in 1..=25 {
+						history.commit(target, |action| {
+							action
+								.add_redo_operation(Add(value))
+								.add_undo_operation(Add(-value));
+						});
+					}
+				});
+			}
+			// Every state a reader sees has each committed action applied exactly once.
+			scope.spawn(|| {
+				for _ in 0..100 {
+					let history = history.read();
+					let target = target.read().unwrap();
+					let applied: i32 = (0..history.position())
+						.filter_map(|index| history.actions.get(index))
+						.flat_map(|action| action.apply_ops.as_slice())
+						.map(|op| op.0)
+						.sum();
+					assert_eq!(*target, applied);
+				}
+			});
+		});
+
+		assert_eq!(history.read().position(), 100);
+		assert_eq!(*target.read().unwrap(), 4 * (1..=25).sum::<i32>());
+		history.jump_to(0, &target).unwrap();
+		assert_eq!(*target.read().unwrap(), 0);
+		assert!(matches!(
+			history.undo(&target),
+			Err(UndoRedoError::NothingToDo)
+		));
+		history.redo(&target).unwrap();
+		assert_eq!(history.read().position(), 1);
+	}
+}
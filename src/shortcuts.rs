@@ -0,0 +1,145 @@
+use crate::{HistoryStorage, Operation, UndoRedo, UndoRedoError};
+
+/// The conventions a platform follows for undo and redo shortcuts.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum Platform {
+	/// Command+Z undoes, and Command+Shift+Z redoes.
+	Apple,
+	/// Ctrl+Z undoes, and Ctrl+Shift+Z or Ctrl+Y redoes.
+	Other,
+}
+
+impl Platform {
+	/// Returns the platform this crate was compiled for.
+	pub const fn current() -> Self {
+		if cfg!(any(target_os = "macos", target_os = "ios")) {
+			Self::Apple
+		} else {
+			Self::Other
+		}
+	}
+}
+
+/// A key press, as reported by whatever library handles input.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub struct KeyPress {
+	/// The key that was pressed, as the character it types without Shift held (so `'z'` rather
+	/// than `'Z'`). Keys which don't type a character can use any character other than `'z'` and
+	/// `'y'`.
+	pub key: char,
+	/// Whether Ctrl was held.
+	pub ctrl: bool,
+	/// Whether Shift was held.
+	pub shift: bool,
+	/// Whether Alt (or Option) was held.
+	pub alt: bool,
+	/// Whether Command (or the Windows/Super key) was held.
+	pub command: bool,
+	/// Whether this press was repeated from the key being held down.
+	pub repeat: bool,
+}
+
+/// A history shortcut which a key press can trigger.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum Shortcut {
+	/// Reverts actions.
+	Undo,
+	/// Applies actions.
+	Redo,
+}
+
+/// Translates key presses into undo and redo, following a platform's conventions.
+///
+/// When a shortcut's key is held down, each repeated press undoes or redoes one action at first.
+/// After a number of repeats, each one undoes or redoes more actions at once, so that holding the
+/// key moves through a long history quickly. See [`Self::set_acceleration`].
+#[derive(Clone, Debug)]
+pub struct ShortcutHandler {
+	platform: Platform,
+	accelerate_after: usize,
+	max_steps: usize,
+	repeats: usize,
+}
+
+impl ShortcutHandler {
+	/// Creates a handler which follows the conventions of `platform`.
+	pub const fn new(platform: Platform) -> Self {
+		Self {
+			platform,
+			accelerate_after: 10,
+			max_steps: 8,
+			repeats: 0,
+		}
+	}
+
+	/// Sets how acceleration works while a shortcut's key is held down. After `after` repeats,
+	/// each repeat undoes or redoes one more action than the last, up to `max_steps` actions.
+	///
+	/// By default, acceleration starts after 10 repeats, up to 8 actions per repeat. A `max_steps`
+	/// of 1 or less disables acceleration.
+	pub fn set_acceleration(&mut self, after: usize, max_steps: usize) {
+		self.accelerate_after = after;
+		self.max_steps = max_steps;
+	}
+
+	/// Returns the shortcut `press` triggers, if any.
+	pub fn shortcut(&self, press: &KeyPress) -> Option<Shortcut> {
+		let modifier = match self.platform {
+			Platform::Apple => press.command && !press.ctrl,
+			Platform::Other => press.ctrl && !press.command,
+		};
+		if !modifier || press.alt {
+			return None;
+		}
+		match (press.key, press.shift, self.platform) {
+			('z', false, _) => Some(Shortcut::Undo),
+			('z', true, _) | ('y', false, Platform::Other) => Some(Shortcut::Redo),
+			_ => None,
+		}
+	}
+
+	/// Handles `press`, undoing or redoing actions of `history` if it triggers a shortcut.
+	///
+	/// Returns the shortcut that was triggered and how many actions were undone or redone, or
+	/// `None` if `press` is not a shortcut.
+	///
+	/// # Errors
+	/// Returns `UndoRedoError::NothingToDo` if `press` triggers a shortcut, but there is nothing
+	/// to undo or redo.
+	pub fn handle<Op, S, For>(
+		&mut self,
+		press: &KeyPress,
+		history: &mut UndoRedo<Op, S>,
+		apply_to: &mut For,
+	) -> Option<Result<(Shortcut, usize), UndoRedoError>>
+	where
+		Op: Operation<For>,
+		S: HistoryStorage<Op>,
+	{
+		let shortcut = self.shortcut(press)?;
+		self.repeats = if press.repeat {
+			self.repeats.saturating_add(1)
+		} else {
+			0
+		};
+
+		let steps = self.steps();
+		let result = match shortcut {
+			Shortcut::Undo => history.undo_n(steps, apply_to),
+			Shortcut::Redo => history.redo_n(steps, apply_to),
+		};
+		Some(result.map(|count| (shortcut, count)))
+	}
+
+	/// Returns how many actions the current press should undo or redo.
+	fn steps(&self) -> usize {
+		let accelerated = self.repeats.saturating_sub(self.accelerate_after);
+		accelerated.saturating_add(1).min(self.max_steps.max(1))
+	}
+}
+
+impl Default for ShortcutHandler {
+	fn default() -> Self {
+		Self::new(Platform::current())
+	}
+}
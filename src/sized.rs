@@ -0,0 +1,78 @@
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::mem::size_of;
+
+use crate::SizedOperation;
+
+macro_rules! impl_without_heap {
+	($($ty:ty),+ $(,)?) => {
+		$(
+			impl SizedOperation for $ty {
+				fn heap_size(&self) -> usize {
+					0
+				}
+			}
+		)+
+	};
+}
+
+impl_without_heap!(
+	(),
+	bool,
+	char,
+	u8,
+	u16,
+	u32,
+	u64,
+	u128,
+	usize,
+	i8,
+	i16,
+	i32,
+	i64,
+	i128,
+	isize,
+	f32,
+	f64,
+);
+
+impl SizedOperation for String {
+	fn heap_size(&self) -> usize {
+		self.capacity()
+	}
+}
+
+impl SizedOperation for Box<str> {
+	fn heap_size(&self) -> usize {
+		self.len()
+	}
+}
+
+impl<T: SizedOperation> SizedOperation for Vec<T> {
+	fn heap_size(&self) -> usize {
+		self.capacity() * size_of::<T>() + self.iter().map(T::heap_size).sum::<usize>()
+	}
+}
+
+impl<T: SizedOperation> SizedOperation for Box<[T]> {
+	fn heap_size(&self) -> usize {
+		self.len() * size_of::<T>() + self.iter().map(T::heap_size).sum::<usize>()
+	}
+}
+
+impl<T: SizedOperation> SizedOperation for Box<T> {
+	fn heap_size(&self) -> usize {
+		size_of::<T>() + (**self).heap_size()
+	}
+}
+
+impl<T: SizedOperation> SizedOperation for Option<T> {
+	fn heap_size(&self) -> usize {
+		self.as_ref().map_or(0, T::heap_size)
+	}
+}
+
+impl<T: SizedOperation, const N: usize> SizedOperation for [T; N] {
+	fn heap_size(&self) -> usize {
+		self.iter().map(T::heap_size).sum()
+	}
+}
@@ -0,0 +1,144 @@
+use alloc::{
+	string::{String, ToString},
+	sync::Arc,
+	vec::Vec,
+};
+use core::{
+	mem,
+	sync::atomic::{AtomicUsize, Ordering},
+};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use crate::{HistoryStorage, UndoRedo};
+
+/// An immutable copy of the parts of a history needed to display it, such as in an undo menu.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub struct HistorySnapshot {
+	/// See [`UndoRedo::position`].
+	pub position: usize,
+	/// See [`UndoRedo::save_point`].
+	pub save_point: Option<usize>,
+	/// The name of each action, in order from oldest to newest.
+	pub action_names: Vec<Option<String>>,
+}
+
+impl HistorySnapshot {
+	/// Returns the number of actions in the history.
+	pub fn len(&self) -> usize {
+		self.action_names.len()
+	}
+
+	/// Returns `true` if the history has no actions.
+	pub fn is_empty(&self) -> bool {
+		self.action_names.is_empty()
+	}
+
+	/// Returns the name of the action that would be reverted by undoing, if any.
+	pub fn undo_name(&self) -> Option<&str> {
+		let index = self.position.checked_sub(1)?;
+		self.action_names.get(index)?.as_deref()
+	}
+
+	/// Returns the name of the action that would be applied by redoing, if any.
+	pub fn redo_name(&self) -> Option<&str> {
+		self.action_names.get(self.position)?.as_deref()
+	}
+}
+
+impl<Op, S: HistoryStorage<Op>> UndoRedo<Op, S> {
+	/// Returns a [`HistorySnapshot`] of this history's current state.
+	pub fn snapshot(&self) -> HistorySnapshot {
+		HistorySnapshot {
+			position: self.tapehead,
+			save_point: self.save_point,
+			action_names: self
+				.iter_actions()
+				.map(|action| action.get_name().map(ToString::to_string))
+				.collect(),
+		}
+	}
+}
+
+/// Publishes [`HistorySnapshot`]s from the thread that owns a history, for any number of
+/// [`SnapshotReader`]s on other threads.
+///
+/// Readers never wait on the history itself. Reading a snapshot that hasn't changed since it was
+/// last read is a single atomic load; only reading a newly published snapshot takes a lock, and
+/// that lock is only ever held long enough to swap or clone an [`Arc`].
+#[derive(Debug, Default)]
+pub struct SnapshotPublisher {
+	shared: Arc<Published>,
+}
+
+#[derive(Debug, Default)]
+struct Published {
+	/// Incremented every time a snapshot is published, so readers can tell if theirs is outdated.
+	version: AtomicUsize,
+	latest: Mutex<Arc<HistorySnapshot>>,
+}
+
+impl Published {
+	fn lock(&self) -> MutexGuard<'_, Arc<HistorySnapshot>> {
+		self.latest.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+
+	fn load(&self) -> (usize, Arc<HistorySnapshot>) {
+		let latest = self.lock();
+		// The version is only changed while the lock is held, so it matches the snapshot.
+		(self.version.load(Ordering::Relaxed), Arc::clone(&latest))
+	}
+}
+
+impl SnapshotPublisher {
+	/// Creates a publisher whose initial snapshot is of an empty history.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Takes a snapshot of `history` and publishes it, replacing the previous snapshot.
+	pub fn publish<Op, S: HistoryStorage<Op>>(&self, history: &UndoRedo<Op, S>) {
+		let snapshot = Arc::new(history.snapshot());
+		let mut latest = self.shared.lock();
+		let previous = mem::replace(&mut *latest, snapshot);
+		self.shared.version.fetch_add(1, Ordering::Release);
+		// Free the previous snapshot (if no reader holds it) only after unlocking.
+		drop(latest);
+		drop(previous);
+	}
+
+	/// Creates a reader of the snapshots published by this publisher. Readers can be sent to other
+	/// threads, and cloned.
+	pub fn reader(&self) -> SnapshotReader {
+		let shared = Arc::clone(&self.shared);
+		let (version, cached) = shared.load();
+		SnapshotReader {
+			shared,
+			version,
+			cached,
+		}
+	}
+}
+
+/// Reads the snapshots published by a [`SnapshotPublisher`].
+#[derive(Clone, Debug)]
+pub struct SnapshotReader {
+	shared: Arc<Published>,
+	version: usize,
+	cached: Arc<HistorySnapshot>,
+}
+
+impl SnapshotReader {
+	/// Returns the most recently published snapshot.
+	pub fn load(&mut self) -> &Arc<HistorySnapshot> {
+		if self.shared.version.load(Ordering::Acquire) != self.version {
+			(self.version, self.cached) = self.shared.load();
+		}
+		&self.cached
+	}
+
+	/// Returns the snapshot returned by the last call to [`Self::load`], without checking for a
+	/// newer one.
+	pub fn cached(&self) -> &Arc<HistorySnapshot> {
+		&self.cached
+	}
+}
@@ -0,0 +1,249 @@
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TryRecvError};
+
+use crate::{Action, HistoryStorage, Operation, UndoRedo};
+
+/// Identifies an action committed through an [`ActionStage`].
+///
+/// IDs are assigned in the order actions are committed, starting from 0, and are never reused by
+/// the same stage.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ActionId(u64);
+
+impl ActionId {
+	/// Returns this ID as a number.
+	pub fn get(self) -> u64 {
+		self.0
+	}
+}
+
+/// A bounded queue of actions built on other threads, waiting to be committed to a history by the
+/// thread that owns it.
+///
+/// Worker threads submit actions through [`ActionSubmitter`]s, and the owner thread commits them
+/// with [`Self::commit_pending`], in the order they were submitted.
+#[derive(Debug)]
+pub struct ActionStage<Op> {
+	submitter: ActionSubmitter<Op>,
+	pending: Receiver<Staged<Op>>,
+	/// How many actions have been received from `pending`, wrapping on overflow.
+	received: usize,
+	next_id: u64,
+}
+
+#[derive(Debug)]
+struct Staged<Op> {
+	action: Action<Op>,
+	reply: SyncSender<ActionId>,
+}
+
+impl<Op> ActionStage<Op> {
+	/// Creates a stage which holds up to `capacity` actions waiting to be committed. Once it is
+	/// full, submitting an action blocks until the owner commits the pending actions.
+	pub fn new(capacity: usize) -> Self {
+		let (sender, pending) = mpsc::sync_channel(capacity);
+		Self {
+			submitter: ActionSubmitter {
+				sender,
+				submitted: Arc::default(),
+			},
+			pending,
+			received: 0,
+			next_id: 0,
+		}
+	}
+
+	/// Returns a new submitter for this stage, which can be sent to another thread.
+	pub fn submitter(&self) -> ActionSubmitter<Op> {
+		self.submitter.clone()
+	}
+
+	/// Commits every pending action to `history` (see [`UndoRedo::commit_action`]), applying each
+	/// to `target` in the order they were submitted. Returns how many actions were committed.
+	///
+	/// Only actions which were submitted before this is called are committed, so this returns even
+	/// while other threads keep submitting actions. Those are left for the next call.
+	///
+	/// # Panics
+	/// See [`UndoRedo::commit_action`].
+	pub fn commit_pending<For, S>(
+		&mut self,
+		history: &mut UndoRedo<Op, S>,
+		target: &mut For,
+	) -> usize
+	where
+		Op: Operation<For>,
+		S: HistoryStorage<Op>,
+	{
+		let submitted = self.submitter.submitted.load(Ordering::Acquire);
+		let pending = submitted.wrapping_sub(self.received);
+		for committed in 0..pending {
+			let staged = match self.pending.try_recv() {
+				Ok(staged) => staged,
+				// The stage holds a sender of its own, so it can never be disconnected.
+				Err(TryRecvError::Empty | TryRecvError::Disconnected) => return committed,
+			};
+			self.received = self.received.wrapping_add(1);
+			history.commit_action(staged.action, target);
+
+			let id = ActionId(self.next_id);
+			self.next_id += 1;
+			// The submitter may not be waiting for the ID, in which case nobody needs it.
+			let _ = staged.reply.try_send(id);
+		}
+		pending
+	}
+}
+
+/// Submits actions to an [`ActionStage`]. Cloning this creates another submitter for the same
+/// stage.
+#[derive(Debug)]
+pub struct ActionSubmitter<Op> {
+	sender: SyncSender<Staged<Op>>,
+	/// How many actions have been submitted to the stage, wrapping on overflow. This is only
+	/// increased once an action has been sent, so it never counts actions the stage can't receive
+	/// yet.
+	submitted: Arc<AtomicUsize>,
+}
+
+// A new submitter for the same stage can be created regardless of what operations it holds. As
+// the `Clone` derive macro assumes that we want a trait bound on `Op` no matter what, we have to
+// manually implement `Clone`.
+impl<Op> Clone for ActionSubmitter<Op> {
+	fn clone(&self) -> Self {
+		Self {
+			sender: self.sender.clone(),
+			submitted: Arc::clone(&self.submitted),
+		}
+	}
+}
+
+impl<Op> ActionSubmitter<Op> {
+	/// Submits `action` to be committed, blocking while the stage is full. The returned ticket can
+	/// be used to find out the action's ID once it has been committed.
+	///
+	/// # Errors
+	/// Returns `action` back if the stage has been dropped.
+	pub fn submit(&self, action: Action<Op>) -> Result<ActionTicket, Action<Op>> {
+		let (reply, receiver) = mpsc::sync_channel(1);
+		self.sender
+			.send(Staged { action, reply })
+			.map_err(|error| error.0.action)?;
+		self.submitted.fetch_add(1, Ordering::Release);
+		Ok(ActionTicket { receiver })
+	}
+}
+
+/// Reports the [`ActionId`] of an action submitted with [`ActionSubmitter::submit`].
+#[derive(Debug)]
+pub struct ActionTicket {
+	receiver: Receiver<ActionId>,
+}
+
+impl ActionTicket {
+	/// Blocks until the action has been committed, then returns its ID.
+	///
+	/// Returns `None` if the stage was dropped before the action was committed.
+	pub fn wait(self) -> Option<ActionId> {
+		self.receiver.recv().ok()
+	}
+
+	/// Returns the action's ID if it has been committed, without blocking.
+	pub fn try_get(&self) -> Option<ActionId> {
+		self.receiver.try_recv().ok()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use alloc::{string::ToString, vec::Vec};
+	use std::thread;
+
+	use super::*;
+
+	#[derive(Debug)]
+	struct Add(i32);
+
+	impl Operation<i32> for Add {
+		fn apply(&self, item: &mut i32) {
+			*item += self.0;
+		}
+	}
+
+	fn add(value: i32) -> Action<Add> {
+		let mut action = Action::default();
+		action
+			.set_name(value)
+			.add_redo_operation(Add(value))
+			.add_undo_operation(Add(-value));
+		action
+	}
+
+	#[test]
+	fn ids_follow_commit_order() {
+		let mut stage = ActionStage::new(16);
+		let mut history = UndoRedo::new();
+		let mut target = 0;
+
+		let tickets: Vec<_> = thread::scope(|scope| {
+			let workers: Vec<_> = (0..4)
+				.map(|worker| {
+					let submitter = stage.submitter();
+					scope.spawn(move || {
+						(0..4)
+							.map(|i| {
+								let value = worker * 4 + i;
+								(value, submitter.submit(add(value)).unwrap())
+							})
+							.collect::<Vec<_>>()
+					})
+				})
+				.collect();
+			workers
+				.into_iter()
+				.flat_map(|worker| worker.join().unwrap())
+				.collect()
+		});
+		assert_eq!(stage.commit_pending(&mut history, &mut target), 16);
+		assert_eq!(target, (0..16).sum());
+
+		let mut ids = Vec::new();
+		for (value, ticket) in tickets {
+			let id = ticket.try_get().unwrap();
+			// Each action was committed at the position matching its ID.
+			let action = history.actions.get(id.get() as usize).unwrap();
+			assert_eq!(action.get_name(), Some(value.to_string().as_str()));
+			ids.push(id.get());
+		}
+		ids.sort_unstable();
+		assert_eq!(ids, Vec::from_iter(0..16));
+	}
+
+	#[test]
+	fn commits_only_actions_pending_at_start() {
+		let mut stage = ActionStage::new(2);
+		let mut history = UndoRedo::new();
+		let mut target = 0;
+		let submitter = stage.submitter();
+		let first = submitter.submit(add(1)).unwrap();
+		submitter.submit(add(2)).unwrap();
+
+		thread::scope(|scope| {
+			// Blocks until committing frees up room in the stage.
+			let late = scope.spawn(|| submitter.submit(add(3)).unwrap());
+			assert_eq!(stage.commit_pending(&mut history, &mut target), 2);
+			assert_eq!(target, 3);
+			let late = late.join().unwrap();
+
+			assert_eq!(stage.commit_pending(&mut history, &mut target), 1);
+			assert_eq!(late.wait(), Some(ActionId(2)));
+		});
+		assert_eq!(first.wait(), Some(ActionId(0)));
+		assert_eq!(stage.commit_pending(&mut history, &mut target), 0);
+		assert_eq!(target, 6);
+
+		drop(stage);
+		assert!(submitter.submit(add(4)).is_err());
+	}
+}
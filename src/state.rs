@@ -0,0 +1,273 @@
+use alloc::string::ToString;
+
+use crate::{Action, HistoryStorage, Operation, UndoRedo};
+
+/// An object whose state (or some designated part of it) can be captured and later restored.
+///
+/// This allows undo-redo for objects small enough that storing their state before and after each
+/// change is simpler than writing operations to revert it. See [`UndoRedo::commit_snapshot`].
+pub trait Snapshot {
+	/// A captured copy of the state. For a `Clone` type, this is usually `Self`.
+	type State;
+
+	/// Returns a copy of the current state.
+	fn capture(&self) -> Self::State;
+
+	/// Replaces the current state with `state`.
+	fn restore(&mut self, state: &Self::State);
+}
+
+/// An operation which [restores](Snapshot::restore) a captured state.
+///
+/// This can be used as a history's operation type directly, or be one of many kinds of operation
+/// (through `From<Restore<State>>`), so that snapshots can be mixed with ordinary operations.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Restore<State>(pub State);
+
+impl<For: Snapshot> Operation<For> for Restore<For::State> {
+	fn apply(&self, item: &mut For) {
+		item.restore(&self.0);
+	}
+}
+
+/// An object which can compute the differences between two of its states, as a patch which can
+/// be applied to turn one into the other.
+///
+/// This allows undo-redo for objects which can't describe their changes as operations, but can
+/// compare their states. See [`UndoRedo::record_diff`].
+pub trait Diffable {
+	/// A description of the differences between two states.
+	type Patch;
+
+	/// Returns a patch which turns `self` into `other`.
+	fn diff(&self, other: &Self) -> Self::Patch;
+
+	/// Applies `patch` to `self`.
+	fn apply_patch(&mut self, patch: &Self::Patch);
+}
+
+/// An operation which [applies a patch](Diffable::apply_patch).
+///
+/// Like [`Restore`], this can be used as a history's operation type directly, or be one of many
+/// kinds of operation (through `From<ApplyPatch<Patch>>`).
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ApplyPatch<Patch>(pub Patch);
+
+impl<For: Diffable> Operation<For> for ApplyPatch<For::Patch> {
+	fn apply(&self, item: &mut For) {
+		item.apply_patch(&self.0);
+	}
+}
+
+/// An operation which can be recorded by observing `For` before and after it is changed, rather
+/// than being built by hand. See [`UndoRedo::record_with`].
+///
+/// This is implemented for [`Restore`] and [`ApplyPatch`]. Implement it for other operation types
+/// to capture only the relevant state, or to mix several kinds of operation.
+pub trait RecordOperation<For>: Sized {
+	/// What is captured from `For` before it is changed.
+	type Memo;
+
+	/// Captures what is needed from `item` before it is changed.
+	fn before(item: &For) -> Self::Memo;
+
+	/// Given what was captured before `item` was changed, returns operations which redo and undo
+	/// the change, as `(redo, undo)`.
+	fn after(memo: Self::Memo, item: &For) -> (Self, Self);
+}
+
+impl<For: Snapshot> RecordOperation<For> for Restore<For::State> {
+	type Memo = For::State;
+
+	fn before(item: &For) -> Self::Memo {
+		item.capture()
+	}
+
+	fn after(memo: Self::Memo, item: &For) -> (Self, Self) {
+		(Restore(item.capture()), Restore(memo))
+	}
+}
+
+impl<For: Diffable + Clone> RecordOperation<For> for ApplyPatch<For::Patch> {
+	type Memo = For;
+
+	fn before(item: &For) -> Self::Memo {
+		item.clone()
+	}
+
+	fn after(memo: Self::Memo, item: &For) -> (Self, Self) {
+		(ApplyPatch(memo.diff(item)), ApplyPatch(item.diff(&memo)))
+	}
+}
+
+impl<Op, S: HistoryStorage<Op>> UndoRedo<Op, S> {
+	/// Calls `change` to change `apply_to`, then adds an action named `name` at the current point
+	/// in history, which redoes or undoes the change, as [`Self::create_action`] would. The action
+	/// is built by observing `apply_to` before and after the change (see [`RecordOperation`]).
+	///
+	/// The action is returned so that more can be done with it.
+	///
+	/// # Panics
+	/// See [`Self::create_action`].
+	pub fn record_with<For>(
+		&mut self,
+		apply_to: &mut For,
+		name: impl ToString,
+		change: impl FnOnce(&mut For),
+	) -> &mut Action<Op>
+	where
+		Op: RecordOperation<For>,
+	{
+		let memo = Op::before(apply_to);
+		change(apply_to);
+		let (redo, undo) = Op::after(memo, apply_to);
+
+		let mut action = Action::default();
+		action.set_name(name);
+		action.add_redo_operation(redo);
+		action.add_undo_operation(undo);
+		self.push_applied(action)
+	}
+
+	/// Calls `change` to change `apply_to`, then adds an action at the current point in history
+	/// which restores the state from before or after the change, as [`Self::create_action`]
+	/// would.
+	///
+	/// The action is returned so that it can be named.
+	///
+	/// # Panics
+	/// See [`Self::create_action`].
+	pub fn commit_snapshot<For>(
+		&mut self,
+		apply_to: &mut For,
+		change: impl FnOnce(&mut For),
+	) -> &mut Action<Op>
+	where
+		For: Snapshot,
+		Op: From<Restore<For::State>>,
+	{
+		let before = apply_to.capture();
+		change(apply_to);
+		let after = apply_to.capture();
+
+		let mut action = Action::default();
+		action.add_redo_operation(Restore(after).into());
+		action.add_undo_operation(Restore(before).into());
+
+		self.push_applied(action)
+	}
+
+	/// Calls `change` to change `apply_to`, then adds an action at the current point in history
+	/// which applies the differences between its states before and after the change (or the
+	/// reverse of them), as [`Self::create_action`] would.
+	///
+	/// The action is returned so that it can be named.
+	///
+	/// # Panics
+	/// See [`Self::create_action`].
+	pub fn record_diff<For>(
+		&mut self,
+		apply_to: &mut For,
+		change: impl FnOnce(&mut For),
+	) -> &mut Action<Op>
+	where
+		For: Diffable + Clone,
+		Op: From<ApplyPatch<For::Patch>>,
+	{
+		let before = apply_to.clone();
+		change(apply_to);
+
+		let mut action = Action::default();
+		action.add_redo_operation(ApplyPatch(before.diff(apply_to)).into());
+		action.add_undo_operation(ApplyPatch(apply_to.diff(&before)).into());
+		self.push_applied(action)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A counter, captured as its value, and patched by adding to it.
+	#[derive(Clone, Debug, PartialEq)]
+	struct Counter(i32);
+
+	impl Snapshot for Counter {
+		type State = i32;
+
+		fn capture(&self) -> i32 {
+			self.0
+		}
+
+		fn restore(&mut self, state: &i32) {
+			self.0 = *state;
+		}
+	}
+
+	impl Diffable for Counter {
+		type Patch = i32;
+
+		fn diff(&self, other: &Self) -> i32 {
+			other.0 - self.0
+		}
+
+		fn apply_patch(&mut self, patch: &i32) {
+			self.0 += patch;
+		}
+	}
+
+	#[test]
+	fn snapshots_restore_states() {
+		let mut history = UndoRedo::<Restore<i32>>::new();
+		let mut counter = Counter(1);
+
+		history.commit_snapshot(&mut counter, |counter| counter.0 = 5);
+		history.commit_snapshot(&mut counter, |counter| counter.0 *= 2);
+		assert_eq!(counter, Counter(10));
+		history.undo(&mut counter).unwrap();
+		assert_eq!(counter, Counter(5));
+		history.undo(&mut counter).unwrap();
+		assert_eq!(counter, Counter(1));
+		history.jump_to(2, &mut counter).unwrap();
+		assert_eq!(counter, Counter(10));
+	}
+
+	#[test]
+	fn diffs_apply_changes() {
+		let mut history = UndoRedo::<ApplyPatch<i32>>::new();
+		let mut counter = Counter(1);
+
+		let action = history.record_diff(&mut counter, |counter| counter.0 = 5);
+		assert_eq!(action.apply_ops(), [ApplyPatch(4)]);
+		assert_eq!(action.revert_ops(), [ApplyPatch(-4)]);
+		history.record_diff(&mut counter, |counter| counter.0 *= 2);
+		assert_eq!(counter, Counter(10));
+
+		// Patches apply on top of other changes, unlike snapshots.
+		counter.0 += 100;
+		history.undo(&mut counter).unwrap();
+		history.undo(&mut counter).unwrap();
+		assert_eq!(counter, Counter(101));
+	}
+
+	#[test]
+	fn records_named_changes() {
+		let mut snapshots = UndoRedo::<Restore<i32>>::new();
+		let mut patches = UndoRedo::<ApplyPatch<i32>>::new();
+		let mut counter = Counter(1);
+
+		let action = snapshots.record_with(&mut counter, "Set", |counter| counter.0 = 5);
+		assert_eq!(action.get_name(), Some("Set"));
+		assert_eq!(action.apply_ops(), [Restore(5)]);
+		assert_eq!(action.revert_ops(), [Restore(1)]);
+
+		let action = patches.record_with(&mut counter, "Double", |counter| counter.0 *= 2);
+		assert_eq!(action.get_name(), Some("Double"));
+		assert_eq!(action.apply_ops(), [ApplyPatch(5)]);
+
+		patches.undo(&mut counter).unwrap();
+		assert_eq!(counter, Counter(5));
+		snapshots.undo(&mut counter).unwrap();
+		assert_eq!(counter, Counter(1));
+	}
+}
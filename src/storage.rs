@@ -0,0 +1,346 @@
+use alloc::{collections::VecDeque, sync::Arc, vec::Vec};
+
+use crate::Action;
+
+/// A list of [`Action`]s, used by [`UndoRedo`](crate::UndoRedo) to store its history.
+///
+/// Actions are only ever added to the end of the list, and removed from either end of it.
+pub trait HistoryStorage<Op> {
+	/// Returns the number of actions stored.
+	fn len(&self) -> usize;
+
+	/// Returns `true` if no actions are stored.
+	fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Returns the action at `index`, or `None` if `index` is out of bounds.
+	fn get(&self, index: usize) -> Option<&Action<Op>>;
+
+	/// Returns the action at `index` mutably, or `None` if `index` is out of bounds.
+	///
+	/// Storages that share actions with other storages may also return `None` if the action is
+	/// currently shared. However, the most recently pushed action must always be available.
+	fn get_mut(&mut self, index: usize) -> Option<&mut Action<Op>>;
+
+	/// Adds an action to the end of the list.
+	///
+	/// If there is no room for the action, storages may make room by removing actions from the
+	/// beginning of the list. Returns the number of actions that were removed this way.
+	fn push(&mut self, action: Action<Op>) -> usize;
+
+	/// Returns `true` if there is no room for another action, such that [`Self::push`] would need
+	/// to remove an action to make room.
+	fn is_full(&self) -> bool {
+		false
+	}
+
+	/// Removes every action from index `len` onwards. If there are `len` or fewer actions stored,
+	/// this does nothing.
+	fn truncate(&mut self, len: usize);
+
+	/// Removes up to `count` actions from the beginning of the list, returning how many were
+	/// removed.
+	fn evict_oldest(&mut self, count: usize) -> usize;
+
+	/// Removes every action.
+	fn clear(&mut self) {
+		self.truncate(0);
+	}
+
+	/// Returns the number of bytes of heap memory this storage has allocated to hold its
+	/// actions. This includes the actions themselves, but not any memory they own.
+	fn heap_size(&self) -> usize {
+		self.len() * size_of::<Action<Op>>()
+	}
+
+	/// Releases any memory this storage holds but doesn't need.
+	fn shrink_to_fit(&mut self) {}
+}
+
+impl<Op> HistoryStorage<Op> for Vec<Action<Op>> {
+	fn len(&self) -> usize {
+		self.len()
+	}
+
+	fn get(&self, index: usize) -> Option<&Action<Op>> {
+		self.as_slice().get(index)
+	}
+
+	fn get_mut(&mut self, index: usize) -> Option<&mut Action<Op>> {
+		self.as_mut_slice().get_mut(index)
+	}
+
+	fn push(&mut self, action: Action<Op>) -> usize {
+		self.push(action);
+		0
+	}
+
+	fn truncate(&mut self, len: usize) {
+		self.truncate(len);
+	}
+
+	fn evict_oldest(&mut self, count: usize) -> usize {
+		let count = count.min(self.len());
+		self.drain(..count);
+		count
+	}
+
+	fn clear(&mut self) {
+		self.clear();
+	}
+
+	fn heap_size(&self) -> usize {
+		self.capacity() * size_of::<Action<Op>>()
+	}
+
+	fn shrink_to_fit(&mut self) {
+		self.shrink_to_fit();
+	}
+}
+
+/// A [`HistoryStorage`] which holds up to a fixed number of actions, evicting the oldest action
+/// when a new one is added past that limit.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct BoundedStorage<Op> {
+	actions: VecDeque<Action<Op>>,
+	max_len: usize,
+}
+
+impl<Op> BoundedStorage<Op> {
+	/// Creates an empty storage which holds up to `max_len` actions. `max_len` is raised to 1 if
+	/// it is 0, as a history must be able to hold the action being created.
+	pub const fn new(max_len: usize) -> Self {
+		Self {
+			actions: VecDeque::new(),
+			max_len: if max_len == 0 { 1 } else { max_len },
+		}
+	}
+
+	/// Returns the maximum number of actions this storage will hold.
+	pub fn max_len(&self) -> usize {
+		self.max_len
+	}
+}
+
+impl<Op> HistoryStorage<Op> for BoundedStorage<Op> {
+	fn len(&self) -> usize {
+		self.actions.len()
+	}
+
+	fn get(&self, index: usize) -> Option<&Action<Op>> {
+		self.actions.get(index)
+	}
+
+	fn get_mut(&mut self, index: usize) -> Option<&mut Action<Op>> {
+		self.actions.get_mut(index)
+	}
+
+	fn push(&mut self, action: Action<Op>) -> usize {
+		let evicted = self.evict_oldest((self.actions.len() + 1).saturating_sub(self.max_len));
+		self.actions.push_back(action);
+		evicted
+	}
+
+	fn is_full(&self) -> bool {
+		self.actions.len() >= self.max_len
+	}
+
+	fn truncate(&mut self, len: usize) {
+		self.actions.truncate(len);
+	}
+
+	fn evict_oldest(&mut self, count: usize) -> usize {
+		let count = count.min(self.actions.len());
+		self.actions.drain(..count);
+		count
+	}
+
+	fn clear(&mut self) {
+		self.actions.clear();
+	}
+
+	fn heap_size(&self) -> usize {
+		self.actions.capacity() * size_of::<Action<Op>>()
+	}
+
+	fn shrink_to_fit(&mut self) {
+		self.actions.shrink_to_fit();
+	}
+}
+
+/// A [`HistoryStorage`] which holds up to `N` actions in a fixed-size array, evicting the oldest
+/// action when a new one is added past that limit.
+///
+/// Unlike the other storages, this never allocates memory. Note that actions may still allocate
+/// memory to hold their operations, unless the `inline-ops` feature is enabled and each action has
+/// at most one operation in each direction.
+///
+/// Use [`UndoRedo::try_create_action`](crate::UndoRedo::try_create_action) to be told when this is
+/// full, rather than evicting actions.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ArrayStorage<Op, const N: usize> {
+	/// The actions, treated as a ring buffer beginning at `self.head`.
+	slots: [Option<Action<Op>>; N],
+	head: usize,
+	len: usize,
+}
+
+impl<Op, const N: usize> ArrayStorage<Op, N> {
+	/// Creates an empty storage.
+	///
+	/// Using this with an `N` of 0 is a compile-time error, as a history must be able to hold the
+	/// action being created.
+	pub const fn new() -> Self {
+		const {
+			assert!(
+				N > 0,
+				"an `ArrayStorage` must be able to hold at least one action"
+			);
+		}
+		Self {
+			slots: [const { None }; N],
+			head: 0,
+			len: 0,
+		}
+	}
+
+	/// Returns the position within `self.slots` of the action at `index`.
+	fn slot(&self, index: usize) -> Option<usize> {
+		(index < self.len).then(|| (self.head + index) % N)
+	}
+}
+
+// An empty storage does not contain any `Op`. As the `Default` derive macro assumes that we want a
+// trait bound on `Op` no matter what, we have to manually implement `Default`.
+impl<Op, const N: usize> Default for ArrayStorage<Op, N> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<Op, const N: usize> HistoryStorage<Op> for ArrayStorage<Op, N> {
+	fn len(&self) -> usize {
+		self.len
+	}
+
+	fn get(&self, index: usize) -> Option<&Action<Op>> {
+		self.slots[self.slot(index)?].as_ref()
+	}
+
+	fn get_mut(&mut self, index: usize) -> Option<&mut Action<Op>> {
+		self.slots[self.slot(index)?].as_mut()
+	}
+
+	fn push(&mut self, action: Action<Op>) -> usize {
+		let evicted = self.evict_oldest((self.len + 1).saturating_sub(N));
+		self.slots[(self.head + self.len) % N] = Some(action);
+		self.len += 1;
+		evicted
+	}
+
+	fn is_full(&self) -> bool {
+		self.len == N
+	}
+
+	fn truncate(&mut self, len: usize) {
+		while self.len > len {
+			self.len -= 1;
+			self.slots[(self.head + self.len) % N] = None;
+		}
+	}
+
+	fn evict_oldest(&mut self, count: usize) -> usize {
+		let count = count.min(self.len);
+		for _ in 0..count {
+			self.slots[self.head] = None;
+			self.head = (self.head + 1) % N;
+			self.len -= 1;
+		}
+		count
+	}
+
+	fn heap_size(&self) -> usize {
+		0
+	}
+}
+
+/// A [`HistoryStorage`] which keeps each action behind an [`Arc`], so that cloning it (and thus
+/// cloning an [`UndoRedo`](crate::UndoRedo) using it) only copies pointers, rather than every
+/// operation.
+///
+/// Actions become shared between clones, and so can no longer be mutated once the storage is
+/// cloned.
+#[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ArcStorage<Op> {
+	actions: Vec<Arc<Action<Op>>>,
+}
+
+// `Arc` can be cloned regardless of what it holds, but the `Clone` and `Default` derive macros
+// assume that we want a trait bound on `Op` no matter what, so we have to implement them manually.
+impl<Op> Clone for ArcStorage<Op> {
+	fn clone(&self) -> Self {
+		Self {
+			actions: self.actions.clone(),
+		}
+	}
+}
+
+impl<Op> Default for ArcStorage<Op> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<Op> ArcStorage<Op> {
+	/// Creates an empty storage.
+	pub const fn new() -> Self {
+		Self {
+			actions: Vec::new(),
+		}
+	}
+}
+
+impl<Op> HistoryStorage<Op> for ArcStorage<Op> {
+	fn len(&self) -> usize {
+		self.actions.len()
+	}
+
+	fn get(&self, index: usize) -> Option<&Action<Op>> {
+		self.actions.get(index).map(|action| &**action)
+	}
+
+	fn get_mut(&mut self, index: usize) -> Option<&mut Action<Op>> {
+		self.actions.get_mut(index).and_then(Arc::get_mut)
+	}
+
+	fn push(&mut self, action: Action<Op>) -> usize {
+		self.actions.push(Arc::new(action));
+		0
+	}
+
+	fn truncate(&mut self, len: usize) {
+		self.actions.truncate(len);
+	}
+
+	fn evict_oldest(&mut self, count: usize) -> usize {
+		let count = count.min(self.actions.len());
+		self.actions.drain(..count);
+		count
+	}
+
+	fn clear(&mut self) {
+		self.actions.clear();
+	}
+
+	fn heap_size(&self) -> usize {
+		// Each `Arc` points to an allocation holding its strong and weak counts, followed by the
+		// action.
+		let shared_size = 2 * size_of::<usize>() + size_of::<Action<Op>>();
+		self.actions.capacity() * size_of::<Arc<Action<Op>>>() + self.actions.len() * shared_size
+	}
+
+	fn shrink_to_fit(&mut self) {
+		self.actions.shrink_to_fit();
+	}
+}
@@ -0,0 +1,154 @@
+use alloc::{boxed::Box, vec::Vec};
+use core::fmt;
+
+use crate::{Action, HistoryStorage, InvertibleOperation, Operation, UndoRedo, UndoRedoError};
+
+/// An object together with the history of operations on it, which records every operation
+/// dispatched to it, and notifies subscribers whenever the object changes.
+///
+/// This suits small applications, which would otherwise keep the object, its history, and change
+/// notifications as separate pieces.
+pub struct Store<State, Op, S = Vec<Action<Op>>> {
+	state: State,
+	history: UndoRedo<Op, S>,
+	subscribers: Vec<Subscriber<State>>,
+}
+
+/// A callback registered with [`Store::subscribe`].
+type Subscriber<State> = Box<dyn FnMut(&State)>;
+
+// Subscribers are closures, which can't be formatted. As the `Debug` derive macro assumes that
+// every field can be, we have to manually implement `Debug`.
+impl<State: fmt::Debug, Op: fmt::Debug, S: fmt::Debug> fmt::Debug for Store<State, Op, S> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Store")
+			.field("state", &self.state)
+			.field("history", &self.history)
+			.field("subscribers", &self.subscribers.len())
+			.finish()
+	}
+}
+
+impl<State, Op> Store<State, Op> {
+	/// Creates a store holding `state`, with an empty history.
+	pub fn new(state: State) -> Self {
+		Self::with_history(state, UndoRedo::new())
+	}
+}
+
+impl<State, Op, S> Store<State, Op, S> {
+	/// Creates a store holding `state` and `history`. `state` must be in the state it would be in
+	/// at the current position in history.
+	pub fn with_history(state: State, history: UndoRedo<Op, S>) -> Self {
+		Self {
+			state,
+			history,
+			subscribers: Vec::new(),
+		}
+	}
+
+	/// Returns the object.
+	pub fn state(&self) -> &State {
+		&self.state
+	}
+
+	/// Returns the history.
+	pub fn history(&self) -> &UndoRedo<Op, S> {
+		&self.history
+	}
+
+	/// Returns the object and history.
+	pub fn into_parts(self) -> (State, UndoRedo<Op, S>) {
+		(self.state, self.history)
+	}
+
+	/// Registers `subscriber` to be called with the object whenever it is changed by this store.
+	pub fn subscribe(&mut self, subscriber: impl FnMut(&State) + 'static) {
+		self.subscribers.push(Box::new(subscriber));
+	}
+
+	/// Calls every subscriber with the object.
+	fn notify(&mut self) {
+		for subscriber in &mut self.subscribers {
+			subscriber(&self.state);
+		}
+	}
+}
+
+impl<State, Op, S: HistoryStorage<Op>> Store<State, Op, S> {
+	/// Applies `operation` to the object, and records it in the history as an action which also
+	/// holds its inverse (see [`UndoRedo::commit_invertible`]).
+	///
+	/// The action is returned so that it can be named.
+	///
+	/// # Panics
+	/// See [`UndoRedo::create_action`].
+	pub fn dispatch(&mut self, operation: Op) -> &mut Action<Op>
+	where
+		Op: InvertibleOperation<State>,
+	{
+		self.history.commit_invertible(operation, &mut self.state);
+		self.notify();
+		self.newest_applied()
+	}
+
+	/// Performs [`UndoRedo::commit_action`] on the object, for changes that need more than one
+	/// operation, or whose operations are not invertible.
+	///
+	/// # Panics
+	/// See [`UndoRedo::create_action`].
+	pub fn dispatch_action(&mut self, action: Action<Op>)
+	where
+		Op: Operation<State>,
+	{
+		self.history.commit_action(action, &mut self.state);
+		self.notify();
+	}
+
+	/// Performs [`UndoRedo::undo`] on the object.
+	///
+	/// # Errors
+	/// See [`UndoRedo::undo`].
+	pub fn undo(&mut self) -> Result<(), UndoRedoError>
+	where
+		Op: Operation<State>,
+	{
+		self.history.undo(&mut self.state)?;
+		self.notify();
+		Ok(())
+	}
+
+	/// Performs [`UndoRedo::redo`] on the object.
+	///
+	/// # Errors
+	/// See [`UndoRedo::redo`].
+	pub fn redo(&mut self) -> Result<(), UndoRedoError>
+	where
+		Op: Operation<State>,
+	{
+		self.history.redo(&mut self.state)?;
+		self.notify();
+		Ok(())
+	}
+
+	/// Performs [`UndoRedo::jump_to`] on the object.
+	///
+	/// # Errors
+	/// See [`UndoRedo::jump_to`].
+	pub fn jump_to(&mut self, position: usize) -> Result<(), UndoRedoError>
+	where
+		Op: Operation<State>,
+	{
+		self.history.jump_to(position, &mut self.state)?;
+		self.notify();
+		Ok(())
+	}
+
+	/// Returns the action most recently added to the history.
+	fn newest_applied(&mut self) -> &mut Action<Op> {
+		self.history
+			.actions
+			.get_mut(self.history.tapehead - 1)
+			.expect("the most recently pushed action should always be available")
+	}
+}
@@ -0,0 +1,599 @@
+use alloc::{collections::BTreeMap, vec::Vec};
+#[cfg(feature = "std")]
+use core::hash::{BuildHasher, Hash};
+use core::{error, fmt};
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+use crate::{HistoryStorage, InvertibleOperation, Operation, UndoRedo, UndoRedoError};
+
+/// A collection of objects which operations can be applied to, each identified by an `Id`.
+///
+/// This lets a history operate on a collection (such as the entities of a scene) without each
+/// operation having to find its own target within it. See [`OnTarget`].
+pub trait TargetResolver<Id> {
+	/// The type of the objects in the collection.
+	type Target;
+
+	/// Returns the object identified by `id`, or `None` if there is no such object.
+	fn resolve(&mut self, id: &Id) -> Option<&mut Self::Target>;
+}
+
+impl<T> TargetResolver<usize> for Vec<T> {
+	type Target = T;
+
+	fn resolve(&mut self, id: &usize) -> Option<&mut T> {
+		self.get_mut(*id)
+	}
+}
+
+impl<K: Ord, V> TargetResolver<K> for BTreeMap<K, V> {
+	type Target = V;
+
+	fn resolve(&mut self, id: &K) -> Option<&mut V> {
+		self.get_mut(id)
+	}
+}
+
+#[cfg(feature = "std")]
+impl<K: Eq + Hash, V, S: BuildHasher> TargetResolver<K> for HashMap<K, V, S> {
+	type Target = V;
+
+	fn resolve(&mut self, id: &K) -> Option<&mut V> {
+		self.get_mut(id)
+	}
+}
+
+/// An operation on one object within a collection, identified by `target`.
+///
+/// This is an [`Operation`] on any [`TargetResolver`] whose objects `op` can be applied to. If
+/// `target` cannot be resolved when this is applied, nothing happens.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct OnTarget<Id, Op> {
+	/// Identifies the object to apply `op` to.
+	pub target: Id,
+	/// The operation to apply.
+	pub op: Op,
+}
+
+impl<Id, Op> OnTarget<Id, Op> {
+	/// Creates an operation which applies `op` to the object identified by `target`.
+	pub const fn new(target: Id, op: Op) -> Self {
+		Self { target, op }
+	}
+}
+
+impl<Id, Op, R> Operation<R> for OnTarget<Id, Op>
+where
+	R: TargetResolver<Id>,
+	Op: Operation<R::Target>,
+{
+	fn apply(&self, item: &mut R) {
+		if let Some(target) = item.resolve(&self.target) {
+			self.op.apply(target);
+		}
+	}
+}
+
+/// An operation on `Inner`, applied to the `Inner` within an `Outer` found by a projection
+/// function (a lens).
+///
+/// This lets operations written for one type (such as a text field) be used on any type containing
+/// it. See also [`UndoRedo::contramap_target`].
+pub struct Lens<Op, Outer, Inner> {
+	pub(crate) op: Op,
+	pub(crate) project: fn(&mut Outer) -> &mut Inner,
+}
+
+impl<Op, Outer, Inner> Lens<Op, Outer, Inner> {
+	/// Creates an operation which applies `op` to the `Inner` returned by `project`.
+	pub const fn new(op: Op, project: fn(&mut Outer) -> &mut Inner) -> Self {
+		Self { op, project }
+	}
+
+	/// Returns the operation applied to the `Inner`.
+	pub fn op(&self) -> &Op {
+		&self.op
+	}
+
+	/// Returns the operation applied to the `Inner`, discarding the projection.
+	pub fn into_op(self) -> Op {
+		self.op
+	}
+}
+
+// `Outer` and `Inner` are only used by the projection function. As the `Clone`, `Copy`, and `Debug`
+// derive macros assume that we want trait bounds on them no matter what, we have to manually
+// implement those traits.
+impl<Op: Clone, Outer, Inner> Clone for Lens<Op, Outer, Inner> {
+	fn clone(&self) -> Self {
+		Self {
+			op: self.op.clone(),
+			project: self.project,
+		}
+	}
+}
+
+impl<Op: Copy, Outer, Inner> Copy for Lens<Op, Outer, Inner> {}
+
+impl<Op: fmt::Debug, Outer, Inner> fmt::Debug for Lens<Op, Outer, Inner> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Lens")
+			.field("op", &self.op)
+			.finish_non_exhaustive()
+	}
+}
+
+impl<Op: Operation<Inner>, Outer, Inner> Operation<Outer> for Lens<Op, Outer, Inner> {
+	fn apply(&self, item: &mut Outer) {
+		self.op.apply((self.project)(item));
+	}
+}
+
+impl<Op> UndoRedo<Op> {
+	/// Converts this history into one which operates on the `Inner` within an `Outer` found by
+	/// `project`, by wrapping each operation in a [`Lens`]. The position in history and the save
+	/// point are kept.
+	pub fn contramap_target<Outer, Inner>(
+		self,
+		project: fn(&mut Outer) -> &mut Inner,
+	) -> UndoRedo<Lens<Op, Outer, Inner>> {
+		let actions = self
+			.actions
+			.into_iter()
+			.map(|action| action.map_ops(|op| Lens::new(op, project)))
+			.collect();
+		let mut history = UndoRedo::with_empty_storage(actions);
+		history.tapehead = self.tapehead;
+		history.save_point = self.save_point;
+		history
+	}
+}
+
+impl<Id, Op, S: HistoryStorage<OnTarget<Id, Op>>> UndoRedo<OnTarget<Id, Op>, S> {
+	/// Like [`Self::redo`], but first checks that every operation's target can be resolved in
+	/// `registry`, so that actions whose targets no longer exist are reported rather than partly
+	/// applied.
+	///
+	/// # Errors
+	/// * Returns `ResolveError::Missing` with the first target that cannot be resolved. Nothing is
+	///   redone in this case.
+	/// * Returns `ResolveError::History` with the errors of [`Self::redo`].
+	///
+	/// # Panics
+	/// See [`Self::redo`].
+	pub fn redo_resolved<R>(&mut self, registry: &mut R) -> Result<(), ResolveError<Id>>
+	where
+		R: TargetResolver<Id>,
+		Op: Operation<R::Target>,
+		Id: Clone,
+	{
+		if let Some(action) = self.redo_action() {
+			check_targets(action.apply_ops(), registry)?;
+		}
+		Ok(self.redo(registry)?)
+	}
+
+	/// Like [`Self::undo`], but first checks that every operation's target can be resolved in
+	/// `registry`, so that actions whose targets no longer exist are reported rather than partly
+	/// reverted.
+	///
+	/// # Errors
+	/// * Returns `ResolveError::Missing` with the first target that cannot be resolved. Nothing is
+	///   undone in this case.
+	/// * Returns `ResolveError::History` with the errors of [`Self::undo`].
+	pub fn undo_resolved<R>(&mut self, registry: &mut R) -> Result<(), ResolveError<Id>>
+	where
+		R: TargetResolver<Id>,
+		Op: Operation<R::Target>,
+		Id: Clone,
+	{
+		if let Some(action) = self.undo_action() {
+			check_targets(action.revert_ops(), registry)?;
+		}
+		Ok(self.undo(registry)?)
+	}
+}
+
+fn check_targets<Id: Clone, Op, R: TargetResolver<Id>>(
+	ops: &[OnTarget<Id, Op>],
+	registry: &mut R,
+) -> Result<(), ResolveError<Id>> {
+	match ops.iter().find(|op| registry.resolve(&op.target).is_none()) {
+		Some(op) => Err(ResolveError::Missing(op.target.clone())),
+		None => Ok(()),
+	}
+}
+
+/// An error indicating an issue with undoing or redoing an action whose operations are resolved
+/// against a [`TargetResolver`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResolveError<Id> {
+	/// The history itself could not perform the request.
+	History(UndoRedoError),
+	/// The target with the given ID no longer exists.
+	Missing(Id),
+}
+
+impl<Id> From<UndoRedoError> for ResolveError<Id> {
+	fn from(value: UndoRedoError) -> Self {
+		Self::History(value)
+	}
+}
+
+impl<Id: fmt::Debug> fmt::Display for ResolveError<Id> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::History(e) => write!(f, "{e}"),
+			Self::Missing(id) => write!(f, "target {id:?} no longer exists"),
+		}
+	}
+}
+
+impl<Id: fmt::Debug> error::Error for ResolveError<Id> {
+	fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+		match self {
+			Self::History(e) => Some(e),
+			Self::Missing(_) => None,
+		}
+	}
+}
+
+/// A lightweight reference to an object in a [`TargetRegistry`].
+///
+/// Unlike a reference, a handle can be stored in operations indefinitely, and saved alongside
+/// them (see [`Self::to_bits`]). Once its object is removed, the handle never resolves to
+/// anything again, even if another object takes its place, unless the object is put back with
+/// [`TargetRegistry::restore`].
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Handle {
+	index: u32,
+	generation: u32,
+}
+
+impl Handle {
+	/// Returns this handle as a number, for saving it.
+	pub fn to_bits(self) -> u64 {
+		(u64::from(self.generation) << 32) | u64::from(self.index)
+	}
+
+	/// Returns the handle previously turned into `bits` by [`Self::to_bits`].
+	pub fn from_bits(bits: u64) -> Self {
+		Self {
+			// Truncation is intended, as each half holds one field.
+			index: bits as u32,
+			generation: (bits >> 32) as u32,
+		}
+	}
+}
+
+/// A collection of objects, each referred to by a [`Handle`], which operations can be resolved
+/// against at the time they are applied.
+///
+/// This lets operations refer to objects which may be removed and recreated, or which are only
+/// loaded after the operations are. Use [`OnTarget<Handle, Op>`](OnTarget) as the operation
+/// type, and [`UndoRedo::undo_resolved`] and [`UndoRedo::redo_resolved`] to find out when an
+/// object no longer exists.
+///
+/// Objects can be put back at the handles they had with [`Self::restore`], such as when
+/// rebuilding a registry from saved handles (see [`Handle::to_bits`]) and objects, or when undoing
+/// the removal of an object (see [`RegistryEdit`]).
+#[derive(Clone, Debug)]
+pub struct TargetRegistry<T> {
+	slots: Vec<Slot<T>>,
+	free: Vec<u32>,
+	len: usize,
+}
+
+#[derive(Clone, Debug)]
+struct Slot<T> {
+	/// The generation of the object in this slot, or of the last one if it's empty.
+	generation: u32,
+	/// The generation to give the next object inserted into this slot, which is higher than any
+	/// it has held. If every generation has been used, this is `None`, and the slot is retired
+	/// rather than reused.
+	next_generation: Option<u32>,
+	value: Option<T>,
+}
+
+// An empty registry holds no objects. As the `Default` derive macro assumes that we want a trait
+// bound on `T` no matter what, we have to manually implement `Default`.
+impl<T> Default for TargetRegistry<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<T> TargetRegistry<T> {
+	/// How far past the end of the registry the index of a handle given to [`Self::restore`] may
+	/// be. Restoring a handle makes room for every index up to its own, so this stops a corrupt
+	/// handle from making the registry allocate room for billions of objects.
+	pub const MAX_RESTORE_GAP: usize = 1 << 16;
+
+	/// Creates an empty registry.
+	pub const fn new() -> Self {
+		Self {
+			slots: Vec::new(),
+			free: Vec::new(),
+			len: 0,
+		}
+	}
+
+	/// Returns the number of objects in the registry.
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	/// Returns `true` if the registry holds no objects.
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	/// Adds `value` to the registry, returning a new handle to it.
+	///
+	/// # Panics
+	/// Panics if the registry already holds `u32::MAX` objects.
+	pub fn insert(&mut self, value: T) -> Handle {
+		self.len += 1;
+		if let Some(index) = self.free.pop()
+			&& let Some(slot) = self.slots.get_mut(index as usize)
+			&& let Some(generation) = slot.next_generation
+		{
+			slot.generation = generation;
+			slot.next_generation = generation.checked_add(1);
+			slot.value = Some(value);
+			return Handle { index, generation };
+		}
+
+		let index = u32::try_from(self.slots.len()).expect("registry should not be full");
+		self.slots.push(Slot {
+			generation: 0,
+			next_generation: Some(1),
+			value: Some(value),
+		});
+		Handle {
+			index,
+			generation: 0,
+		}
+	}
+
+	/// Removes and returns the object referred to by `handle`, or `None` if it no longer exists.
+	pub fn remove(&mut self, handle: Handle) -> Option<T> {
+		let slot = self
+			.slots
+			.get_mut(handle.index as usize)
+			.filter(|slot| slot.generation == handle.generation)?;
+		let value = slot.value.take()?;
+		// Once every generation of a slot has been used, it is retired rather than reused.
+		if slot.next_generation.is_some() {
+			self.free.push(handle.index);
+		}
+		self.len -= 1;
+		Some(value)
+	}
+
+	/// Puts `value` into the registry at `handle`, so that `handle` (which may have come from
+	/// another registry, or from [`Handle::from_bits`]) refers to it. This can bring back a handle
+	/// whose object was removed, but other handles to objects removed from the same place stay
+	/// invalid.
+	///
+	/// A registry only knows about the handles it has given out or had restored into it. When
+	/// rebuilding a registry from saved objects, handles to objects which were removed before
+	/// saving may come to refer to newly inserted objects.
+	///
+	/// Handles can be restored in any order, but each may be at most [`Self::MAX_RESTORE_GAP`]
+	/// places past the highest index restored or inserted so far. Restoring saved handles in order
+	/// of [`Handle::to_bits`] never reaches this limit.
+	///
+	/// # Errors
+	/// Returns `value` back if another object is already in the place `handle` refers to (whether
+	/// or not it has the same handle), or if `handle` is too far past the end of the registry.
+	///
+	/// # Panics
+	/// Panics if `handle` refers to the last possible place in the registry.
+	pub fn restore(&mut self, handle: Handle, value: T) -> Result<(), T> {
+		let index = handle.index as usize;
+		if index.saturating_sub(self.slots.len()) > Self::MAX_RESTORE_GAP {
+			return Err(value);
+		}
+		while self.slots.len() <= index {
+			let new = u32::try_from(self.slots.len()).expect("registry should not be full");
+			self.slots.push(Slot {
+				generation: 0,
+				next_generation: Some(0),
+				value: None,
+			});
+			self.free.push(new);
+		}
+		let Some(slot) = self.slots.get_mut(index) else {
+			return Err(value);
+		};
+		if slot.value.is_some() {
+			return Err(value);
+		}
+
+		let next_generation = handle.generation.checked_add(1);
+		slot.next_generation = slot
+			.next_generation
+			.zip(next_generation)
+			.map(|(a, b)| a.max(b));
+		slot.generation = handle.generation;
+		slot.value = Some(value);
+		self.free.retain(|&free| free != handle.index);
+		self.len += 1;
+		Ok(())
+	}
+
+	/// Returns `true` if the object referred to by `handle` still exists.
+	pub fn contains(&self, handle: Handle) -> bool {
+		self.get(handle).is_some()
+	}
+
+	/// Returns the object referred to by `handle`, or `None` if it no longer exists.
+	pub fn get(&self, handle: Handle) -> Option<&T> {
+		self.slots
+			.get(handle.index as usize)
+			.filter(|slot| slot.generation == handle.generation)?
+			.value
+			.as_ref()
+	}
+
+	/// Returns the object referred to by `handle` mutably, or `None` if it no longer exists.
+	pub fn get_mut(&mut self, handle: Handle) -> Option<&mut T> {
+		self.slots
+			.get_mut(handle.index as usize)
+			.filter(|slot| slot.generation == handle.generation)?
+			.value
+			.as_mut()
+	}
+
+	/// Returns an iterator over every object in the registry, along with its handle.
+	pub fn iter(&self) -> impl Iterator<Item = (Handle, &T)> {
+		(0..).zip(&self.slots).filter_map(|(index, slot)| {
+			let handle = Handle {
+				index,
+				generation: slot.generation,
+			};
+			slot.value.as_ref().map(|value| (handle, value))
+		})
+	}
+}
+
+impl<T> TargetResolver<Handle> for TargetRegistry<T> {
+	type Target = T;
+
+	fn resolve(&mut self, id: &Handle) -> Option<&mut T> {
+		self.get_mut(*id)
+	}
+}
+
+/// An operation which adds an object to a [`TargetRegistry`] at a known handle, or removes it, so
+/// that removing an object can be undone without invalidating the handles that refer to it.
+///
+/// Each variant holds the object, so that it can be inverted into the other.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RegistryEdit<T> {
+	/// Puts the object into the registry at the handle, as [`TargetRegistry::restore`] does. If
+	/// another object is already there, nothing happens.
+	Restore(Handle, T),
+	/// Removes the object at the handle from the registry.
+	Remove(Handle, T),
+}
+
+impl<T: Clone> RegistryEdit<T> {
+	/// Creates an operation which removes the object `handle` refers to from `registry`, and
+	/// which puts it back at the same handle when inverted. Returns `None` if `handle` doesn't
+	/// refer to an object in `registry`.
+	pub fn remove(registry: &TargetRegistry<T>, handle: Handle) -> Option<Self> {
+		registry
+			.get(handle)
+			.map(|value| Self::Remove(handle, value.clone()))
+	}
+}
+
+impl<T: Clone> Operation<TargetRegistry<T>> for RegistryEdit<T> {
+	fn apply(&self, item: &mut TargetRegistry<T>) {
+		match self {
+			Self::Restore(handle, value) => {
+				let _ = item.restore(*handle, value.clone());
+			}
+			Self::Remove(handle, _) => {
+				item.remove(*handle);
+			}
+		}
+	}
+}
+
+impl<T: Clone> InvertibleOperation<TargetRegistry<T>> for RegistryEdit<T> {
+	fn inverse(&self) -> Self {
+		match self {
+			Self::Restore(handle, value) => Self::Remove(*handle, value.clone()),
+			Self::Remove(handle, value) => Self::Restore(*handle, value.clone()),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::Action;
+
+	#[test]
+	fn restore_brings_back_removed_handle() {
+		let mut registry = TargetRegistry::default();
+		let first = registry.insert(1);
+		assert_eq!(registry.remove(first), Some(1));
+
+		let second = registry.insert(2);
+		assert_ne!(first, second);
+		assert_eq!(registry.restore(first, 1), Err(1));
+
+		registry.remove(second);
+		assert_eq!(registry.restore(first, 1), Ok(()));
+		assert_eq!(registry.get(first), Some(&1));
+		assert_eq!(registry.get(second), None);
+
+		// Later insertions don't reuse any handle this place has had.
+		registry.remove(first);
+		let third = registry.insert(3);
+		assert_ne!(third, first);
+		assert_ne!(third, second);
+	}
+
+	#[test]
+	fn restore_rebuilds_saved_registry() {
+		let mut registry = TargetRegistry::default();
+		let handles: Vec<_> = (0..4).map(|value| registry.insert(value)).collect();
+		registry.remove(handles[1]);
+
+		let saved: Vec<_> = registry
+			.iter()
+			.map(|(handle, &value)| (handle.to_bits(), value))
+			.collect();
+		let mut rebuilt = TargetRegistry::default();
+		for (bits, value) in saved {
+			assert_eq!(rebuilt.restore(Handle::from_bits(bits), value), Ok(()));
+		}
+
+		assert_eq!(rebuilt.len(), 3);
+		for (value, &handle) in (0..4).zip(&handles) {
+			assert_eq!(rebuilt.get(handle), registry.get(handle));
+			assert_eq!(rebuilt.get(handle).is_some(), value != 1);
+		}
+		let new = rebuilt.insert(4);
+		assert!(!handles[2..].contains(&new));
+	}
+
+	#[test]
+	fn undoing_removal_keeps_handle() {
+		let mut registry = TargetRegistry::default();
+		let handle = registry.insert(5);
+		let mut history = UndoRedo::<_>::default();
+
+		let edit = RegistryEdit::remove(&registry, handle).unwrap();
+		let mut action = Action::default();
+		action.add_invertible_operation(edit);
+		history.commit_action(action, &mut registry);
+		assert!(!registry.contains(handle));
+
+		history.undo(&mut registry).unwrap();
+		assert_eq!(registry.get(handle), Some(&5));
+		history.redo(&mut registry).unwrap();
+		assert!(!registry.contains(handle));
+	}
+
+	#[test]
+	fn restore_rejects_distant_handles() {
+		let mut registry = TargetRegistry::new();
+		let distant = Handle::from_bits(u64::from(u32::MAX - 1));
+		assert_eq!(registry.restore(distant, 1), Err(1));
+		assert!(registry.slots.is_empty());
+
+		let furthest = Handle::from_bits(TargetRegistry::<i32>::MAX_RESTORE_GAP as u64);
+		assert_eq!(registry.restore(furthest, 2), Ok(()));
+		assert_eq!(registry.get(furthest), Some(&2));
+		// Restoring in order of index is never limited.
+		let next = Handle::from_bits(furthest.to_bits() + 1);
+		assert_eq!(registry.restore(next, 3), Ok(()));
+	}
+}
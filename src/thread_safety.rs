@@ -0,0 +1,110 @@
+use alloc::{boxed::Box, rc::Rc, sync::Arc};
+
+use crate::{Action, ArcStorage, ArrayStorage, BoundedStorage, Operation, UndoRedo};
+
+/// An [`Operation`] which can be sent and shared between threads.
+///
+/// This is implemented for every such operation, and exists so that histories of trait objects
+/// can be moved between threads. For example, `UndoRedo<Box<dyn SyncOperation<For>>>` is `Send`
+/// and `Sync`, whereas `UndoRedo<Box<dyn Operation<For>>>` is neither.
+///
+/// # Examples
+/// A history of boxed operations can be moved between threads if they are `SyncOperation`s:
+/// ```
+/// use michis_undo_redo::SyncDynUndoRedo;
+///
+/// fn assert_send_sync<T: Send + Sync>() {}
+/// assert_send_sync::<SyncDynUndoRedo<String>>();
+/// ```
+///
+/// But not if they are only `Operation`s, as those may not be `Send`:
+/// ```compile_fail,E0277
+/// use michis_undo_redo::DynUndoRedo;
+///
+/// fn assert_send<T: Send>() {}
+/// assert_send::<DynUndoRedo<String>>();
+/// ```
+///
+/// Likewise, a history is only `Send` if its operations are:
+/// ```compile_fail,E0277
+/// use std::rc::Rc;
+///
+/// use michis_undo_redo::UndoRedo;
+///
+/// fn assert_send<T: Send>() {}
+/// assert_send::<UndoRedo<Rc<u8>>>();
+/// ```
+///
+/// And only `Sync` if its operations are:
+/// ```compile_fail,E0277
+/// use std::cell::Cell;
+///
+/// use michis_undo_redo::UndoRedo;
+///
+/// fn assert_sync<T: Sync>() {}
+/// assert_sync::<UndoRedo<Cell<u8>>>();
+/// ```
+pub trait SyncOperation<For>: Operation<For> + Send + Sync {}
+
+impl<For, Op: Operation<For> + Send + Sync + ?Sized> SyncOperation<For> for Op {}
+
+/// An undo-redo history of boxed operations, for when the operations can't all be listed in one
+/// enum (such as when they are provided by plugins).
+pub type DynUndoRedo<For> = UndoRedo<Box<dyn Operation<For>>>;
+
+/// Like [`DynUndoRedo`], but for operations which can be sent and shared between threads, so
+/// that the history can be too.
+pub type SyncDynUndoRedo<For> = UndoRedo<Box<dyn SyncOperation<For>>>;
+
+impl<For, Op: Operation<For> + ?Sized> Operation<For> for Box<Op> {
+	fn apply(&self, item: &mut For) {
+		(**self).apply(item);
+	}
+}
+
+impl<For, Op: Operation<For> + ?Sized> Operation<For> for Rc<Op> {
+	fn apply(&self, item: &mut For) {
+		(**self).apply(item);
+	}
+}
+
+impl<For, Op: Operation<For> + ?Sized> Operation<For> for Arc<Op> {
+	fn apply(&self, item: &mut For) {
+		(**self).apply(item);
+	}
+}
+
+// Compile-time checks of the guarantees documented on `UndoRedo`. These functions are never
+// called; they only need to type-check.
+const _: () = {
+	fn assert_send<T: Send>() {}
+	fn assert_sync<T: Sync>() {}
+
+	#[expect(dead_code, reason = "only exists to be type-checked")]
+	fn send_ops_make_send_histories<Op: Send>() {
+		assert_send::<Action<Op>>();
+		assert_send::<UndoRedo<Op>>();
+		assert_send::<UndoRedo<Op, BoundedStorage<Op>>>();
+		assert_send::<UndoRedo<Op, ArrayStorage<Op, 1>>>();
+	}
+
+	#[expect(dead_code, reason = "only exists to be type-checked")]
+	fn sync_ops_make_sync_histories<Op: Sync>() {
+		assert_sync::<Action<Op>>();
+		assert_sync::<UndoRedo<Op>>();
+		assert_sync::<UndoRedo<Op, BoundedStorage<Op>>>();
+		assert_sync::<UndoRedo<Op, ArrayStorage<Op, 1>>>();
+	}
+
+	#[expect(dead_code, reason = "only exists to be type-checked")]
+	fn send_sync_ops_make_send_sync_shared_histories<Op: Send + Sync>() {
+		assert_send::<UndoRedo<Op, ArcStorage<Op>>>();
+		assert_sync::<UndoRedo<Op, ArcStorage<Op>>>();
+	}
+
+	#[expect(dead_code, reason = "only exists to be type-checked")]
+	fn sync_operation_objects_make_send_sync_histories<For: 'static>() {
+		assert_send::<SyncDynUndoRedo<For>>();
+		assert_sync::<SyncDynUndoRedo<For>>();
+	}
+};
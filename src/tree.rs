@@ -0,0 +1,1250 @@
+use alloc::{
+	collections::{BTreeMap, BTreeSet, BinaryHeap},
+	string::{String, ToString},
+	vec::Vec,
+};
+use core::{
+	cmp::Reverse,
+	ops::{Bound, ControlFlow},
+	time::Duration,
+};
+use std::time::SystemTime;
+#[cfg(feature = "persist")]
+use std::{
+	fs::File,
+	io::{BufReader, Read, Write},
+	time::UNIX_EPOCH,
+};
+
+use crate::{Action, Operation, UndoRedo, UndoRedoError};
+#[cfg(feature = "persist")]
+use crate::{
+	PersistError, PersistOperation,
+	persist::{
+		Input, write_action, write_atomically, write_len, write_name, write_preamble, write_u64,
+	},
+};
+
+/// Identifies a node in an [`UndoTree`].
+///
+/// IDs are assigned in the order nodes are created, and are never reused by the same tree, so they
+/// remain valid for as long as the node exists. Comparing two IDs tells which node is older.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct NodeId(u64);
+
+impl NodeId {
+	/// Recreates an ID from a number previously returned by [`Self::get`], such as one recorded by
+	/// an external tool.
+	pub const fn new(id: u64) -> Self {
+		Self(id)
+	}
+
+	/// Returns this ID as a number.
+	pub fn get(self) -> u64 {
+		self.0
+	}
+}
+
+/// An undo-redo history which keeps every branch of history, rather than erasing unapplied actions
+/// whenever a new action is created.
+///
+/// Each node of the tree holds one [`Action`]. Creating an action after undoing adds a new child to
+/// the current node, leaving its other children (the "futures" that were undone) intact. The root
+/// of the tree is the point in history where no actions are applied, and is represented by `None`
+/// wherever a node is expected.
+#[derive(Debug)]
+pub struct UndoTree<Op> {
+	nodes: BTreeMap<NodeId, TreeNode<Op>>,
+	root: Branches,
+	/// The most recently applied node, or `None` if we're at the root.
+	current: Option<NodeId>,
+	next_id: u64,
+}
+
+/// A node in an [`UndoTree`].
+#[derive(Debug)]
+pub struct TreeNode<Op> {
+	action: Action<Op>,
+	parent: Option<NodeId>,
+	branches: Branches,
+	created: SystemTime,
+	visited: SystemTime,
+	/// The name of the branch which starts at this node, if one has been given.
+	branch_name: Option<String>,
+}
+
+/// The children of a node (or the root).
+#[derive(Debug, Default)]
+struct Branches {
+	/// In the order they were created.
+	children: Vec<NodeId>,
+	/// The child that redoing moves to. This is whichever child was most recently created or
+	/// undone from.
+	redo_child: Option<NodeId>,
+}
+
+// An empty tree does not contain any `Op`. As the `Default` derive macro assumes that we want a
+// trait bound on `Op` no matter what, we have to manually implement `Default`.
+impl<Op> Default for UndoTree<Op> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<Op> UndoTree<Op> {
+	/// Creates an empty undo tree.
+	pub const fn new() -> Self {
+		Self {
+			nodes: BTreeMap::new(),
+			root: Branches {
+				children: Vec::new(),
+				redo_child: None,
+			},
+			current: None,
+			next_id: 0,
+		}
+	}
+
+	/// Resets the undo tree to its default state.
+	pub fn clear_history(&mut self) {
+		*self = Self::new();
+	}
+
+	/// Returns the number of nodes in the tree.
+	pub fn len(&self) -> usize {
+		self.nodes.len()
+	}
+
+	/// Returns `true` if the tree has no nodes.
+	pub fn is_empty(&self) -> bool {
+		self.nodes.is_empty()
+	}
+
+	/// Returns the most recently applied node, or `None` if no actions are applied.
+	pub fn current(&self) -> Option<NodeId> {
+		self.current
+	}
+
+	/// Returns the node with the given ID, if it exists.
+	pub fn get(&self, id: NodeId) -> Option<&TreeNode<Op>> {
+		self.nodes.get(&id)
+	}
+
+	/// Returns an iterator over every node in the tree, in the order they were created.
+	pub fn iter(&self) -> impl Iterator<Item = (NodeId, &TreeNode<Op>)> {
+		self.nodes.iter().map(|(&id, node)| (id, node))
+	}
+
+	/// Returns the children of `node`, in the order they were created. If `node` is `None`, the
+	/// children of the root are returned.
+	pub fn children(&self, node: Option<NodeId>) -> &[NodeId] {
+		self.branches(node)
+			.map_or(&[], |branches| branches.children.as_slice())
+	}
+
+	/// Returns the node that would be applied by [`Self::redo`], if any.
+	pub fn redo_target(&self) -> Option<NodeId> {
+		self.redo_child(self.current)
+	}
+
+	/// Returns the child of `node` that redoing would apply if `node` were the current node. If
+	/// `node` is `None`, this is for the root.
+	pub fn redo_child(&self, node: Option<NodeId>) -> Option<NodeId> {
+		self.branches(node)?.redo_child
+	}
+
+	/// Creates a new action as a child of the current node, returning it so it can be filled with
+	/// undo/redo operations. It becomes the node applied by [`Self::redo`].
+	///
+	/// Unlike [`UndoRedo::create_action`], no actions are erased.
+	/// Any other children of the current node remain as alternate branches.
+	pub fn create_action(&mut self) -> &mut Action<Op> {
+		let id = NodeId(self.next_id);
+		self.next_id += 1;
+
+		let parent = self.current;
+		if let Some(branches) = self.branches_mut(parent) {
+			branches.children.push(id);
+			branches.redo_child = Some(id);
+		}
+
+		let now = SystemTime::now();
+		let node = self.nodes.entry(id).or_insert(TreeNode {
+			action: Action::default(),
+			parent,
+			branches: Branches::default(),
+			created: now,
+			visited: now,
+			branch_name: None,
+		});
+		&mut node.action
+	}
+
+	/// Adds an already-built action as a child of the current node, as [`Self::create_action`]
+	/// would, then applies it to `apply_to`.
+	pub fn commit_action<For>(&mut self, action: Action<Op>, apply_to: &mut For)
+	where
+		Op: Operation<For>,
+	{
+		*self.create_action() = action;
+		// An action was just created, so there is always something to redo.
+		let _ = self.redo(apply_to);
+	}
+
+	/// Applies the node returned by [`Self::redo_target`], making it the current node.
+	///
+	/// # Errors
+	/// Returns `UndoRedoError::NothingToDo` if there is nothing to apply (usually because the
+	/// current node has no children.)
+	pub fn redo<For>(&mut self, apply_to: &mut For) -> Result<(), UndoRedoError>
+	where
+		Op: Operation<For>,
+	{
+		let id = self.redo_target().ok_or(UndoRedoError::NothingToDo)?;
+		let node = self.nodes.get_mut(&id).ok_or(UndoRedoError::NothingToDo)?;
+		node.action.apply(apply_to);
+		node.visited = SystemTime::now();
+		self.current = Some(id);
+		Ok(())
+	}
+
+	/// Reverts the current node, making its parent the current node. Redoing afterwards will apply
+	/// the reverted node again.
+	///
+	/// # Errors
+	/// Returns `UndoRedoError::NothingToDo` if there is nothing to revert (that is, no actions are
+	/// applied.)
+	pub fn undo<For>(&mut self, apply_to: &mut For) -> Result<(), UndoRedoError>
+	where
+		Op: Operation<For>,
+	{
+		let id = self.current.ok_or(UndoRedoError::NothingToDo)?;
+		let node = self.nodes.get_mut(&id).ok_or(UndoRedoError::NothingToDo)?;
+		node.action.revert(apply_to);
+		node.visited = SystemTime::now();
+
+		let parent = node.parent;
+		self.current = parent;
+		if let Some(branches) = self.branches_mut(parent) {
+			branches.redo_child = Some(id);
+		}
+		Ok(())
+	}
+
+	/// Returns the branches which start at the current node: the alternate futures which can be
+	/// reached by redoing from here. Each branch is identified by the ID of its first node.
+	///
+	/// The branch that [`Self::redo`] would follow is given by [`Self::redo_target`].
+	pub fn branches_at_current(&self) -> &[NodeId] {
+		self.children(self.current)
+	}
+
+	/// Reverts and applies nodes along the shortest path through the tree until `node` is the
+	/// current node: first reverting up to the nearest node shared by both paths, then applying
+	/// down to `node`. If `node` is `None`, every applied action is reverted.
+	///
+	/// Afterwards, redoing from any node along the path follows the path again.
+	///
+	/// # Errors
+	/// Returns `UndoRedoError::OutOfBounds` if no node has the ID given by `node`. No actions are
+	/// applied or reverted in this case.
+	pub fn jump_to_node<For>(
+		&mut self,
+		node: Option<NodeId>,
+		apply_to: &mut For,
+	) -> Result<(), UndoRedoError>
+	where
+		Op: Operation<For>,
+	{
+		if node.is_some_and(|id| !self.nodes.contains_key(&id)) {
+			return Err(UndoRedoError::OutOfBounds);
+		}
+		self.travel_to(node, apply_to);
+		Ok(())
+	}
+
+	/// Switches to the branch starting at (or passing through) node `id`, by jumping to it as
+	/// [`Self::jump_to_node`] does. This can be used to switch to any branch, not just one starting
+	/// at the current node.
+	///
+	/// # Errors
+	/// See [`Self::jump_to_node`].
+	pub fn switch_branch<For>(
+		&mut self,
+		id: NodeId,
+		apply_to: &mut For,
+	) -> Result<(), UndoRedoError>
+	where
+		Op: Operation<For>,
+	{
+		self.jump_to_node(Some(id), apply_to)
+	}
+
+	/// Moves back through the tree by `steps` nodes in the order they were created, regardless of
+	/// which branch they're on, as Vim's `:earlier` does. If there are fewer than `steps` earlier
+	/// nodes, this moves to the root.
+	///
+	/// # Errors
+	/// Returns `UndoRedoError::NothingToDo` if no actions are applied.
+	pub fn earlier<For>(&mut self, steps: usize, apply_to: &mut For) -> Result<(), UndoRedoError>
+	where
+		Op: Operation<For>,
+	{
+		let current = self.current.ok_or(UndoRedoError::NothingToDo)?;
+		if steps == 0 {
+			return Ok(());
+		}
+		let target = self
+			.nodes
+			.range(..current)
+			.rev()
+			.nth(steps - 1)
+			.map(|(&id, _)| id);
+		self.travel_to(target, apply_to);
+		Ok(())
+	}
+
+	/// Moves forward through the tree by `steps` nodes in the order they were created, regardless
+	/// of which branch they're on, as Vim's `:later` does. If there are fewer than `steps` later
+	/// nodes, this moves to the newest node.
+	///
+	/// # Errors
+	/// Returns `UndoRedoError::NothingToDo` if the current node is the newest node.
+	pub fn later<For>(&mut self, steps: usize, apply_to: &mut For) -> Result<(), UndoRedoError>
+	where
+		Op: Operation<For>,
+	{
+		let start = self.current.map_or(Bound::Unbounded, Bound::Excluded);
+		let target = self
+			.nodes
+			.range((start, Bound::Unbounded))
+			.take(steps)
+			.last()
+			.map(|(&id, _)| id)
+			.ok_or(UndoRedoError::NothingToDo)?;
+		self.travel_to(Some(target), apply_to);
+		Ok(())
+	}
+
+	/// Moves to the newest node created at or before `time`, regardless of which branch it's on.
+	/// If every node was created after `time`, this moves to the root.
+	///
+	/// Combined with [`TreeNode::created`], this can be used to move by wall-clock time, like
+	/// Vim's `:earlier 10m`.
+	pub fn travel_to_time<For>(&mut self, time: SystemTime, apply_to: &mut For)
+	where
+		Op: Operation<For>,
+	{
+		let target = self
+			.nodes
+			.iter()
+			.rev()
+			.find(|(_, node)| node.created <= time)
+			.map(|(&id, _)| id);
+		self.travel_to(target, apply_to);
+	}
+
+	/// Reverts and applies nodes along the shortest path through the tree until `target` is the
+	/// current node (or until no actions are applied, if `target` is `None`).
+	fn travel_to<For>(&mut self, target: Option<NodeId>, apply_to: &mut For)
+	where
+		Op: Operation<For>,
+	{
+		let target_path = self.path_from_root(target);
+		let current_path = self.path_from_root(self.current);
+		let shared = shared_len(&target_path, &current_path);
+
+		// Every node along both paths exists, so none of these undos or redos can fail.
+		for _ in shared..current_path.len() {
+			let _ = self.undo(apply_to);
+		}
+		for &node in &target_path[shared..] {
+			if let Some(branches) = self.branches_mut(self.current) {
+				branches.redo_child = Some(node);
+			}
+			let _ = self.redo(apply_to);
+		}
+	}
+
+	/// Copies the action of node `id` (usually from another branch) and commits the copy as a child
+	/// of the current node, as [`Self::commit_action`] would. Returns the ID of the new node.
+	///
+	/// Before the copy is committed, `resolve` is called with it and the object it will be applied
+	/// to, so that it can be adjusted to fit the current state (such as when something it changes
+	/// no longer exists). If `resolve` returns `ControlFlow::Break`, nothing is committed and
+	/// `Ok(None)` is returned.
+	///
+	/// # Errors
+	/// Returns `UndoRedoError::OutOfBounds` if no node has the ID `id`.
+	pub fn cherry_pick<For>(
+		&mut self,
+		id: NodeId,
+		apply_to: &mut For,
+		resolve: impl FnOnce(&mut Action<Op>, &For) -> ControlFlow<()>,
+	) -> Result<Option<NodeId>, UndoRedoError>
+	where
+		Op: Operation<For> + Clone,
+	{
+		let mut action = self
+			.nodes
+			.get(&id)
+			.ok_or(UndoRedoError::OutOfBounds)?
+			.action
+			.clone();
+		if resolve(&mut action, apply_to).is_break() {
+			return Ok(None);
+		}
+		self.commit_action(action, apply_to);
+		Ok(self.current)
+	}
+
+	/// Copies the actions of another branch onto the current node, so work done on the wrong
+	/// branch can be recovered. Returns the IDs of the new nodes, in order.
+	///
+	/// The actions copied are those on the path to `tip` which are not also on the path to the
+	/// current node, starting from where the two paths diverge. Each copy is passed to `transform`
+	/// (along with the object it will be applied to) and then committed, as with
+	/// [`Self::cherry_pick`]. If `transform` returns `ControlFlow::Break`, that copy and all later
+	/// ones are skipped, while those already committed are kept.
+	///
+	/// # Errors
+	/// Returns `UndoRedoError::OutOfBounds` if no node has the ID `tip`.
+	pub fn rebase_branch<For>(
+		&mut self,
+		tip: NodeId,
+		apply_to: &mut For,
+		mut transform: impl FnMut(&mut Action<Op>, &For) -> ControlFlow<()>,
+	) -> Result<Vec<NodeId>, UndoRedoError>
+	where
+		Op: Operation<For> + Clone,
+	{
+		if !self.nodes.contains_key(&tip) {
+			return Err(UndoRedoError::OutOfBounds);
+		}
+		let branch_path = self.path_from_root(Some(tip));
+		let current_path = self.path_from_root(self.current);
+		let shared = shared_len(&branch_path, &current_path);
+		// Copied up front, as committing changes which nodes are on the current path.
+		let actions: Vec<Action<Op>> = branch_path[shared..]
+			.iter()
+			.filter_map(|id| self.nodes.get(id))
+			.map(|node| node.action.clone())
+			.collect();
+
+		let mut grafted = Vec::new();
+		for mut action in actions {
+			if transform(&mut action, apply_to).is_break() {
+				break;
+			}
+			self.commit_action(action, apply_to);
+			grafted.extend(self.current);
+		}
+		Ok(grafted)
+	}
+
+	/// Returns one path through the tree, flattened into a linear [`UndoRedo`] history of copies
+	/// of its actions, leaving out every other branch. The position in the new history matches the
+	/// current node. The tree itself is not changed.
+	///
+	/// The path runs from the root to `tip`. If `tip` is `None`, the path runs to the current
+	/// node, then continues down the branches [`Self::redo`] would follow.
+	///
+	/// # Errors
+	/// Returns `UndoRedoError::OutOfBounds` if no node has the ID `tip`, or if the current node is
+	/// not on the path to `tip` (switch to a node on the path first, using
+	/// [`Self::switch_branch`]).
+	pub fn linearize(&self, tip: Option<NodeId>) -> Result<UndoRedo<Op>, UndoRedoError>
+	where
+		Op: Clone,
+	{
+		let path = match tip {
+			Some(tip) if !self.nodes.contains_key(&tip) => return Err(UndoRedoError::OutOfBounds),
+			Some(tip) => self.path_from_root(Some(tip)),
+			None => {
+				let mut path = self.path_from_root(self.current);
+				let mut next = self.redo_target();
+				while let Some(id) = next {
+					path.push(id);
+					next = self.redo_child(Some(id));
+				}
+				path
+			}
+		};
+		let position = match self.current {
+			None => 0,
+			Some(current) => {
+				let index = path.iter().position(|&id| id == current);
+				index.ok_or(UndoRedoError::OutOfBounds)? + 1
+			}
+		};
+
+		let actions: Vec<Action<Op>> = path
+			.iter()
+			.filter_map(|id| self.nodes.get(id))
+			.map(|node| node.action.clone())
+			.collect();
+		let mut history = UndoRedo::with_empty_storage(actions);
+		history.tapehead = position;
+		Ok(history)
+	}
+
+	/// Names the branch which starts at node `id`, replacing any name it already had.
+	///
+	/// # Errors
+	/// Returns `UndoRedoError::OutOfBounds` if no node has the ID `id`.
+	pub fn set_branch_name(
+		&mut self,
+		id: NodeId,
+		name: impl ToString,
+	) -> Result<(), UndoRedoError> {
+		let node = self.nodes.get_mut(&id).ok_or(UndoRedoError::OutOfBounds)?;
+		node.branch_name = Some(name.to_string());
+		Ok(())
+	}
+
+	/// Removes the name of the branch which starts at node `id`, returning it.
+	pub fn clear_branch_name(&mut self, id: NodeId) -> Option<String> {
+		self.nodes.get_mut(&id)?.branch_name.take()
+	}
+
+	/// Returns the name of the branch which starts at node `id`, if it has one.
+	pub fn branch_name(&self, id: NodeId) -> Option<&str> {
+		self.nodes.get(&id)?.branch_name()
+	}
+
+	/// Returns an iterator over every named branch, as the ID of its first node and its name.
+	pub fn named_branches(&self) -> impl Iterator<Item = (NodeId, &str)> {
+		self.nodes
+			.iter()
+			.filter_map(|(&id, node)| Some((id, node.branch_name()?)))
+	}
+
+	/// Returns the branch with the given name, if any. If several branches share the name, the
+	/// oldest is returned.
+	pub fn find_branch(&self, name: &str) -> Option<NodeId> {
+		self.named_branches()
+			.find(|&(_, branch_name)| branch_name == name)
+			.map(|(id, _)| id)
+	}
+
+	/// Removes stale branches according to `strategy`, calling `on_evict` with each node removed
+	/// (children before their parents). Returns the number of nodes removed.
+	///
+	/// Nodes on the path from the root to the current node are never removed.
+	pub fn prune(
+		&mut self,
+		strategy: PruneStrategy,
+		mut on_evict: impl FnMut(NodeId, TreeNode<Op>),
+	) -> usize {
+		let current_path: BTreeSet<NodeId> =
+			self.path_from_root(self.current).into_iter().collect();
+
+		match strategy {
+			PruneStrategy::UnvisitedFor(duration) => {
+				let Some(cutoff) = SystemTime::now().checked_sub(duration) else {
+					return 0;
+				};
+				let latest = self.latest_visits();
+				self.prune_branches(&current_path, &mut on_evict, |children| {
+					children
+						.iter()
+						.filter(|child| latest.get(child).is_some_and(|&time| time < cutoff))
+						.copied()
+						.collect()
+				})
+			}
+			PruneStrategy::KeepRecent(count) => {
+				let latest = self.latest_visits();
+				self.prune_branches(&current_path, &mut on_evict, |children| {
+					let mut children = children.to_vec();
+					// Most recently visited first, with the branch leading to the current node
+					// before all others so that it is always kept.
+					children.sort_by_key(|child| {
+						Reverse((current_path.contains(child), latest.get(child).copied()))
+					});
+					children.split_off(count.max(1).min(children.len()))
+				})
+			}
+			PruneStrategy::MaxNodes(max) => {
+				let mut leaves: BinaryHeap<_> = self
+					.nodes
+					.iter()
+					.filter(|(id, node)| {
+						node.branches.children.is_empty() && !current_path.contains(id)
+					})
+					.map(|(&id, node)| Reverse((node.visited, id)))
+					.collect();
+
+				let mut removed = 0;
+				while self.nodes.len() > max {
+					let Some(Reverse((_, id))) = leaves.pop() else {
+						break;
+					};
+					let parent = self.nodes.get(&id).and_then(|node| node.parent);
+					removed += self.remove_subtree(id, &mut on_evict);
+
+					if let Some(parent) = parent.filter(|parent| !current_path.contains(parent))
+						&& let Some(node) = self.nodes.get(&parent)
+						&& node.branches.children.is_empty()
+					{
+						leaves.push(Reverse((node.visited, parent)));
+					}
+				}
+				removed
+			}
+		}
+	}
+
+	/// Walks the tree from the root, calling `select` with the children of each node (other than
+	/// those leading to the current node), and removing the branches it returns.
+	fn prune_branches(
+		&mut self,
+		current_path: &BTreeSet<NodeId>,
+		on_evict: &mut impl FnMut(NodeId, TreeNode<Op>),
+		mut select: impl FnMut(&[NodeId]) -> Vec<NodeId>,
+	) -> usize {
+		let mut stale = Vec::new();
+		let mut stack = Vec::from([None]);
+		while let Some(node) = stack.pop() {
+			let children = self.children(node);
+			let selected = select(children);
+			for &child in children {
+				if current_path.contains(&child) || !selected.contains(&child) {
+					stack.push(Some(child));
+				} else {
+					stale.push(child);
+				}
+			}
+		}
+
+		stale
+			.into_iter()
+			.map(|id| self.remove_subtree(id, on_evict))
+			.sum()
+	}
+
+	/// Returns, for each node, the latest time that it or any of its descendants was visited.
+	fn latest_visits(&self) -> BTreeMap<NodeId, SystemTime> {
+		let mut latest: BTreeMap<NodeId, SystemTime> = BTreeMap::new();
+		// Children always have greater IDs than their parents, so visiting nodes in reverse order
+		// sees every child before its parent.
+		for (&id, node) in self.nodes.iter().rev() {
+			let time = latest
+				.get(&id)
+				.map_or(node.visited, |&time| time.max(node.visited));
+			latest.insert(id, time);
+			if let Some(parent) = node.parent {
+				let entry = latest.entry(parent).or_insert(time);
+				*entry = (*entry).max(time);
+			}
+		}
+		latest
+	}
+
+	/// Removes node `id` and all of its descendants, returning how many nodes were removed.
+	fn remove_subtree(
+		&mut self,
+		id: NodeId,
+		on_evict: &mut impl FnMut(NodeId, TreeNode<Op>),
+	) -> usize {
+		let parent = self.nodes.get(&id).and_then(|node| node.parent);
+		if let Some(branches) = self.branches_mut(parent) {
+			branches.children.retain(|&child| child != id);
+			if branches.redo_child == Some(id) {
+				branches.redo_child = branches.children.last().copied();
+			}
+		}
+
+		let mut subtree = Vec::from([id]);
+		let mut index = 0;
+		while let Some(&node) = subtree.get(index) {
+			subtree.extend_from_slice(self.children(Some(node)));
+			index += 1;
+		}
+		let removed = subtree.len();
+		for node in subtree.into_iter().rev() {
+			if let Some(removed) = self.nodes.remove(&node) {
+				on_evict(node, removed);
+			}
+		}
+		removed
+	}
+
+	/// Returns the nodes from the root down to `node`, not including the root.
+	fn path_from_root(&self, node: Option<NodeId>) -> Vec<NodeId> {
+		let mut path = Vec::new();
+		let mut next = node;
+		while let Some(id) = next {
+			path.push(id);
+			next = self.nodes.get(&id).and_then(|node| node.parent);
+		}
+		path.reverse();
+		path
+	}
+
+	fn branches(&self, node: Option<NodeId>) -> Option<&Branches> {
+		match node {
+			None => Some(&self.root),
+			Some(id) => self.nodes.get(&id).map(|node| &node.branches),
+		}
+	}
+
+	fn branches_mut(&mut self, node: Option<NodeId>) -> Option<&mut Branches> {
+		match node {
+			None => Some(&mut self.root),
+			Some(id) => self.nodes.get_mut(&id).map(|node| &mut node.branches),
+		}
+	}
+}
+
+/// Returns how many nodes two paths from the root have in common.
+fn shared_len(a: &[NodeId], b: &[NodeId]) -> usize {
+	a.iter().zip(b).take_while(|(a, b)| a == b).count()
+}
+
+/// A strategy for removing stale branches from an [`UndoTree`], used by [`UndoTree::prune`].
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum PruneStrategy {
+	/// Removes every branch in which no node has been visited within the given duration.
+	UnvisitedFor(Duration),
+	/// Keeps at most the given number of branches at each node (but always at least one),
+	/// removing those whose nodes were visited least recently.
+	KeepRecent(usize),
+	/// Removes the least recently visited leaf nodes until no more than the given number of nodes
+	/// remain.
+	MaxNodes(usize),
+}
+
+impl<Op> TreeNode<Op> {
+	/// Returns the action held by this node.
+	pub fn action(&self) -> &Action<Op> {
+		&self.action
+	}
+
+	/// Returns this node's parent, or `None` if it is a child of the root.
+	pub fn parent(&self) -> Option<NodeId> {
+		self.parent
+	}
+
+	/// Returns this node's children, in the order they were created.
+	pub fn children(&self) -> &[NodeId] {
+		&self.branches.children
+	}
+
+	/// Returns when this node was created.
+	pub fn created(&self) -> SystemTime {
+		self.created
+	}
+
+	/// Returns when this node was last created, applied, or reverted.
+	pub fn last_visited(&self) -> SystemTime {
+		self.visited
+	}
+
+	/// Returns the name of the branch which starts at this node, if it has one.
+	pub fn branch_name(&self) -> Option<&str> {
+		self.branch_name.as_deref()
+	}
+}
+
+/// Bytes written at the start of every persisted undo tree.
+#[cfg(feature = "persist")]
+const TREE_MAGIC: [u8; 4] = *b"MURT";
+
+#[cfg(feature = "persist")]
+impl<Op: PersistOperation> UndoTree<Op> {
+	/// Saves the entire tree, including the current node and branch names, to the file at `path`.
+	///
+	/// If the file already exists, it is replaced, without ever leaving a partially written tree
+	/// (see [`UndoRedo::save_to_path`]).
+	///
+	/// # Errors
+	/// Returns `PersistError::Io` if the file could not be created or written to.
+	pub fn save_to_path(&self, path: impl AsRef<std::path::Path>) -> Result<(), PersistError> {
+		write_atomically(path.as_ref(), |writer| self.save_to_writer(writer))
+	}
+
+	/// Loads a tree previously saved with [`Self::save_to_path`].
+	///
+	/// # Errors
+	/// See [`UndoRedo::load_from_path`].
+	pub fn load_from_path(path: impl AsRef<std::path::Path>) -> Result<Self, PersistError> {
+		Self::load_from_reader(BufReader::new(File::open(path)?))
+	}
+
+	/// Writes the entire tree, including the current node and branch names, to `writer`.
+	///
+	/// # Errors
+	/// Returns `PersistError::Io` if writing fails.
+	pub fn save_to_writer(&self, mut writer: impl Write) -> Result<(), PersistError> {
+		writer.write_all(&self.encode_tree())?;
+		Ok(())
+	}
+
+	/// Reads a tree previously written with [`Self::save_to_writer`].
+	///
+	/// # Errors
+	/// See [`UndoRedo::load_from_path`].
+	pub fn load_from_reader(mut reader: impl Read) -> Result<Self, PersistError> {
+		let mut bytes = Vec::new();
+		reader.read_to_end(&mut bytes)?;
+		Self::decode_tree(&bytes)
+	}
+
+	fn encode_tree(&self) -> Vec<u8> {
+		let mut out = Vec::new();
+		write_preamble::<Op>(&mut out, TREE_MAGIC);
+		write_u64(&mut out, self.next_id);
+		write_node_id(&mut out, self.current);
+		write_node_id(&mut out, self.root.redo_child);
+		write_len(&mut out, self.nodes.len());
+
+		// Nodes are written in order of ID, so every parent is written before its children.
+		for (&id, node) in &self.nodes {
+			write_u64(&mut out, id.0);
+			write_node_id(&mut out, node.parent);
+			write_node_id(&mut out, node.branches.redo_child);
+			write_time(&mut out, node.created);
+			write_time(&mut out, node.visited);
+			write_name(&mut out, node.branch_name.as_deref());
+			write_action(&mut out, &node.action);
+		}
+		out
+	}
+
+	fn decode_tree(bytes: &[u8]) -> Result<Self, PersistError> {
+		let mut input = Input(bytes);
+		input.preamble::<Op>(TREE_MAGIC)?;
+
+		let mut tree = Self::new();
+		tree.next_id = input.u64()?;
+		let current = read_node_id(&mut input)?;
+		let root_redo_child = read_node_id(&mut input)?;
+		let count = input.len()?;
+
+		let mut redo_children = Vec::new();
+		for _ in 0..count {
+			let id = NodeId(input.u64()?);
+			let parent = read_node_id(&mut input)?;
+			let redo_child = read_node_id(&mut input)?;
+			let created = read_time(&mut input)?;
+			let visited = read_time(&mut input)?;
+			let branch_name = input.name()?;
+			let action = input.action()?;
+
+			// IDs must be unique and increasing, and every parent must have come before its child,
+			// which also rules out cycles.
+			if id.0 >= tree.next_id
+				|| tree
+					.nodes
+					.last_key_value()
+					.is_some_and(|(&last, _)| last >= id)
+			{
+				return Err(PersistError::Corrupt);
+			}
+			tree.branches_mut(parent)
+				.ok_or(PersistError::Corrupt)?
+				.children
+				.push(id);
+			tree.nodes.insert(
+				id,
+				TreeNode {
+					action,
+					parent,
+					branches: Branches::default(),
+					created,
+					visited,
+					branch_name,
+				},
+			);
+			redo_children.push((Some(id), redo_child));
+		}
+		redo_children.push((None, root_redo_child));
+
+		// Redo children can only be checked once every node has been read.
+		for (node, redo_child) in redo_children {
+			let branches = tree.branches_mut(node).ok_or(PersistError::Corrupt)?;
+			if redo_child.is_some_and(|child| !branches.children.contains(&child)) {
+				return Err(PersistError::Corrupt);
+			}
+			branches.redo_child = redo_child;
+		}
+		if current.is_some_and(|id| !tree.nodes.contains_key(&id)) || !input.0.is_empty() {
+			return Err(PersistError::Corrupt);
+		}
+		tree.current = current;
+
+		Ok(tree)
+	}
+}
+
+/// Writes an optional node ID, as one more than the ID, with 0 meaning `None`.
+#[cfg(feature = "persist")]
+fn write_node_id(out: &mut Vec<u8>, id: Option<NodeId>) {
+	write_u64(out, id.map_or(0, |id| id.0 + 1));
+}
+
+#[cfg(feature = "persist")]
+fn read_node_id(input: &mut Input<'_>) -> Result<Option<NodeId>, PersistError> {
+	Ok(input.u64()?.checked_sub(1).map(NodeId))
+}
+
+/// Writes a time as seconds and nanoseconds since the Unix epoch. Times before the epoch are
+/// written as the epoch itself.
+#[cfg(feature = "persist")]
+fn write_time(out: &mut Vec<u8>, time: SystemTime) {
+	let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+	write_u64(out, since_epoch.as_secs());
+	out.extend_from_slice(&since_epoch.subsec_nanos().to_le_bytes());
+}
+
+#[cfg(feature = "persist")]
+fn read_time(input: &mut Input<'_>) -> Result<SystemTime, PersistError> {
+	let secs = input.u64()?;
+	let nanos = input.u32()?;
+	if nanos >= 1_000_000_000 {
+		return Err(PersistError::Corrupt);
+	}
+	UNIX_EPOCH
+		.checked_add(Duration::new(secs, nanos))
+		.ok_or(PersistError::Corrupt)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[derive(Clone, Debug, PartialEq)]
+	struct Add(i32);
+
+	impl Operation<i32> for Add {
+		fn apply(&self, item: &mut i32) {
+			*item += self.0;
+		}
+	}
+
+	#[cfg(feature = "persist")]
+	impl PersistOperation for Add {
+		const SCHEMA_VERSION: u32 = 1;
+
+		fn encode(&self, out: &mut Vec<u8>) {
+			out.extend_from_slice(&self.0.to_le_bytes());
+		}
+
+		fn decode(bytes: &[u8]) -> Option<Self> {
+			Some(Self(i32::from_le_bytes(bytes.try_into().ok()?)))
+		}
+	}
+
+	fn add(value: i32) -> Action<Add> {
+		let mut action = Action::default();
+		action.add_redo_operation(Add(value));
+		action.add_undo_operation(Add(-value));
+		action
+	}
+
+	/// Returns a tree with the branches 1 -> 2 -> 3 and 1 -> 20, where 20 is current.
+	fn branched() -> (UndoTree<Add>, i32) {
+		let mut tree = UndoTree::new();
+		let mut target = 0;
+		for value in [1, 2, 3] {
+			tree.commit_action(add(value), &mut target);
+		}
+		tree.undo(&mut target).unwrap();
+		tree.undo(&mut target).unwrap();
+		tree.commit_action(add(20), &mut target);
+		(tree, target)
+	}
+
+	/// Returns the IDs of the nodes adding 1, 2, 3 and 20 to the tree made by [`branched`].
+	fn nodes(tree: &UndoTree<Add>) -> [NodeId; 4] {
+		let first = tree.children(None)[0];
+		let [second, twenty] = *tree.children(Some(first)) else {
+			panic!("the first node should have two branches");
+		};
+		[first, second, tree.children(Some(second))[0], twenty]
+	}
+
+	#[test]
+	fn new_actions_keep_undone_branches() {
+		let (mut tree, mut target) = branched();
+		let [first, second, _, twenty] = nodes(&tree);
+		assert_eq!(target, 21);
+		assert_eq!(tree.len(), 4);
+		assert_eq!(tree.children(Some(first)), [second, twenty]);
+		assert_eq!(tree.get(twenty).unwrap().parent(), Some(first));
+
+		tree.undo(&mut target).unwrap();
+		assert_eq!(tree.redo_target(), Some(twenty));
+		tree.redo(&mut target).unwrap();
+		assert_eq!((tree.current(), target), (Some(twenty), 21));
+		assert_eq!(tree.redo(&mut target), Err(UndoRedoError::NothingToDo));
+	}
+
+	#[test]
+	fn switching_branches_follows_the_tree() {
+		let (mut tree, mut target) = branched();
+		let [first, second, third, twenty] = nodes(&tree);
+
+		tree.undo(&mut target).unwrap();
+		assert_eq!(tree.branches_at_current(), [second, twenty]);
+		tree.switch_branch(third, &mut target).unwrap();
+		assert_eq!((tree.current(), target), (Some(third), 6));
+
+		// Redoing follows the branch that was last switched to.
+		tree.undo(&mut target).unwrap();
+		tree.undo(&mut target).unwrap();
+		assert_eq!(tree.redo_target(), Some(second));
+		tree.switch_branch(twenty, &mut target).unwrap();
+		assert_eq!(target, 21);
+		assert_eq!(tree.current(), Some(twenty));
+		assert_eq!(tree.redo_child(Some(first)), Some(twenty));
+	}
+
+	#[test]
+	fn names_branches() {
+		let (mut tree, _) = branched();
+		let [_, second, _, twenty] = nodes(&tree);
+
+		tree.set_branch_name(second, "draft").unwrap();
+		tree.set_branch_name(twenty, "final").unwrap();
+		assert_eq!(tree.branch_name(second), Some("draft"));
+		assert_eq!(tree.find_branch("final"), Some(twenty));
+		assert_eq!(
+			tree.named_branches().collect::<Vec<_>>(),
+			[(second, "draft"), (twenty, "final")]
+		);
+
+		assert_eq!(tree.clear_branch_name(second), Some(String::from("draft")));
+		assert_eq!(tree.find_branch("draft"), None);
+		assert_eq!(
+			tree.set_branch_name(NodeId(99), "missing"),
+			Err(UndoRedoError::OutOfBounds)
+		);
+	}
+
+	#[cfg(feature = "persist")]
+	#[test]
+	fn saves_and_loads_branches() {
+		let (mut tree, mut target) = branched();
+		let [_, second, third, twenty] = nodes(&tree);
+		tree.set_branch_name(second, "draft").unwrap();
+
+		let mut bytes = Vec::new();
+		tree.save_to_writer(&mut bytes).unwrap();
+		let mut loaded = UndoTree::<Add>::load_from_reader(bytes.as_slice()).unwrap();
+		assert_eq!(loaded.len(), 4);
+		assert_eq!(loaded.current(), Some(twenty));
+		assert_eq!(loaded.find_branch("draft"), Some(second));
+		assert_eq!(
+			loaded.get(third).unwrap().created(),
+			tree.get(third).unwrap().created()
+		);
+
+		loaded.switch_branch(third, &mut target).unwrap();
+		assert_eq!(target, 6);
+		// New nodes don't reuse the IDs of saved ones.
+		loaded.create_action();
+		assert_eq!(loaded.current(), Some(third));
+		assert_eq!(loaded.redo_target(), Some(NodeId(4)));
+
+		assert!(UndoTree::<Add>::load_from_reader(&bytes[..bytes.len() - 1]).is_err());
+	}
+
+	#[test]
+	fn prunes_branches_off_the_current_path() {
+		let (mut tree, _) = branched();
+		let [first, second, third, twenty] = nodes(&tree);
+
+		assert_eq!(
+			tree.prune(
+				PruneStrategy::UnvisitedFor(Duration::from_secs(3600)),
+				|_, _| {}
+			),
+			0
+		);
+
+		let mut evicted = Vec::new();
+		let removed = tree.prune(PruneStrategy::KeepRecent(1), |id, _| evicted.push(id));
+		assert_eq!(removed, 2);
+		// Children are evicted before their parents.
+		assert_eq!(evicted, [third, second]);
+		assert_eq!(tree.children(Some(first)), [twenty]);
+		assert_eq!(tree.current(), Some(twenty));
+	}
+
+	#[test]
+	fn pruning_never_removes_the_current_path() {
+		let (mut tree, mut target) = branched();
+		let [first, second, _, twenty] = nodes(&tree);
+
+		assert_eq!(tree.prune(PruneStrategy::MaxNodes(0), |_, _| {}), 2);
+		assert_eq!(tree.len(), 2);
+		assert_eq!(tree.children(Some(first)), [twenty]);
+		assert!(tree.get(second).is_none());
+
+		tree.undo(&mut target).unwrap();
+		assert_eq!(tree.redo_target(), Some(twenty));
+	}
+
+	#[test]
+	fn cherry_picks_from_other_branches() {
+		let (mut tree, mut target) = branched();
+		let [_, _, third, twenty] = nodes(&tree);
+
+		let skipped = tree.cherry_pick(third, &mut target, |_, _| ControlFlow::Break(()));
+		assert_eq!(skipped, Ok(None));
+		assert_eq!((tree.len(), target), (4, 21));
+
+		let picked = tree
+			.cherry_pick(third, &mut target, |action, &target| {
+				assert_eq!(target, 21);
+				action.add_redo_operation(Add(10));
+				action.add_undo_operation(Add(-10));
+				ControlFlow::Continue(())
+			})
+			.unwrap()
+			.unwrap();
+		assert_eq!(target, 34);
+		assert_eq!(tree.current(), Some(picked));
+		assert_eq!(tree.get(picked).unwrap().parent(), Some(twenty));
+		// The original is left as it was.
+		assert_eq!(tree.get(third).unwrap().action(), &add(3));
+
+		tree.undo(&mut target).unwrap();
+		assert_eq!(target, 21);
+		assert_eq!(
+			tree.cherry_pick(NodeId(99), &mut target, |_, _| ControlFlow::Continue(())),
+			Err(UndoRedoError::OutOfBounds)
+		);
+	}
+
+	#[test]
+	fn rebases_branch_onto_current_node() {
+		let (mut tree, mut target) = branched();
+		let [_, _, third, twenty] = nodes(&tree);
+
+		let grafted = tree
+			.rebase_branch(third, &mut target, |_, _| ControlFlow::Continue(()))
+			.unwrap();
+		assert_eq!(target, 26);
+		assert_eq!(grafted.len(), 2);
+		assert_eq!(tree.get(grafted[0]).unwrap().parent(), Some(twenty));
+		assert_eq!(tree.get(grafted[1]).unwrap().parent(), Some(grafted[0]));
+		assert_eq!(tree.current(), Some(grafted[1]));
+		assert_eq!(tree.len(), 6);
+	}
+
+	#[test]
+	fn rebasing_stops_at_break() {
+		let (mut tree, mut target) = branched();
+		let [_, _, third, twenty] = nodes(&tree);
+
+		let mut seen = Vec::new();
+		let grafted = tree
+			.rebase_branch(third, &mut target, |action, &target| {
+				seen.push(target);
+				if action == &add(3) {
+					ControlFlow::Break(())
+				} else {
+					ControlFlow::Continue(())
+				}
+			})
+			.unwrap();
+		assert_eq!(seen, [21, 23]);
+		assert_eq!(grafted.len(), 1);
+		assert_eq!(tree.get(grafted[0]).unwrap().parent(), Some(twenty));
+		assert_eq!(target, 23);
+
+		assert_eq!(
+			tree.rebase_branch(NodeId(99), &mut target, |_, _| ControlFlow::Continue(())),
+			Err(UndoRedoError::OutOfBounds)
+		);
+	}
+
+	#[test]
+	fn linearize_follows_current_branch() {
+		let (tree, target) = branched();
+		let history = tree.linearize(None).unwrap();
+
+		assert_eq!(history.position(), 2);
+		let mut replayed = 0;
+		history.replay_into(&mut replayed, |_, _| ControlFlow::Continue(()));
+		assert_eq!(replayed, target);
+		assert_eq!(tree.len(), 4);
+	}
+
+	#[test]
+	fn linearize_to_other_branch_keeps_tree() {
+		let (mut tree, mut target) = branched();
+		let first = tree.children(None)[0];
+		let second = tree.children(Some(first))[0];
+		let tip = tree.children(Some(second))[0];
+
+		assert_eq!(
+			tree.linearize(Some(tip)).err(),
+			Some(UndoRedoError::OutOfBounds)
+		);
+		assert_eq!(tree.len(), 4);
+
+		tree.switch_branch(tip, &mut target).unwrap();
+		let history = tree.linearize(Some(tip)).unwrap();
+		assert_eq!(history.position(), 3);
+		assert_eq!(target, 6);
+	}
+
+	#[test]
+	fn moves_through_nodes_in_creation_order() {
+		let (mut tree, mut target) = branched();
+		let [first, second, third, twenty] = nodes(&tree);
+
+		tree.earlier(1, &mut target).unwrap();
+		assert_eq!((tree.current(), target), (Some(third), 6));
+		tree.earlier(2, &mut target).unwrap();
+		assert_eq!((tree.current(), target), (Some(first), 1));
+		tree.later(1, &mut target).unwrap();
+		assert_eq!((tree.current(), target), (Some(second), 3));
+		tree.later(10, &mut target).unwrap();
+		assert_eq!((tree.current(), target), (Some(twenty), 21));
+		assert_eq!(tree.later(1, &mut target), Err(UndoRedoError::NothingToDo));
+
+		tree.earlier(10, &mut target).unwrap();
+		assert_eq!((tree.current(), target), (None, 0));
+		assert_eq!(
+			tree.earlier(1, &mut target),
+			Err(UndoRedoError::NothingToDo)
+		);
+	}
+
+	#[test]
+	fn travels_to_times() {
+		let (mut tree, mut target) = branched();
+		let [_, second, ..] = nodes(&tree);
+
+		tree.travel_to_time(std::time::UNIX_EPOCH, &mut target);
+		assert_eq!((tree.current(), target), (None, 0));
+		let created = tree.get(second).unwrap().created();
+		tree.travel_to_time(created, &mut target);
+		// Nodes created at the same instant as `second` are also included.
+		assert!(tree.current() >= Some(second));
+		tree.travel_to_time(SystemTime::now(), &mut target);
+		assert_eq!(target, 21);
+	}
+
+	#[test]
+	fn jumps_to_nodes_by_id() {
+		let (mut tree, mut target) = branched();
+		let [.., third, _] = nodes(&tree);
+
+		tree.jump_to_node(Some(NodeId::new(third.get())), &mut target)
+			.unwrap();
+		assert_eq!(target, 6);
+		tree.jump_to_node(None, &mut target).unwrap();
+		assert_eq!(target, 0);
+		assert_eq!(
+			tree.jump_to_node(Some(NodeId::new(99)), &mut target),
+			Err(UndoRedoError::OutOfBounds)
+		);
+		assert_eq!((tree.current(), target), (None, 0));
+	}
+}
@@ -0,0 +1,125 @@
+//! Checks that undoing, redoing, and jumping through a history don't allocate, as documented on
+//! `UndoRedo`.
+
+use core::{
+	alloc::{GlobalAlloc, Layout},
+	cell::Cell,
+};
+use std::alloc::System;
+
+use michis_undo_redo::{
+	ArcStorage, ArrayStorage, BoundedStorage, HistoryStorage, Operation, UndoRedo,
+};
+
+/// Counts the allocations made by each thread, so that tests running in parallel don't affect
+/// each other's counts.
+struct CountingAllocator;
+
+thread_local! {
+	static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+// SAFETY: Every call is forwarded to `System`, which upholds the `GlobalAlloc` contract.
+unsafe impl GlobalAlloc for CountingAllocator {
+	unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+		count_allocation();
+		// SAFETY: The caller upholds the contract of `GlobalAlloc::alloc`.
+		unsafe { System.alloc(layout) }
+	}
+
+	unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+		count_allocation();
+		// SAFETY: The caller upholds the contract of `GlobalAlloc::alloc_zeroed`.
+		unsafe { System.alloc_zeroed(layout) }
+	}
+
+	unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+		count_allocation();
+		// SAFETY: The caller upholds the contract of `GlobalAlloc::realloc`.
+		unsafe { System.realloc(ptr, layout, new_size) }
+	}
+
+	unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+		// SAFETY: The caller upholds the contract of `GlobalAlloc::dealloc`.
+		unsafe { System.dealloc(ptr, layout) }
+	}
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn count_allocation() {
+	// This can fail while the thread is being torn down, in which case there's nothing to count.
+	let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+}
+
+/// Returns the number of allocations `f` makes on this thread.
+fn allocations_during(f: impl FnOnce()) -> usize {
+	let before = ALLOCATIONS.with(Cell::get);
+	f();
+	ALLOCATIONS.with(Cell::get) - before
+}
+
+#[derive(Clone, Debug)]
+struct Add(i32);
+
+impl Operation<i32> for Add {
+	fn apply(&self, item: &mut i32) {
+		*item += self.0;
+	}
+}
+
+/// Fills `history` with named, skipped, and multi-operation actions, then checks that moving
+/// through it doesn't allocate.
+fn assert_navigation_does_not_allocate<S: HistoryStorage<Add>>(mut history: UndoRedo<Add, S>) {
+	let mut target = 0;
+	for value in 1..=4 {
+		history
+			.create_action()
+			.set_name(value)
+			.add_redo_operation(Add(value))
+			.add_redo_operation(Add(value))
+			.add_undo_operation(Add(-value))
+			.add_undo_operation(Add(-value));
+		history.redo(&mut target).unwrap();
+	}
+	history.set_skipped(1, true, &mut target).unwrap();
+	let len = history.position();
+
+	let allocations = allocations_during(|| {
+		history.undo(&mut target).unwrap();
+		history.redo(&mut target).unwrap();
+		history.jump_to(0, &mut target).unwrap();
+		history.jump_to(len, &mut target).unwrap();
+		history.undo(&mut target).unwrap();
+		history.jump_to(1, &mut target).unwrap();
+		history.redo(&mut target).unwrap();
+	});
+	assert_eq!(allocations, 0);
+	assert_eq!(target, 2);
+}
+
+#[test]
+fn vec_storage() {
+	assert_navigation_does_not_allocate(UndoRedo::<Add>::default());
+}
+
+#[test]
+fn bounded_storage() {
+	assert_navigation_does_not_allocate(UndoRedo::with_storage(BoundedStorage::new(8)));
+}
+
+#[test]
+fn array_storage() {
+	assert_navigation_does_not_allocate(UndoRedo::with_storage(ArrayStorage::<_, 8>::new()));
+}
+
+#[test]
+fn arc_storage() {
+	assert_navigation_does_not_allocate(UndoRedo::with_storage(ArcStorage::default()));
+}
+
+#[test]
+fn counts_allocations() {
+	assert_eq!(allocations_during(|| drop(Box::new(0))), 1);
+}